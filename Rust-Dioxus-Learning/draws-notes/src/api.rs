@@ -0,0 +1,318 @@
+//! The versioned REST api under `/api/v1`, for scripts that can't speak the server function
+//! wire format. It goes through the same `data` functions as the app, authenticated with a
+//! bearer token instead of the session cookie. `openapi.json` describes every route.
+
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use dioxus_fullstack::prelude::ServerFnError;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+use crate::{
+    auth, data,
+    error::NotesError,
+    model::{
+        note::{Note, NotePage, NoteSort},
+        notebook::Notebook,
+    },
+    NOTEBOOK_TABLE, NOTE_PAGE_SIZE, NOTE_TABLE, TAG_TABLE,
+};
+
+const OPENAPI: &str = include_str!("../openapi.json");
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/tokens", post(create_token).delete(revoke_token))
+        .route("/notebooks", get(list_notebooks).post(create_notebook))
+        .route("/notebooks/:id", put(rename_notebook))
+        .route("/notes", get(list_notes).post(create_note))
+        .route(
+            "/notes/:id",
+            get(read_note).put(update_note).delete(trash_note),
+        )
+}
+
+/// A failed request, sent back as `{"message": ..}` with the status picked from the
+/// `NotesError`. Conflicts also carry the note as it's saved now.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    error: NotesError,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<Note>,
+}
+
+impl ApiError {
+    fn unauthenticated() -> ApiError {
+        ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            error: NotesError::Unauthorized("missing or expired token".to_string()),
+        }
+    }
+}
+
+impl From<NotesError> for ApiError {
+    fn from(error: NotesError) -> Self {
+        let status = match &error {
+            NotesError::NotFound(_) => StatusCode::NOT_FOUND,
+            NotesError::Conflict(_) => StatusCode::CONFLICT,
+            NotesError::Validation(_) => StatusCode::BAD_REQUEST,
+            NotesError::Unauthorized(_) => StatusCode::FORBIDDEN,
+            NotesError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError { status, error }
+    }
+}
+
+impl From<ServerFnError> for ApiError {
+    fn from(error: ServerFnError) -> Self {
+        NotesError::from_server_fn_error(&error).into()
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let current = match &self.error {
+            NotesError::Conflict(note) => Some(note.as_ref().clone()),
+            _ => None,
+        };
+        let body = ErrorBody {
+            message: self.error.to_string(),
+            current,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+async fn require_token_user(headers: &HeaderMap) -> ApiResult<Thing> {
+    let token = auth::bearer_token_from(headers).ok_or_else(ApiError::unauthenticated)?;
+    auth::user_for_token(token)
+        .await?
+        .ok_or_else(ApiError::unauthenticated)
+}
+
+/// Ids go in paths with or without their table, `note:abc` and `abc` are the same note. An id
+/// from another table is treated as missing.
+fn record_id(table: &str, id: &str) -> ApiResult<String> {
+    match id.split_once(':') {
+        None => Ok(format!("{}:{}", table, id)),
+        Some((tb, _)) if tb == table => Ok(id.to_string()),
+        Some(_) => Err(NotesError::NotFound(id.to_string()).into()),
+    }
+}
+
+async fn openapi() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], OPENAPI)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogIn {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Token {
+    pub token: String,
+}
+
+/// `POST /tokens`, trades a username and password for a token. Tokens are sessions, so they
+/// expire like the app's do.
+pub async fn create_token(Json(log_in): Json<LogIn>) -> ApiResult<(StatusCode, Json<Token>)> {
+    let user = auth::check_credentials(&log_in.username, &log_in.password)
+        .await?
+        .ok_or(ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            error: NotesError::Unauthorized("wrong username or password".to_string()),
+        })?;
+    let token = auth::create_session(user).await?;
+    Ok((StatusCode::CREATED, Json(Token { token })))
+}
+
+/// `DELETE /tokens`, revokes the token the request was made with.
+pub async fn revoke_token(headers: HeaderMap) -> ApiResult<StatusCode> {
+    require_token_user(&headers).await?;
+    if let Some(token) = auth::bearer_token_from(&headers) {
+        auth::delete_session(token).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_notebooks(headers: HeaderMap) -> ApiResult<Json<Vec<Notebook>>> {
+    let user = require_token_user(&headers).await?;
+    Ok(Json(data::get_notebooks(&user).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotebookBody {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Created {
+    pub id: String,
+}
+
+pub async fn create_notebook(
+    headers: HeaderMap,
+    Json(body): Json<NotebookBody>,
+) -> ApiResult<(StatusCode, Json<Created>)> {
+    let user = require_token_user(&headers).await?;
+    let id = data::upsert_notebook(
+        &user,
+        Notebook {
+            id: None,
+            name: body.name,
+            count: None,
+            role: None,
+            parent: None,
+            total: None,
+        },
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(Created { id })))
+}
+
+pub async fn rename_notebook(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<NotebookBody>,
+) -> ApiResult<StatusCode> {
+    let user = require_token_user(&headers).await?;
+    data::upsert_notebook(
+        &user,
+        Notebook {
+            id: Some(record_id(NOTEBOOK_TABLE, &id)?),
+            name: body.name,
+            count: None,
+            role: None,
+            parent: None,
+            total: None,
+        },
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct NotesQuery {
+    pub notebook: Option<String>,
+    /// the notes of the notebooks nested under `notebook` too
+    #[serde(default)]
+    pub children: bool,
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub sort: NoteSort,
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// `GET /notes`, a page of summaries like the notes bar shows, so `content` is only a preview.
+pub async fn list_notes(
+    Query(query): Query<NotesQuery>,
+    headers: HeaderMap,
+) -> ApiResult<Json<NotePage>> {
+    let user = require_token_user(&headers).await?;
+    let notebook = query
+        .notebook
+        .map(|id| record_id(NOTEBOOK_TABLE, &id))
+        .transpose()?;
+    let page = data::get_note_summaries(
+        &user,
+        notebook,
+        query.children,
+        query.tag.map(|id| record_id(TAG_TABLE, &id)).transpose()?,
+        query.sort,
+        query.cursor,
+        query.limit.unwrap_or(NOTE_PAGE_SIZE),
+    )
+    .await?;
+    Ok(Json(page))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewNote {
+    pub notebook: String,
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+}
+
+pub async fn create_note(
+    headers: HeaderMap,
+    Json(body): Json<NewNote>,
+) -> ApiResult<(StatusCode, Json<Note>)> {
+    let user = require_token_user(&headers).await?;
+    let id = data::upsert_note(
+        &user,
+        Note {
+            title: body.title,
+            content: body.content,
+            ..Note::new(record_id(NOTEBOOK_TABLE, &body.notebook)?)
+        },
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(data::get_note(&user, id).await?)))
+}
+
+pub async fn read_note(Path(id): Path<String>, headers: HeaderMap) -> ApiResult<Json<Note>> {
+    let user = require_token_user(&headers).await?;
+    Ok(Json(
+        data::get_note(&user, record_id(NOTE_TABLE, &id)?).await?,
+    ))
+}
+
+/// What `PUT /notes/:id` takes. `version` has to be the one last read, same as in the app, and
+/// leaving out `notebook` keeps the note where it is.
+#[derive(Debug, Deserialize)]
+pub struct NoteUpdate {
+    pub notebook: Option<String>,
+    pub title: String,
+    pub content: String,
+    pub version: u64,
+}
+
+pub async fn update_note(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<NoteUpdate>,
+) -> ApiResult<Json<Note>> {
+    let user = require_token_user(&headers).await?;
+    let id = record_id(NOTE_TABLE, &id)?;
+    let notebook = match body.notebook {
+        Some(notebook) => record_id(NOTEBOOK_TABLE, &notebook)?,
+        None => data::get_note(&user, id.clone()).await?.notebook,
+    };
+    data::upsert_note(
+        &user,
+        Note {
+            id: Some(id.clone()),
+            title: body.title,
+            content: body.content,
+            version: body.version,
+            ..Note::new(notebook)
+        },
+    )
+    .await?;
+    Ok(Json(data::get_note(&user, id).await?))
+}
+
+/// `DELETE /notes/:id` moves the note to the trash, like deleting it in the app.
+pub async fn trash_note(Path(id): Path<String>, headers: HeaderMap) -> ApiResult<StatusCode> {
+    let user = require_token_user(&headers).await?;
+    data::delete_note(&user, record_id(NOTE_TABLE, &id)?).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashSet,
+    io::{Cursor, Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    error::NotesError,
+    model::{
+        export::{ExportFormat, ExportedNote, NotebookExport},
+        note::Note,
+        notebook::Notebook,
+    },
+};
+
+const FENCE: &str = "---";
+const MARKDOWN_EXTENSION: &str = ".md";
+
+/// The yaml block at the top of every exported markdown file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FrontMatter {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    notebook: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated_at: Option<String>,
+}
+
+pub fn file_name(notebook: &Notebook, format: ExportFormat) -> String {
+    format!("{}.{}", slug(&notebook.name), format.extension())
+}
+
+pub fn to_json(export: &NotebookExport) -> Result<Vec<u8>, NotesError> {
+    serde_json::to_vec_pretty(export).map_err(|e| NotesError::Db(e.to_string()))
+}
+
+pub fn from_json(bytes: &[u8]) -> Result<NotebookExport, NotesError> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| NotesError::Validation(format!("not a notebook export: {}", e)))
+}
+
+pub fn to_markdown_zip(export: &NotebookExport) -> Result<Vec<u8>, NotesError> {
+    let write_error = |e: zip::result::ZipError| NotesError::Db(e.to_string());
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let mut used = HashSet::new();
+
+    for exported in &export.notes {
+        // titles aren't unique, the file names have to be
+        let base = slug(&exported.note.title);
+        let mut name = format!("{}{}", base, MARKDOWN_EXTENSION);
+        let mut n = 1;
+        while !used.insert(name.clone()) {
+            n += 1;
+            name = format!("{} ({}){}", base, n, MARKDOWN_EXTENSION);
+        }
+
+        zip.start_file(name, FileOptions::default())
+            .map_err(write_error)?;
+        zip.write_all(to_markdown(exported, &export.notebook.name)?.as_bytes())
+            .map_err(|e| NotesError::Db(e.to_string()))?;
+    }
+
+    Ok(zip.finish().map_err(write_error)?.into_inner())
+}
+
+/// Reads every markdown file in the archive, wherever it sits. The notebook is named after
+/// the first front matter that has one, or the archive itself.
+pub fn from_markdown_zip(file_name: &str, bytes: &[u8]) -> Result<NotebookExport, NotesError> {
+    let read_error =
+        |e: zip::result::ZipError| NotesError::Validation(format!("not a zip archive: {}", e));
+    let mut zip = ZipArchive::new(Cursor::new(bytes)).map_err(read_error)?;
+    let mut notebook_name = None;
+    let mut notes = vec![];
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).map_err(read_error)?;
+        let path = entry.name().to_string();
+        if !entry.is_file() || !path.to_lowercase().ends_with(MARKDOWN_EXTENSION) {
+            continue;
+        }
+        let mut text = String::new();
+        entry
+            .read_to_string(&mut text)
+            .map_err(|_| NotesError::Validation(format!("{} isn't utf-8 text", path)))?;
+
+        let (front_matter, content) = from_markdown(&text)?;
+        if notebook_name.is_none() && !front_matter.notebook.trim().is_empty() {
+            notebook_name = Some(front_matter.notebook.clone());
+        }
+        let title = if front_matter.title.trim().is_empty() {
+            stem(&path).to_string()
+        } else {
+            front_matter.title
+        };
+
+        notes.push(ExportedNote {
+            note: Note {
+                title,
+                content,
+                ..Note::new("".to_string())
+            },
+            tags: front_matter.tags,
+            created_at: front_matter.created_at,
+            updated_at: front_matter.updated_at,
+        });
+    }
+
+    Ok(NotebookExport {
+        notebook: Notebook {
+            id: None,
+            name: notebook_name.unwrap_or_else(|| stem(file_name).to_string()),
+            count: None,
+            role: None,
+            parent: None,
+            total: None,
+        },
+        notes,
+    })
+}
+
+fn to_markdown(exported: &ExportedNote, notebook: &str) -> Result<String, NotesError> {
+    let front_matter = FrontMatter {
+        title: exported.note.title.clone(),
+        notebook: notebook.to_string(),
+        tags: exported.tags.clone(),
+        created_at: exported.created_at.clone(),
+        updated_at: exported.updated_at.clone(),
+    };
+    let yaml = serde_yaml::to_string(&front_matter).map_err(|e| NotesError::Db(e.to_string()))?;
+    Ok(format!(
+        "{}\n{}{}\n\n{}",
+        FENCE, yaml, FENCE, exported.note.content
+    ))
+}
+
+/// Splits a markdown file into its front matter and content. Files without front matter are
+/// all content.
+fn from_markdown(text: &str) -> Result<(FrontMatter, String), NotesError> {
+    let text = text.replace("\r\n", "\n");
+    let Some(rest) = text.strip_prefix(&format!("{}\n", FENCE)) else {
+        return Ok((FrontMatter::default(), text));
+    };
+    let Some(end) = rest.find(&format!("\n{}\n", FENCE)) else {
+        return Ok((FrontMatter::default(), text.clone()));
+    };
+
+    let front_matter = serde_yaml::from_str(&rest[..end])
+        .map_err(|e| NotesError::Validation(format!("bad front matter: {}", e)))?;
+    let content = &rest[end + FENCE.len() + 2..];
+    // to_markdown leaves a blank line after the fence
+    let content = content.strip_prefix('\n').unwrap_or(content);
+    Ok((front_matter, content.to_string()))
+}
+
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let slug = slug.trim();
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+fn stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name)
+}
@@ -0,0 +1,162 @@
+use std::{io, path::PathBuf};
+
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{auth, config, ATTACHMENT_TABLE, DB};
+
+lazy_static! {
+    static ref DIR: PathBuf = config::attachments_dir().expect("invalid attachments config");
+}
+
+/// Content types we're happy for the browser to render, everything else is a download.
+/// svg is left out on purpose, it can carry scripts.
+const INLINE_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("pdf", "application/pdf"),
+    ("txt", "text/plain"),
+];
+
+pub fn mime_for(file_name: &str) -> &'static str {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    INLINE_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, mime)| *mime)
+        .unwrap_or("application/octet-stream")
+}
+
+pub fn hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// split on the first two characters so no one directory ends up with every file
+pub fn path_for(hash: &str) -> PathBuf {
+    DIR.join(&hash[..2]).join(hash)
+}
+
+fn is_hash(text: &str) -> bool {
+    text.len() == 64 && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Writes `bytes` under their hash, unless a file with the same contents is already there.
+pub async fn store(bytes: &[u8]) -> io::Result<String> {
+    let hash = hash(bytes);
+    let path = path_for(&hash);
+    if tokio::fs::try_exists(&path).await? {
+        return Ok(hash);
+    }
+
+    tokio::fs::create_dir_all(path.parent().expect("attachment paths have a parent")).await?;
+    // written next to the final name first, so a half written file is never served
+    let partial = path.with_extension("partial");
+    tokio::fs::write(&partial, bytes).await?;
+    tokio::fs::rename(&partial, &path).await?;
+    Ok(hash)
+}
+
+/// Deletes the files for `hashes` that no attachment refers to anymore.
+pub async fn remove_orphans(hashes: Vec<String>) -> surrealdb::Result<()> {
+    let con = DB.get().await;
+    for hash in hashes {
+        let used: Vec<String> = con
+            .query("SELECT VALUE hash FROM type::table($table) WHERE hash = $hash LIMIT 1;")
+            .bind(("table", ATTACHMENT_TABLE))
+            .bind(("hash", hash.clone()))
+            .await?
+            .take(0)?;
+        if !used.is_empty() {
+            continue;
+        }
+        if let Err(e) = tokio::fs::remove_file(path_for(&hash)).await {
+            if e.kind() != io::ErrorKind::NotFound {
+                log::error!("error removing attachment {} {:?}", hash, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct AttachmentRow {
+    name: String,
+    mime: String,
+}
+
+/// `GET /attachments/:hash`, the file behind an attachment on any note the session's user
+/// can see. This is what the markdown preview's images point at.
+pub async fn serve(Path(hash): Path<String>, headers: HeaderMap) -> Result<Response, StatusCode> {
+    let token = auth::session_token_from(&headers).ok_or(StatusCode::UNAUTHORIZED)?;
+    let user = match auth::user_for_token(token).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            log::error!("error checking attachment session {:?}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    if !is_hash(&hash) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let notebooks = auth::accessible_notebooks(&user).await.map_err(|e| {
+        log::error!("error getting attachment notebooks {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let con = DB.get().await;
+    let db_error = |e: surrealdb::Error| {
+        log::error!("error finding attachment {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    let rows: Vec<AttachmentRow> = con
+        .query("SELECT name, mime FROM type::table($table) WHERE hash = $hash AND note.notebook IN $notebooks LIMIT 1;")
+        .bind(("table", ATTACHMENT_TABLE))
+        .bind(("hash", hash.clone()))
+        .bind(("notebooks", notebooks))
+        .await
+        .map_err(db_error)?
+        .take(0)
+        .map_err(db_error)?;
+    let row = rows.into_iter().next().ok_or(StatusCode::NOT_FOUND)?;
+
+    let bytes = tokio::fs::read(path_for(&hash)).await.map_err(|e| {
+        log::error!("error reading attachment {} {:?}", hash, e);
+        StatusCode::NOT_FOUND
+    })?;
+
+    let disposition = if row.mime == "application/octet-stream" {
+        format!("attachment; filename=\"{}\"", row.name.replace(['"', '\\'], "_"))
+    } else {
+        "inline".to_string()
+    };
+    let mut response = bytes.into_response();
+    let response_headers = response.headers_mut();
+    let mut set = |name, value: &str| {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response_headers.insert(name, value);
+        }
+    };
+    set(header::CONTENT_TYPE, &row.mime);
+    set(header::CONTENT_DISPOSITION, &disposition);
+    set(header::X_CONTENT_TYPE_OPTIONS, "nosniff");
+    // the same hash is always the same bytes
+    set(header::CACHE_CONTROL, "private, max-age=31536000, immutable");
+    Ok(response)
+}
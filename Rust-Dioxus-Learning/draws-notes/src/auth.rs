@@ -0,0 +1,276 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use dioxus_fullstack::prelude::*;
+use rand::{rngs::OsRng, RngCore};
+use serde::Deserialize;
+use std::str::FromStr;
+use surrealdb::sql::Thing;
+
+use crate::{error::NotesError, model::share::Role, DB, NOTEBOOK_TABLE, NOTE_TABLE};
+
+pub const SESSION_COOKIE: &str = "draws_session";
+const SESSION_DAYS: u32 = 30;
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+pub fn hash_password(password: &str) -> Result<String, ServerFnError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| ServerFnError::ServerError("couldn't hash password".to_string()))
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+fn new_session_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Pulls the session token out of the request's cookie header, if there is one.
+fn session_token() -> Option<String> {
+    let cx = server_context();
+    let parts = cx.request_parts().ok()?;
+    session_token_from(&parts.headers)
+}
+
+/// Same as `session_token` for handlers that run outside of a server function and only have
+/// the headers, like the live event stream.
+pub fn session_token_from(headers: &http::HeaderMap) -> Option<String> {
+    headers
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// The token from an `Authorization: Bearer` header, which is how api clients send it.
+pub fn bearer_token_from(headers: &http::HeaderMap) -> Option<String> {
+    headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn set_cookie(value: String) {
+    let cx = server_context();
+    let (Ok(value), Ok(mut parts)) = (
+        http::HeaderValue::from_str(&value),
+        cx.response_parts_mut(),
+    ) else {
+        return;
+    };
+    parts.headers.append(http::header::SET_COOKIE, value);
+}
+
+/// Creates a session for `user` and returns its token. The api hands it out as a bearer token,
+/// the app as a cookie through `start_session`.
+pub async fn create_session(user: Thing) -> Result<String, ServerFnError> {
+    let con = DB.get().await;
+    let token = new_session_token();
+
+    con.query("CREATE type::thing('session', $session_token) SET user = $user, expires_at = time::now() + <duration> $ttl;")
+        .bind(("session_token", token.clone()))
+        .bind(("user", user))
+        .bind(("ttl", format!("{}d", SESSION_DAYS)))
+        .await
+        .map_err(NotesError::from)?
+        .check()
+        .map_err(NotesError::from)?;
+    Ok(token)
+}
+
+/// Creates a session for `user` and hands its token to the browser.
+pub async fn start_session(user: Thing) -> Result<(), ServerFnError> {
+    let token = create_session(user).await?;
+    set_cookie(format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+        SESSION_COOKIE,
+        token,
+        SESSION_DAYS * 24 * 60 * 60
+    ));
+    Ok(())
+}
+
+pub async fn delete_session(token: String) -> Result<(), ServerFnError> {
+    let con = DB.get().await;
+    con.query("DELETE type::thing('session', $session_token);")
+        .bind(("session_token", token))
+        .await
+        .map_err(NotesError::from)?;
+    Ok(())
+}
+
+pub async fn end_session() -> Result<(), ServerFnError> {
+    if let Some(token) = session_token() {
+        delete_session(token).await?;
+    }
+
+    set_cookie(format!(
+        "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+        SESSION_COOKIE
+    ));
+    Ok(())
+}
+
+/// The user behind the current request, or `None` when there's no live session.
+pub async fn current_user_id() -> Result<Option<Thing>, ServerFnError> {
+    match session_token() {
+        Some(token) => user_for_token(token).await,
+        None => Ok(None),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Credentials {
+    id: Thing,
+    password_hash: String,
+}
+
+/// The user with this username and password, `None` when either is wrong.
+pub async fn check_credentials(
+    username: &str,
+    password: &str,
+) -> Result<Option<Thing>, ServerFnError> {
+    let con = DB.get().await;
+    let res: Option<Credentials> = con
+        .query("SELECT id, password_hash FROM ONLY user WHERE username = $username LIMIT 1;")
+        .bind(("username", username.trim().to_string()))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    Ok(res
+        .filter(|user| verify_password(password, &user.password_hash))
+        .map(|user| user.id))
+}
+
+pub async fn user_for_token(token: String) -> Result<Option<Thing>, ServerFnError> {
+    let con = DB.get().await;
+    let users: Vec<Thing> = con
+        .query("SELECT VALUE user FROM type::thing('session', $session_token) WHERE expires_at > time::now();")
+        .bind(("session_token", token))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    Ok(users.into_iter().next())
+}
+
+/// Every server function that touches notes or notebooks starts here, the returned user is
+/// then checked with `require_role` against whatever is read or written.
+pub async fn require_user() -> Result<Thing, ServerFnError> {
+    current_user_id()
+        .await?
+        .ok_or(NotesError::Unauthorized("not logged in".to_string()).into())
+}
+
+/// Every notebook the user can see, their own plus the ones shared with them, leaving out
+/// the ones in the trash. Queries over notes filter on `notebook IN $notebooks` with this.
+pub async fn accessible_notebooks(user: &Thing) -> Result<Vec<Thing>, ServerFnError> {
+    let con = DB.get().await;
+    let mut res = con
+        .query("SELECT VALUE id FROM notebook WHERE owner = $user AND deleted_at IS NONE;")
+        .query("SELECT VALUE notebook FROM share WHERE user = $user AND notebook.deleted_at IS NONE;")
+        .bind(("user", user.clone()))
+        .await
+        .map_err(NotesError::from)?;
+
+    let mut notebooks: Vec<Thing> = res.take(0).map_err(NotesError::from)?;
+    let shared: Vec<Thing> = res.take(1).map_err(NotesError::from)?;
+    notebooks.extend(shared);
+    Ok(notebooks)
+}
+
+/// The part of `accessible_notebooks` the user can change notes in.
+pub async fn editable_notebooks(user: &Thing) -> Result<Vec<Thing>, ServerFnError> {
+    let con = DB.get().await;
+    let mut res = con
+        .query("SELECT VALUE id FROM notebook WHERE owner = $user AND deleted_at IS NONE;")
+        .query("SELECT VALUE notebook FROM share WHERE user = $user AND role = 'editor' AND notebook.deleted_at IS NONE;")
+        .bind(("user", user.clone()))
+        .await
+        .map_err(NotesError::from)?;
+
+    let mut notebooks: Vec<Thing> = res.take(0).map_err(NotesError::from)?;
+    let shared: Vec<Thing> = res.take(1).map_err(NotesError::from)?;
+    notebooks.extend(shared);
+    Ok(notebooks)
+}
+
+/// The user's role on `record`, which is either a notebook or a note. Notes inherit the role
+/// from the notebook they're in. A notebook in the trash is only its owner's. Any other kind
+/// of record has no role, so owning one doesn't pass for owning a notebook.
+pub async fn role_on(record: &str, user: &Thing) -> Result<Option<Role>, ServerFnError> {
+    let con = DB.get().await;
+    // an id that doesn't parse can't name anything the user has a role on
+    let Ok(record) = Thing::from_str(record) else {
+        return Ok(None);
+    };
+
+    let notebook = match record.tb.as_str() {
+        NOTE_TABLE => {
+            let notebooks: Vec<Thing> = con
+                .query("SELECT VALUE notebook FROM $record;")
+                .bind(("record", record))
+                .await
+                .map_err(NotesError::from)?
+                .take(0)
+                .map_err(NotesError::from)?;
+            match notebooks.into_iter().next() {
+                Some(notebook) => notebook,
+                None => return Ok(None),
+            }
+        }
+        NOTEBOOK_TABLE => record,
+        _ => return Ok(None),
+    };
+
+    let mut res = con
+        .query("SELECT VALUE id FROM $notebook WHERE owner = $user;")
+        .query("SELECT VALUE role FROM share WHERE notebook = $notebook AND user = $user AND notebook.deleted_at IS NONE;")
+        .bind(("notebook", notebook))
+        .bind(("user", user.clone()))
+        .await
+        .map_err(NotesError::from)?;
+
+    let owned: Vec<Thing> = res.take(0).map_err(NotesError::from)?;
+    if !owned.is_empty() {
+        return Ok(Some(Role::Owner));
+    }
+    let shared: Vec<Role> = res.take(1).map_err(NotesError::from)?;
+    Ok(shared.into_iter().max())
+}
+
+/// Errors out unless the user has at least `min` on `record`. Records they can't see at all
+/// get the same error as missing ones so ids can't be probed.
+pub async fn require_role(record: &str, user: &Thing, min: Role) -> Result<Role, ServerFnError> {
+    match role_on(record, user).await? {
+        Some(role) if role >= min => Ok(role),
+        Some(_) => Err(NotesError::Unauthorized(format!(
+            "{} needs the {} role",
+            record,
+            min.label()
+        ))
+        .into()),
+        None => Err(NotesError::NotFound(record.to_string()).into()),
+    }
+}
@@ -0,0 +1,116 @@
+use dioxus::prelude::*;
+use dioxus_fullstack::prelude::*;
+
+use crate::{
+    error::NotesError,
+    get_backlinks,
+    model::{backlink::Backlink, note::Note},
+    rewrite_backlinks,
+};
+
+/// The notes linking to this one with `[[Title]]`. Once the note has been renamed and saved,
+/// it offers to point the links still using an old title at the new one.
+#[component]
+pub fn Backlinks<'a>(
+    cx: Scope,
+    note_id: String,
+    title: UseState<String>,
+    saved: bool,
+    selected_note: UseState<Option<Note>>,
+    note_summaries: &'a UseFuture<Result<Vec<Note>, ServerFnError>>,
+    read_only: bool,
+) -> Element {
+    let note_summaries = *note_summaries;
+    let backlinks: &UseFuture<Result<Vec<Backlink>, ServerFnError>> =
+        use_future(cx, (note_id), get_backlinks);
+    let error: &UseState<Option<NotesError>> = use_state(cx, || None);
+
+    let list = match backlinks.value() {
+        Some(Ok(list)) => list,
+        Some(Err(e)) => {
+            log::error!("error getting backlinks {:?}", e);
+            return None;
+        }
+        None => return None,
+    };
+    if list.is_empty() {
+        return None;
+    }
+    let stale = list
+        .iter()
+        .filter(|backlink| backlink.is_stale(title.get()))
+        .count();
+    let stale_message = if stale == 1 {
+        "1 note still links here by an old title".to_string()
+    } else {
+        format!("{} notes still link here by an old title", stale)
+    };
+
+    render! {
+        div {
+            class: "w-full flex flex-col gap-1",
+            div {
+                class: "w-full flex flex-row flex-wrap items-center gap-2 text-sm",
+                span {
+                    class: "text-gray-400",
+                    "Linked from"
+                },
+                for backlink in list {
+                    div {
+                        class: "rounded-md bg-gray-700 hover:bg-gray-600 px-2 cursor-default",
+                        onclick: move |_| {
+                            selected_note.set(Some(Note {
+                                id: Some(backlink.note.clone()),
+                                title: backlink.title.clone(),
+                                ..Note::new(backlink.notebook.clone())
+                            }))
+                        },
+                        "{backlink.title}"
+                    }
+                }
+            },
+            if stale > 0 && *saved && !*read_only {
+                rsx! {
+                    div {
+                        class: "w-full flex flex-row items-center gap-4 p-2 bg-gray-700 border border-yellow-600 rounded-md text-sm",
+                        div {
+                            class: "grow",
+                            "{stale_message}"
+                        },
+                        button {
+                            class: "px-4 py-1 bg-blue-500 hover:bg-blue-600 rounded-md",
+                            onclick: move |_| {
+                                cx.spawn({
+                                    to_owned!(note_id);
+                                    to_owned!(backlinks);
+                                    to_owned!(note_summaries);
+                                    to_owned!(error);
+                                    async move {
+                                        match rewrite_backlinks(note_id).await {
+                                            Ok(_) => {
+                                                error.set(None);
+                                                backlinks.restart();
+                                                note_summaries.restart();
+                                            }
+                                            Err(e) => error.set(Some(NotesError::from_server_fn_error(&e))),
+                                        }
+                                    }
+                                })
+                            },
+                            "Update links"
+                        }
+                    }
+                }
+            },
+            if let Some(message) = error.get() {
+                rsx! {
+                    div {
+                        class: "text-red-500",
+                        onclick: move |_| error.set(None),
+                        "{message}"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use dioxus::{html::input_data::keyboard_types::Key, prelude::*};
+use dioxus_fullstack::prelude::*;
+
+use crate::{
+    add_note, delete_note,
+    error::NotesError,
+    get_note, get_note_summaries,
+    model::{
+        note::{Note, NotePage, NoteSort},
+        notebook::Notebook,
+        palette::{fuzzy_filter, PaletteAction, PaletteItem},
+        share::Role,
+        shortcut::{ShortcutAction, Shortcuts},
+        tag::Tag,
+    },
+    move_notes, upsert_notebook, MAX_NOTE_PAGE_SIZE,
+};
+
+// nobody reads past the first few matches, they type another letter instead
+const RESULTS_SHOWN: usize = 12;
+
+/// What typing into the palette is for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PaletteMode {
+    /// fuzzy searching notebooks, notes and actions
+    Search,
+    /// naming a new notebook
+    NewNotebook,
+    /// picking the notebook the open note goes to
+    MoveNote,
+}
+
+/// Moves the open note to the trash.
+async fn delete_open_note(
+    selected_note: UseState<Option<Note>>,
+    note_summaries: UseFuture<Result<Vec<Note>, ServerFnError>>,
+    notebooks: UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    tags: UseFuture<Result<Vec<Tag>, ServerFnError>>,
+) -> Result<(), ServerFnError> {
+    let Some(id) = selected_note
+        .current()
+        .as_ref()
+        .as_ref()
+        .and_then(|note| note.id.clone())
+    else {
+        return Ok(());
+    };
+    delete_note(id).await?;
+    selected_note.set(None);
+    note_summaries.restart();
+    notebooks.restart();
+    tags.restart();
+    Ok(())
+}
+
+/// Moves the open note into `target` and follows it there. Edits the editor hasn't saved yet
+/// stay in its draft.
+async fn move_open_note(
+    target: Notebook,
+    selected_notebook: UseState<Option<Notebook>>,
+    selected_note: UseState<Option<Note>>,
+    note_summaries: UseFuture<Result<Vec<Note>, ServerFnError>>,
+    notebooks: UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+) -> Result<(), ServerFnError> {
+    let (Some(id), Some(target_id)) = (
+        selected_note
+            .current()
+            .as_ref()
+            .as_ref()
+            .and_then(|note| note.id.clone()),
+        target.id.clone(),
+    ) else {
+        return Ok(());
+    };
+    move_notes(vec![id.clone()], target_id).await?;
+    selected_notebook.set(Some(target));
+    // a different note as far as the editor is concerned, so it loads the new version
+    selected_note.set(Some(get_note(id).await?));
+    note_summaries.restart();
+    notebooks.restart();
+    Ok(())
+}
+
+/// The Ctrl+K palette. Fuzzy searches every notebook, every note and the actions that make
+/// sense right now, and runs whichever is picked with Enter or a click.
+#[component]
+pub fn CommandPalette<'a>(
+    cx: Scope,
+    notebooks: &'a UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    note_summaries: &'a UseFuture<Result<Vec<Note>, ServerFnError>>,
+    tags: &'a UseFuture<Result<Vec<Tag>, ServerFnError>>,
+    selected_notebook: UseState<Option<Notebook>>,
+    selected_note: UseState<Option<Note>>,
+    palette_open: UseState<bool>,
+    shortcuts: Shortcuts,
+) -> Element {
+    let notebooks = *notebooks;
+    let note_summaries = *note_summaries;
+    let tags = *tags;
+    let query: &UseState<String> = use_state(cx, || "".to_string());
+    let highlighted: &UseState<usize> = use_state(cx, || 0);
+    let mode = use_state(cx, || PaletteMode::Search);
+    let error: &UseState<Option<NotesError>> = use_state(cx, || None);
+    // every note the user can see, not only the ones in the notes bar
+    let all_notes: &UseFuture<Result<NotePage, ServerFnError>> = use_future(cx, (), |_| {
+        get_note_summaries(
+            None,
+            false,
+            None,
+            NoteSort::Updated,
+            None,
+            MAX_NOTE_PAGE_SIZE,
+        )
+    });
+
+    let notebook_list: Vec<Notebook> = match notebooks.value() {
+        Some(Ok(list)) => list.clone(),
+        _ => vec![],
+    };
+    let open_note = selected_note.get().clone();
+    let note_editable = open_note.as_ref().is_some_and(|note| {
+        notebook_list
+            .iter()
+            .any(|nb| nb.id.as_ref() == Some(&note.notebook) && nb.can_edit())
+    });
+
+    let items = match mode.get() {
+        PaletteMode::Search => {
+            let mut items: Vec<PaletteItem> = PaletteAction::ALL
+                .into_iter()
+                .filter(|action| match action {
+                    PaletteAction::NewNote => selected_notebook
+                        .get()
+                        .as_ref()
+                        .is_some_and(|nb| nb.id.is_some() && nb.can_edit()),
+                    PaletteAction::NewNotebook => true,
+                    PaletteAction::DeleteNote | PaletteAction::MoveNote => note_editable,
+                })
+                .map(PaletteItem::Action)
+                .collect();
+            items.extend(notebook_list.iter().cloned().map(PaletteItem::Notebook));
+            if let Some(Ok(page)) = all_notes.value() {
+                items.extend(page.notes.iter().cloned().map(PaletteItem::Note));
+            }
+            fuzzy_filter(query.get(), items)
+        }
+        PaletteMode::MoveNote => {
+            let targets = notebook_list
+                .iter()
+                .filter(|nb| {
+                    nb.can_edit() && nb.id.as_ref() != open_note.as_ref().map(|note| &note.notebook)
+                })
+                .cloned()
+                .map(PaletteItem::Notebook)
+                .collect();
+            fuzzy_filter(query.get(), targets)
+        }
+        PaletteMode::NewNotebook => vec![],
+    };
+    let items: Vec<PaletteItem> = items.into_iter().take(RESULTS_SHOWN).collect();
+    let highlighted_index = (*highlighted.get()).min(items.len().saturating_sub(1));
+
+    let rows: Vec<(usize, PaletteItem, String, String)> = items
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, item)| {
+            let hint = match &item {
+                PaletteItem::Action(PaletteAction::NewNote) => shortcuts
+                    .binding(ShortcutAction::NewNote)
+                    .map(|binding| binding.to_string())
+                    .unwrap_or_default(),
+                PaletteItem::Note(note) => notebook_list
+                    .iter()
+                    .find(|nb| nb.id.as_ref() == Some(&note.notebook))
+                    .map(|nb| nb.name.clone())
+                    .unwrap_or_default(),
+                item => item.kind().to_string(),
+            };
+            (i, item.clone(), item.label(), hint)
+        })
+        .collect();
+
+    let run = move |item: PaletteItem| {
+        highlighted.set(0);
+        match (*mode.get(), item) {
+            (PaletteMode::Search, PaletteItem::Action(PaletteAction::NewNotebook)) => {
+                query.set("".to_string());
+                mode.set(PaletteMode::NewNotebook);
+            }
+            (PaletteMode::Search, PaletteItem::Action(PaletteAction::MoveNote)) => {
+                query.set("".to_string());
+                mode.set(PaletteMode::MoveNote);
+            }
+            (PaletteMode::Search, PaletteItem::Notebook(notebook)) => {
+                selected_notebook.set(Some(notebook));
+                palette_open.set(false);
+            }
+            (PaletteMode::Search, PaletteItem::Note(note)) => {
+                // opened in its own notebook so the notes bar lists it too
+                if let Some(Ok(list)) = notebooks.value() {
+                    if let Some(notebook) = list
+                        .iter()
+                        .find(|nb| nb.id.as_ref() == Some(&note.notebook))
+                    {
+                        selected_notebook.set(Some(notebook.clone()));
+                    }
+                }
+                selected_note.set(Some(note));
+                palette_open.set(false);
+            }
+            (_, item) => cx.spawn({
+                to_owned!(notebooks);
+                to_owned!(note_summaries);
+                to_owned!(tags);
+                to_owned!(selected_notebook);
+                to_owned!(selected_note);
+                to_owned!(palette_open);
+                to_owned!(error);
+                async move {
+                    let res = match item {
+                        PaletteItem::Action(PaletteAction::NewNote) => {
+                            match selected_notebook.current().as_ref().clone() {
+                                Some(notebook) => {
+                                    add_note(
+                                        notebook,
+                                        None,
+                                        selected_note,
+                                        note_summaries,
+                                        notebooks,
+                                    )
+                                    .await
+                                }
+                                None => Ok(()),
+                            }
+                        }
+                        PaletteItem::Action(PaletteAction::DeleteNote) => {
+                            delete_open_note(selected_note, note_summaries, notebooks, tags).await
+                        }
+                        PaletteItem::Notebook(target) => {
+                            move_open_note(
+                                target,
+                                selected_notebook,
+                                selected_note,
+                                note_summaries,
+                                notebooks,
+                            )
+                            .await
+                        }
+                        _ => Ok(()),
+                    };
+                    match res {
+                        Ok(()) => palette_open.set(false),
+                        Err(e) => error.set(Some(NotesError::from_server_fn_error(&e))),
+                    }
+                }
+            }),
+        }
+    };
+
+    let create_notebook = move || {
+        let name = query.get().trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        cx.spawn({
+            to_owned!(notebooks);
+            to_owned!(selected_notebook);
+            to_owned!(palette_open);
+            to_owned!(error);
+            async move {
+                let notebook = Notebook {
+                    id: None,
+                    name,
+                    count: None,
+                    role: None,
+                    parent: None,
+                    total: None,
+                };
+                match upsert_notebook(notebook.clone()).await {
+                    Ok(id) => {
+                        notebooks.restart();
+                        selected_notebook.set(Some(Notebook {
+                            id: Some(id),
+                            count: Some(0),
+                            role: Some(Role::Owner),
+                            parent: None,
+                            total: None,
+                            ..notebook
+                        }));
+                        palette_open.set(false);
+                    }
+                    Err(e) => error.set(Some(NotesError::from_server_fn_error(&e))),
+                }
+            }
+        })
+    };
+
+    let item_count = items.len();
+    let onkeydown = move |evt: Event<KeyboardData>| match evt.key() {
+        Key::ArrowDown => {
+            highlighted.set((highlighted_index + 1).min(item_count.saturating_sub(1)))
+        }
+        Key::ArrowUp => highlighted.set(highlighted_index.saturating_sub(1)),
+        Key::Enter if *mode.get() == PaletteMode::NewNotebook => create_notebook(),
+        Key::Enter => {
+            if let Some(item) = items.get(highlighted_index) {
+                run(item.clone());
+            }
+        }
+        // backs out of naming or moving first, then closes
+        Key::Escape if *mode.get() != PaletteMode::Search => {
+            query.set("".to_string());
+            mode.set(PaletteMode::Search);
+        }
+        Key::Escape => palette_open.set(false),
+        _ => {}
+    };
+
+    let placeholder = match mode.get() {
+        PaletteMode::Search => "Search notebooks, notes and actions",
+        PaletteMode::NewNotebook => "Name the new notebook and press Enter",
+        PaletteMode::MoveNote => "Move the note to...",
+    };
+    const HIGHLIGHTED_STYLE: &str =
+        "flex flex-row items-center gap-2 px-4 py-1 bg-gray-700 cursor-default";
+    const ROW_STYLE: &str = "flex flex-row items-center gap-2 px-4 py-1 cursor-default";
+
+    render! {
+        div {
+            class: "fixed inset-0 bg-black/50 flex items-start justify-center pt-24 z-20",
+            onclick: move |_| palette_open.set(false),
+            div {
+                class: "bg-gray-800 border border-gray-600 rounded-md flex flex-col w-[36rem] max-h-[60%] overflow-hidden",
+                onclick: move |evt| evt.stop_propagation(),
+                input {
+                    class: "w-full p-2 bg-gray-800 border-b border-gray-600 focus:outline-none focus:ring-0",
+                    placeholder: placeholder,
+                    autofocus: true,
+                    value: "{query}",
+                    oninput: move |evt| {
+                        query.set(evt.value.clone());
+                        highlighted.set(0);
+                    },
+                    onkeydown: onkeydown,
+                },
+                div {
+                    class: "flex flex-col overflow-y-auto",
+                    for (i, item, label, hint) in rows {
+                        div {
+                            class: if i == highlighted_index { HIGHLIGHTED_STYLE } else { ROW_STYLE },
+                            onmouseenter: move |_| highlighted.set(i),
+                            onclick: move |_| run(item.clone()),
+                            div {
+                                class: "grow truncate",
+                                "{label}"
+                            },
+                            div {
+                                class: "text-gray-400 text-sm",
+                                "{hint}"
+                            }
+                        }
+                    }
+                },
+                if let Some(message) = error.get() {
+                    rsx! {
+                        div {
+                            class: "px-4 py-1 text-red-500",
+                            onclick: move |_| error.set(None),
+                            "{message}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
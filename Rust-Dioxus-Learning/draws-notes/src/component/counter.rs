@@ -2,7 +2,7 @@ use dioxus::{html::input_data::keyboard_types::Key, prelude::*};
 
 #[component]
 pub fn Counter(cx: Scope, count: u32) -> Element {
-    rsx! {
+    render! {
         div {
             class: "pr-2 flex items-center shrink",
             div {
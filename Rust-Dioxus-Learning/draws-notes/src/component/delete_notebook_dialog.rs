@@ -0,0 +1,125 @@
+use dioxus::prelude::*;
+use dioxus_fullstack::prelude::*;
+
+use crate::{delete_notebook, model::notebook::Notebook};
+
+#[component]
+pub fn DeleteNotebookDialog<'a>(
+    cx: Scope,
+    notebook: Notebook,
+    notebooks: &'a UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    selected_notebook: UseState<Option<Notebook>>,
+    deleting_notebook: UseState<Option<Notebook>>,
+) -> Element {
+    let notebooks: &UseFuture<Result<Vec<Notebook>, ServerFnError>> = notebooks;
+    // empty string means delete the notes, otherwise the id of the notebook to move them into
+    let move_target: &UseState<String> = use_state(cx, || "".to_string());
+    let error: &UseState<Option<String>> = use_state(cx, || None);
+
+    let count = notebook.count.unwrap_or(0);
+    let others: Vec<Notebook> = match notebooks.value() {
+        Some(Ok(list)) => list
+            .iter()
+            .filter(|nb| nb.id.is_some() && nb.id != notebook.id && nb.can_edit())
+            .cloned()
+            .collect(),
+        _ => vec![],
+    };
+
+    let confirm_label = if move_target.get().is_empty() {
+        "Move to Trash"
+    } else {
+        "Move notes and delete"
+    };
+
+    render! {
+        div {
+            class: "fixed inset-0 bg-black/50 flex items-center justify-center z-10",
+            div {
+                class: "bg-gray-800 border border-gray-600 rounded-md p-4 flex flex-col gap-4 w-96",
+                div {
+                    class: "text-xl",
+                    "Move \"{notebook.name}\" to the Trash?"
+                },
+                div {
+                    class: "text-gray-400",
+                    if count == 1 {
+                        rsx! { "This notebook has 1 note." }
+                    } else {
+                        rsx! { "This notebook has {count} notes." }
+                    }
+                },
+                if count > 0 {
+                    rsx! {
+                        select {
+                            class: "w-full p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                            value: "{move_target}",
+                            onchange: move |evt| {
+                                move_target.set(evt.value.clone())
+                            },
+                            option {
+                                value: "",
+                                "Move the notes to the Trash too"
+                            },
+                            for other in others.iter() {
+                                option {
+                                    value: "{other.id.clone().unwrap_or_default()}",
+                                    "Move the notes to {other.name}"
+                                }
+                            }
+                        }
+                    }
+                },
+                if let Some(error) = error.get() {
+                    rsx! {
+                        div {
+                            class: "text-red-500",
+                            "{error}"
+                        }
+                    }
+                },
+                div {
+                    class: "flex flex-row justify-end gap-2",
+                    button {
+                        class: "px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-md",
+                        onclick: move |_| {
+                            deleting_notebook.set(None);
+                        },
+                        "Cancel"
+                    },
+                    button {
+                        class: "px-4 py-2 bg-red-600 hover:bg-red-700 rounded-md",
+                        onclick: move |_| {
+                            cx.spawn({
+                                to_owned!(notebook);
+                                to_owned!(notebooks);
+                                to_owned!(selected_notebook);
+                                to_owned!(deleting_notebook);
+                                to_owned!(move_target);
+                                to_owned!(error);
+                                async move {
+                                    let target = Some(move_target.current().to_string())
+                                        .filter(|target| !target.is_empty());
+                                    match delete_notebook(notebook.clone(), target).await {
+                                        Ok(()) => {
+                                            if selected_notebook.current().as_ref().as_ref().map(|nb| &nb.id) == Some(&notebook.id) {
+                                                selected_notebook.set(None);
+                                            }
+                                            deleting_notebook.set(None);
+                                            notebooks.restart();
+                                        }
+                                        Err(e) => {
+                                            log::error!("error deleting notebook {:?}", e);
+                                            error.set(Some("Couldn't delete the notebook".to_string()));
+                                        }
+                                    }
+                                }
+                            })
+                        },
+                        "{confirm_label}"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use dioxus::prelude::*;
+
+use crate::error::NotesError;
+
+/// Shown in place of whatever failed to load, with a way to try again.
+#[component]
+pub fn ErrorToast<'a>(
+    cx: Scope,
+    error: NotesError,
+    onretry: EventHandler<'a, MouseEvent>,
+) -> Element {
+    render! {
+        div {
+            class: "m-2 p-2 flex flex-row items-center gap-2 bg-red-900 border border-red-700 rounded-md text-sm text-white",
+            div {
+                class: "grow",
+                "{error}"
+            },
+            button {
+                class: "px-2 bg-red-700 hover:bg-red-600 rounded-md",
+                onclick: move |evt| onretry.call(evt),
+                "Retry"
+            }
+        }
+    }
+}
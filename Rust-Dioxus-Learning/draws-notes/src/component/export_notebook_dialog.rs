@@ -0,0 +1,97 @@
+use dioxus::prelude::*;
+
+use crate::{
+    error::NotesError,
+    export_notebook,
+    model::{export::ExportFormat, notebook::Notebook},
+};
+
+#[component]
+pub fn ExportNotebookDialog(
+    cx: Scope,
+    notebook: Notebook,
+    exporting_notebook: UseState<Option<Notebook>>,
+) -> Element {
+    let error: &UseState<Option<NotesError>> = use_state(cx, || None);
+    let exporting = use_state(cx, || false);
+
+    let export = move |format: ExportFormat| {
+        let Some(notebook_id) = notebook.id.clone() else {
+            return;
+        };
+        exporting.set(true);
+        cx.spawn({
+            to_owned!(exporting_notebook);
+            to_owned!(exporting);
+            to_owned!(error);
+            async move {
+                match export_notebook(notebook_id, format).await {
+                    Ok(file) => {
+                        #[cfg(feature = "web")]
+                        if let Err(e) = crate::download::save_file(&file.file_name, &file.bytes) {
+                            log::error!("error saving export {:?}", e);
+                        }
+                        exporting_notebook.set(None);
+                    }
+                    Err(e) => error.set(Some(NotesError::from_server_fn_error(&e))),
+                }
+                exporting.set(false);
+            }
+        });
+    };
+
+    render! {
+        div {
+            class: "fixed inset-0 bg-black/50 flex items-center justify-center z-10",
+            div {
+                class: "bg-gray-800 border border-gray-600 rounded-md p-4 flex flex-col gap-4 w-96",
+                div {
+                    class: "text-xl",
+                    "Export \"{notebook.name}\""
+                },
+                button {
+                    class: "px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-md text-left disabled:text-gray-400",
+                    disabled: *exporting.get(),
+                    onclick: move |_| export(ExportFormat::Markdown),
+                    div {
+                        "Markdown"
+                    },
+                    div {
+                        class: "text-gray-400 text-sm",
+                        "A zip with one file per note, readable anywhere"
+                    }
+                },
+                button {
+                    class: "px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-md text-left disabled:text-gray-400",
+                    disabled: *exporting.get(),
+                    onclick: move |_| export(ExportFormat::Json),
+                    div {
+                        "JSON"
+                    },
+                    div {
+                        class: "text-gray-400 text-sm",
+                        "Everything, for importing into draws-notes again"
+                    }
+                },
+                if let Some(error) = error.get() {
+                    rsx! {
+                        div {
+                            class: "text-red-500",
+                            "{error}"
+                        }
+                    }
+                },
+                div {
+                    class: "flex flex-row justify-end",
+                    button {
+                        class: "px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-md",
+                        onclick: move |_| {
+                            exporting_notebook.set(None);
+                        },
+                        "Close"
+                    }
+                }
+            }
+        }
+    }
+}
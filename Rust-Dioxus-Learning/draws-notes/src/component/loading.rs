@@ -2,7 +2,7 @@ use dioxus::prelude::*;
 
 #[component]
 pub fn Loading(cx: Scope, fullscreen: bool) -> Element {
-    let wh = if fullscreen.clone() {
+    let wh = if *fullscreen {
         "h-screen w-screen"
     } else {
         "h-full w-full"
@@ -10,7 +10,7 @@ pub fn Loading(cx: Scope, fullscreen: bool) -> Element {
 
     let base_css = "bg-gray-800 flex items-center justify-center p-8 gap-4 text-gray-400 text-lg";
     let css = format!("{}, {}", base_css, wh);
-    rsx! {
+    render! {
         div {
             class: "{css}",
             div {
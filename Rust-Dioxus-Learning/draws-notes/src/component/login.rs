@@ -0,0 +1,113 @@
+use dioxus::{html::input_data::keyboard_types::Key, prelude::*};
+use dioxus_fullstack::prelude::*;
+
+use crate::{error::NotesError, log_in, model::user::User, sign_up};
+
+#[component]
+pub fn Login<'a>(
+    cx: Scope,
+    current_user: &'a UseFuture<Result<Option<User>, ServerFnError>>,
+) -> Element {
+    let current_user = *current_user;
+    let username: &UseState<String> = use_state(cx, || "".to_string());
+    let password: &UseState<String> = use_state(cx, || "".to_string());
+    let signing_up = use_state(cx, || false);
+    let error: &UseState<Option<String>> = use_state(cx, || None);
+
+    let submit = move || {
+        cx.spawn({
+            to_owned!(username);
+            to_owned!(password);
+            to_owned!(signing_up);
+            to_owned!(error);
+            to_owned!(current_user);
+
+            async move {
+                let username = username.current().to_string();
+                let password = password.current().to_string();
+                let res = if *signing_up.get() {
+                    sign_up(username, password).await
+                } else {
+                    log_in(username, password).await
+                };
+
+                match res {
+                    Ok(_) => {
+                        error.set(None);
+                        current_user.restart();
+                    }
+                    Err(e) => match NotesError::from_server_fn_error(&e) {
+                        NotesError::Db(_) => {
+                            log::error!("error logging in {:?}", e);
+                            error.set(Some("Something went wrong, try again".to_string()));
+                        }
+                        e => error.set(Some(e.to_string())),
+                    },
+                }
+            }
+        })
+    };
+
+    let (title, action, toggle) = if *signing_up.get() {
+        ("Create an account", "Sign up", "Already have an account? Log in")
+    } else {
+        ("Log in", "Log in", "No account yet? Sign up")
+    };
+
+    render! {
+        div {
+            class: "h-screen w-screen bg-gray-800 flex items-center justify-center text-white",
+            div {
+                class: "flex flex-col gap-4 w-80",
+                div {
+                    class: "text-xl",
+                    "{title}"
+                },
+                input {
+                    placeholder: "Username",
+                    class: "w-full p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                    value: "{username}",
+                    oninput: move |evt| {
+                        username.set(evt.value.clone())
+                    },
+                },
+                input {
+                    placeholder: "Password",
+                    r#type: "password",
+                    class: "w-full p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                    value: "{password}",
+                    oninput: move |evt| {
+                        password.set(evt.value.clone())
+                    },
+                    onkeydown: move |evt: Event<KeyboardData>| {
+                        if evt.key() == Key::Enter {
+                            submit();
+                        }
+                    },
+                },
+                if let Some(error) = error.get() {
+                    rsx! {
+                        div {
+                            class: "text-red-500",
+                            "{error}"
+                        }
+                    }
+                },
+                button {
+                    class: "px-4 py-2 bg-blue-500 hover:bg-blue-600 rounded-md disabled:bg-neutral-600",
+                    disabled: username.get().trim().is_empty() || password.get().is_empty(),
+                    onclick: move |_| submit(),
+                    "{action}"
+                },
+                div {
+                    class: "text-gray-400 text-sm",
+                    onclick: move |_| {
+                        error.set(None);
+                        signing_up.set(!signing_up.get());
+                    },
+                    "{toggle}"
+                }
+            }
+        }
+    }
+}
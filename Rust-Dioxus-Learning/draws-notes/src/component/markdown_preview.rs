@@ -0,0 +1,77 @@
+use dioxus::prelude::*;
+
+use crate::{
+    component::save_indicator::SaveState,
+    markdown::{self, toggle_task, Block},
+};
+
+#[component]
+pub fn MarkdownPreview(
+    cx: Scope,
+    content: UseState<String>,
+    save_state: UseState<SaveState>,
+    read_only: bool,
+) -> Element {
+    let blocks = markdown::render(content.get());
+
+    render! {
+        div {
+            class: "markdown w-full h-full p-2 overflow-y-auto bg-gray-700 border border-gray-600 rounded-md",
+            style {
+                dangerous_inner_html: "{markdown::CODE_CSS.as_str()}",
+            }
+            for block in blocks {
+                MarkdownBlock {
+                    block: block,
+                    content: content.clone(),
+                    save_state: save_state.clone(),
+                    read_only: *read_only,
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn MarkdownBlock(
+    cx: Scope,
+    block: Block,
+    content: UseState<String>,
+    save_state: UseState<SaveState>,
+    read_only: bool,
+) -> Element {
+    match block {
+        Block::Html(html) => render! {
+            div {
+                dangerous_inner_html: "{html}",
+            }
+        },
+        Block::TaskList(items) => render! {
+            ul {
+                class: "task-list",
+                for item in items {
+                    li {
+                        class: "flex flex-row items-start gap-2",
+                        input {
+                            r#type: "checkbox",
+                            class: "mt-1",
+                            checked: item.checked,
+                            disabled: *read_only,
+                            onclick: move |_| {
+                                if !*read_only {
+                                    content.set(toggle_task(content.get(), &item.marker));
+                                    if *save_state.get() == SaveState::Saved {
+                                        save_state.set(SaveState::Unsaved);
+                                    }
+                                }
+                            },
+                        },
+                        div {
+                            dangerous_inner_html: "{item.html}",
+                        }
+                    }
+                }
+            }
+        },
+    }
+}
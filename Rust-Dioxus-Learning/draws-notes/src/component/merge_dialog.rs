@@ -0,0 +1,139 @@
+use dioxus::prelude::*;
+
+use crate::{
+    component::save_indicator::SaveState,
+    model::{
+        note::Note,
+        revision::{line_diff, DiffLine},
+    },
+};
+
+/// Shown when a save lost against someone else's. The editor's text stays editable next to
+/// the saved version, and whatever is kept gets rebased onto the saved version so the next
+/// save goes through.
+#[component]
+pub fn MergeDialog(
+    cx: Scope,
+    theirs: Note,
+    new_title: UseState<String>,
+    new_content: UseState<String>,
+    base_version: UseState<u64>,
+    conflict: UseState<Option<Note>>,
+    save_state: UseState<SaveState>,
+) -> Element {
+    let mut diff = vec![];
+    if &theirs.title != new_title.get() {
+        diff.push(DiffLine::Removed(format!("# {}", theirs.title)));
+        diff.push(DiffLine::Added(format!("# {}", new_title.get())));
+    }
+    diff.extend(line_diff(&theirs.content, new_content.get()));
+    let diff: Vec<(&str, &str, String)> = diff
+        .into_iter()
+        .map(|line| match line {
+            DiffLine::Unchanged(text) => ("px-2 text-gray-400", " ", text),
+            DiffLine::Added(text) => ("px-2 bg-green-900", "+", text),
+            DiffLine::Removed(text) => ("px-2 bg-red-900", "-", text),
+        })
+        .collect();
+
+    render! {
+        div {
+            class: "fixed inset-0 bg-black/50 flex items-center justify-center z-10",
+            div {
+                class: "bg-gray-800 border border-gray-600 rounded-md p-4 flex flex-col gap-4 w-[64rem] h-[80%]",
+                div {
+                    class: "text-xl",
+                    "This note was saved somewhere else while you were editing"
+                },
+                div {
+                    class: "text-gray-400",
+                    "Edit your version on the right until it has what you want from both, then keep it and save."
+                },
+                div {
+                    class: "grow flex flex-row gap-4 min-h-0",
+                    div {
+                        class: "w-1/3 flex flex-col gap-2",
+                        div {
+                            class: "text-gray-400",
+                            "Saved version"
+                        },
+                        div {
+                            class: "p-2 bg-gray-700 border border-gray-600 rounded-md",
+                            "{theirs.title}"
+                        },
+                        div {
+                            class: "grow p-2 overflow-y-auto bg-gray-700 border border-gray-600 rounded-md font-mono whitespace-pre-wrap",
+                            "{theirs.content}"
+                        }
+                    },
+                    div {
+                        class: "w-1/3 flex flex-col gap-2",
+                        div {
+                            class: "text-gray-400",
+                            "Changes from the saved version"
+                        },
+                        div {
+                            class: "grow overflow-y-auto font-mono text-sm whitespace-pre-wrap border border-gray-600 rounded-md",
+                            for (style, marker, text) in diff {
+                                div {
+                                    class: "{style}",
+                                    "{marker} {text}"
+                                }
+                            }
+                        }
+                    },
+                    div {
+                        class: "w-1/3 flex flex-col gap-2",
+                        div {
+                            class: "text-gray-400",
+                            "Your version"
+                        },
+                        input {
+                            class: "p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                            value: "{new_title}",
+                            oninput: move |evt| {
+                                new_title.set(evt.value.clone());
+                            },
+                        },
+                        textarea {
+                            class: "grow p-2 bg-gray-700 border border-gray-600 rounded-md resize-none focus:outline-none focus:ring-0 font-mono",
+                            value: "{new_content}",
+                            oninput: move |evt| {
+                                new_content.set(evt.value.clone());
+                            },
+                        }
+                    }
+                },
+                div {
+                    class: "flex flex-row justify-end gap-2",
+                    button {
+                        class: "px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-md",
+                        onclick: move |_| {
+                            new_title.set(theirs.title.clone());
+                            new_content.set(theirs.content.clone());
+                            base_version.set(theirs.version);
+                            save_state.set(SaveState::Saved);
+                            // nothing of ours is left to recover
+                            #[cfg(feature = "web")]
+                            if let Some(id) = theirs.id.as_ref() {
+                                crate::drafts::clear(id);
+                            }
+                            conflict.set(None);
+                        },
+                        "Use saved version"
+                    },
+                    button {
+                        class: "px-4 py-2 bg-blue-500 hover:bg-blue-600 rounded-md",
+                        onclick: move |_| {
+                            base_version.set(theirs.version);
+                            // autosaves again once the dialog is gone
+                            save_state.set(SaveState::Unsaved);
+                            conflict.set(None);
+                        },
+                        "Keep mine"
+                    }
+                }
+            }
+        }
+    }
+}
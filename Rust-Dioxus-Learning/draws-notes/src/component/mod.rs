@@ -1,5 +1,21 @@
+pub mod backlinks;
+pub mod command_palette;
 pub mod counter;
+pub mod delete_notebook_dialog;
+pub mod error_toast;
+pub mod export_notebook_dialog;
 pub mod loading;
+pub mod login;
+pub mod markdown_preview;
+pub mod merge_dialog;
+pub mod note_history;
+pub mod note_tags;
+pub mod related_notes;
 pub mod notebook_bar;
+pub mod notebook_row;
 pub mod notes_bar;
 pub mod notes_view;
+pub mod save_indicator;
+pub mod share_notebook_dialog;
+pub mod templates_dialog;
+pub mod trash_view;
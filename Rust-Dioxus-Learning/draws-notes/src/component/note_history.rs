@@ -0,0 +1,170 @@
+use dioxus::prelude::*;
+use dioxus_fullstack::prelude::*;
+
+use crate::{
+    model::{
+        note::Note,
+        revision::{line_diff, DiffLine, NoteRevision},
+    },
+    restore_note_revision,
+};
+
+#[component]
+pub fn NoteHistory<'a>(
+    cx: Scope,
+    revisions: &'a UseFuture<Result<Vec<NoteRevision>, ServerFnError>>,
+    full_note: &'a UseFuture<Option<Result<Note, ServerFnError>>>,
+    note_summaries: &'a UseFuture<Result<Vec<Note>, ServerFnError>>,
+    read_only: bool,
+) -> Element {
+    let revisions = *revisions;
+    let full_note = *full_note;
+    let note_summaries = *note_summaries;
+
+    // indexes into the revision list, newest first, so the default compares the latest save
+    // against the one before it
+    let compare_from: &UseState<usize> = use_state(cx, || 1);
+    let compare_to: &UseState<usize> = use_state(cx, || 0);
+
+    let list = match revisions.value() {
+        Some(Ok(list)) => list,
+        Some(Err(e)) => {
+            log::error!("error getting revisions {:?}", e);
+            return render! {
+                div {
+                    class: "text-red-500",
+                    "Couldn't load the history"
+                }
+            };
+        }
+        None => {
+            return render! {
+                div {
+                    class: "text-gray-400",
+                    "Loading history..."
+                }
+            };
+        }
+    };
+
+    let from = list.get(*compare_from.get()).or(list.last());
+    let to = list.get(*compare_to.get()).or(list.first());
+    let diff = match (from, to) {
+        (Some(from), Some(to)) => {
+            let mut diff = vec![];
+            if from.title != to.title {
+                diff.push(DiffLine::Removed(format!("# {}", from.title)));
+                diff.push(DiffLine::Added(format!("# {}", to.title)));
+            }
+            diff.extend(line_diff(&from.content, &to.content));
+            diff
+        }
+        _ => vec![],
+    };
+    let diff: Vec<(&str, &str, String)> = diff
+        .into_iter()
+        .map(|line| match line {
+            DiffLine::Unchanged(text) => ("px-2 text-gray-400", " ", text),
+            DiffLine::Added(text) => ("px-2 bg-green-900", "+", text),
+            DiffLine::Removed(text) => ("px-2 bg-red-900", "-", text),
+        })
+        .collect();
+
+    render! {
+        div {
+            class: "w-full max-h-[40%] flex flex-row gap-4 overflow-hidden",
+            div {
+                class: "w-64 shrink-0 flex flex-col overflow-y-auto border border-gray-600 rounded-md",
+                for (i, revision) in list.iter().enumerate() {
+                    div {
+                        class: "flex flex-row items-center pl-2 border-b border-gray-600",
+                        div {
+                            class: "grow flex flex-col",
+                            div {
+                                class: "truncate",
+                                "{revision.title}"
+                            },
+                            div {
+                                class: "text-gray-400 text-xs",
+                                "{revision.created_at}"
+                            }
+                        },
+                        if i > 0 && !*read_only {
+                            rsx! {
+                                button {
+                                    class: "px-2 text-blue-400",
+                                    onclick: move |_| {
+                                        cx.spawn({
+                                            to_owned!(revision);
+                                            to_owned!(full_note);
+                                            to_owned!(revisions);
+                                            to_owned!(note_summaries);
+                                            async move {
+                                                match restore_note_revision(revision.id).await {
+                                                    Ok(_) => {
+                                                        full_note.restart();
+                                                        revisions.restart();
+                                                        note_summaries.restart();
+                                                    }
+                                                    Err(e) => log::error!("error restoring revision {:?}", e),
+                                                }
+                                            }
+                                        })
+                                    },
+                                    "Restore"
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+            div {
+                class: "grow flex flex-col gap-2 overflow-hidden",
+                div {
+                    class: "flex flex-row items-center gap-2 text-gray-400",
+                    "Compare",
+                    select {
+                        class: "p-1 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                        value: "{compare_from}",
+                        onchange: move |evt| {
+                            if let Ok(i) = evt.value.parse() {
+                                compare_from.set(i);
+                            }
+                        },
+                        for (i, revision) in list.iter().enumerate() {
+                            option {
+                                value: "{i}",
+                                "{revision.created_at}"
+                            }
+                        }
+                    },
+                    "with",
+                    select {
+                        class: "p-1 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                        value: "{compare_to}",
+                        onchange: move |evt| {
+                            if let Ok(i) = evt.value.parse() {
+                                compare_to.set(i);
+                            }
+                        },
+                        for (i, revision) in list.iter().enumerate() {
+                            option {
+                                value: "{i}",
+                                "{revision.created_at}"
+                            }
+                        }
+                    }
+                },
+                div {
+                    class: "grow overflow-y-auto font-mono text-sm whitespace-pre-wrap border border-gray-600 rounded-md",
+                    for (style, marker, text) in diff {
+                        div {
+                            class: "{style}",
+                            "{marker} {text}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
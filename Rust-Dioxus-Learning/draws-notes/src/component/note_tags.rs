@@ -0,0 +1,109 @@
+use dioxus::{html::input_data::keyboard_types::Key, prelude::*};
+use dioxus_fullstack::prelude::*;
+
+use crate::{
+    get_note_tags,
+    model::{note::Note, tag::Tag},
+    tag_note, untag_note,
+};
+
+#[component]
+pub fn NoteTags<'a>(
+    cx: Scope,
+    note_id: String,
+    tags: &'a UseFuture<Result<Vec<Tag>, ServerFnError>>,
+    note_summaries: &'a UseFuture<Result<Vec<Note>, ServerFnError>>,
+    read_only: bool,
+) -> Element {
+    let tags = *tags;
+    let note_summaries = *note_summaries;
+    let new_tag_name: &UseState<String> = use_state(cx, || "".to_string());
+
+    let note_tags: &UseFuture<Result<Vec<Tag>, ServerFnError>> =
+        use_future(cx, (note_id), get_note_tags);
+
+    let submit_tag = move |ev: Event<KeyboardData>| {
+        if ev.key() == Key::Enter && !new_tag_name.get().trim().is_empty() {
+            cx.spawn({
+                to_owned!(note_id);
+                to_owned!(new_tag_name);
+                to_owned!(note_tags);
+                to_owned!(tags);
+
+                async move {
+                    match tag_note(note_id, new_tag_name.current().to_string()).await {
+                        Ok(_) => {
+                            new_tag_name.set("".to_string());
+                            note_tags.restart();
+                            tags.restart();
+                        }
+                        Err(e) => log::error!("error tagging note {:?}", e),
+                    }
+                }
+            });
+        }
+    };
+
+    let chips = match note_tags.value() {
+        Some(Ok(list)) => rsx! {
+            for tag in list {
+                div {
+                    class: "rounded-full bg-gray-700 text-sm px-2 flex flex-row items-center gap-1",
+                    "#{tag.name}",
+                    if !*read_only {
+                        rsx! {
+                            span {
+                                class: "text-gray-400 cursor-default",
+                                onclick: move |_| {
+                                    cx.spawn({
+                                        to_owned!(note_id);
+                                        to_owned!(tag);
+                                        to_owned!(note_tags);
+                                        to_owned!(tags);
+                                        to_owned!(note_summaries);
+
+                                        async move {
+                                            if let Some(tag_id) = tag.id {
+                                                match untag_note(note_id, tag_id).await {
+                                                    Ok(()) => {
+                                                        note_tags.restart();
+                                                        tags.restart();
+                                                        // the note may have just dropped out of the tag filter
+                                                        note_summaries.restart();
+                                                    }
+                                                    Err(e) => log::error!("error untagging note {:?}", e),
+                                                }
+                                            }
+                                        }
+                                    })
+                                },
+                                "×"
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        _ => rsx! { "" },
+    };
+
+    render! {
+        div {
+            class: "w-full flex flex-row flex-wrap items-center gap-2",
+            chips,
+            if !*read_only {
+                rsx! {
+                    input {
+                        placeholder: "Add tag",
+                        class: "w-32 px-2 bg-gray-700 border border-gray-600 rounded-md text-sm focus:outline-none focus:ring-0",
+                        value: "{new_tag_name}",
+                        onkeydown: submit_tag,
+                        oninput: move |evt| {
+                            new_tag_name.set(evt.value.clone())
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
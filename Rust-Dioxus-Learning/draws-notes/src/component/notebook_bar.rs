@@ -1,20 +1,53 @@
+use std::collections::HashSet;
+
 use dioxus::{html::input_data::keyboard_types::Key, prelude::*};
 use dioxus_fullstack::prelude::*;
 
 use crate::{
-    component::counter::Counter, get_notebooks, model::notebook::Notebook, upsert_notebook,
+    component::{
+        counter::Counter, delete_notebook_dialog::DeleteNotebookDialog, error_toast::ErrorToast,
+        export_notebook_dialog::ExportNotebookDialog, notebook_row::NotebookRow,
+        share_notebook_dialog::ShareNotebookDialog,
+    },
+    drop_notes,
+    error::NotesError,
+    get_notebooks, import_notebook, log_out,
+    model::{
+        note::Note,
+        notebook::{tree_rows, Notebook},
+        tag::Tag,
+        user::User,
+    },
+    set_notebook_parent, upsert_notebook,
 };
 
 #[component]
 pub fn NotebookBar<'a>(
     cx: Scope,
+    current_user: &'a UseFuture<Result<Option<User>, ServerFnError>>,
     notebooks: &'a UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    tags: &'a UseFuture<Result<Vec<Tag>, ServerFnError>>,
     selected_notebook: UseState<Option<Notebook>>,
+    selected_tag: UseState<Option<Tag>>,
+    note_summaries: &'a UseFuture<Result<Vec<Note>, ServerFnError>>,
+    selected_note: UseState<Option<Note>>,
+    dragged_notes: UseState<Vec<String>>,
 ) -> Element {
     let new_notebook_name: &UseState<String> = use_state(cx, || "".to_string());
     let creating_notebook = use_state(cx, || false);
+    let deleting_notebook: &UseState<Option<Notebook>> = use_state(cx, || None);
+    let sharing_notebook: &UseState<Option<Notebook>> = use_state(cx, || None);
+    let exporting_notebook: &UseState<Option<Notebook>> = use_state(cx, || None);
+    let import_error: &UseState<Option<NotesError>> = use_state(cx, || None);
+    let drop_error: &UseState<Option<NotesError>> = use_state(cx, || None);
+    let collapsed_notebooks: &UseState<HashSet<String>> = use_state(cx, HashSet::new);
+    let dragged_notebook: &UseState<Option<String>> = use_state(cx, || None);
+    let top_level_hovered = use_state(cx, || false);
 
-    let notebooks: &UseFuture<Result<Vec<Notebook>, ServerFnError>> = *notebooks;
+    let notebooks: &UseFuture<Result<Vec<Notebook>, ServerFnError>> = notebooks;
+    let tags: &UseFuture<Result<Vec<Tag>, ServerFnError>> = tags;
+    let current_user = *current_user;
+    let note_summaries = *note_summaries;
 
     let submit_notebook = move |ev: Event<KeyboardData>| {
         if ev.key() == Key::Enter {
@@ -28,6 +61,9 @@ pub fn NotebookBar<'a>(
                         id: None,
                         name: new_notebook_name.current().to_string(),
                         count: None,
+                        role: None,
+                        parent: None,
+                        total: None,
                     })
                     .await;
                     notebooks.restart();
@@ -35,59 +71,176 @@ pub fn NotebookBar<'a>(
             });
         }
     };
+    let drop_notes_on = move |target: Notebook, copy: bool| {
+        let note_ids = dragged_notes.get().clone();
+        dragged_notes.set(vec![]);
+        cx.spawn({
+            to_owned!(selected_note);
+            to_owned!(note_summaries);
+            to_owned!(notebooks);
+            to_owned!(tags);
+            to_owned!(drop_error);
+            async move {
+                match drop_notes(
+                    note_ids,
+                    target,
+                    copy,
+                    selected_note,
+                    note_summaries,
+                    notebooks,
+                    tags,
+                )
+                .await
+                {
+                    Ok(()) => drop_error.set(None),
+                    Err(e) => drop_error.set(Some(NotesError::from_server_fn_error(&e))),
+                }
+            }
+        });
+    };
+    // nests the dragged notebook under `parent`, or moves it to the top level
+    let nest_notebook = move |parent: Option<Notebook>| {
+        let Some(notebook_id) = dragged_notebook.get().clone() else {
+            return;
+        };
+        dragged_notebook.set(None);
+        cx.spawn({
+            to_owned!(notebooks);
+            to_owned!(drop_error);
+            async move {
+                match set_notebook_parent(notebook_id, parent.and_then(|nb| nb.id)).await {
+                    Ok(()) => drop_error.set(None),
+                    Err(e) => drop_error.set(Some(NotesError::from_server_fn_error(&e))),
+                }
+                notebooks.restart();
+            }
+        });
+    };
     const SELECTED_NOTE_STYLE: &str = "flex flex-row content-center items-center bg-gray-900 pl-4";
     const UNSELECTED_NOTE_STYLE: &str = "flex flex-row content-center items-center pl-4";
 
     const SELECTED_ALL_STYLE: &str = "flex flex-row content-center items-center bg-gray-900";
     const UNSELECTED_ALL_STYLE: &str = "flex flex-row content-center items-center";
     let notebooks_list = match notebooks.value() {
+        Some(Ok(list)) => {
+            // shared notebooks nest among themselves, under a parent only when that's shared too
+            let (owned, shared): (Vec<Notebook>, Vec<Notebook>) =
+                list.iter().cloned().partition(|nb| nb.is_owned());
+            let owned_rows = tree_rows(&owned, collapsed_notebooks.get());
+            let shared_rows = tree_rows(&shared, collapsed_notebooks.get());
+            rsx! {
+                div {
+                    class: "flex flex-col justify-h w-[200px] overflow-hidden",
+                    for row in owned_rows {
+                        NotebookRow {
+                            notebook: row.notebook.clone(),
+                            depth: row.depth,
+                            has_children: row.has_children,
+                            collapsed_notebooks: collapsed_notebooks.clone(),
+                            selected_notebook: selected_notebook.clone(),
+                            deleting_notebook: deleting_notebook.clone(),
+                            sharing_notebook: sharing_notebook.clone(),
+                            exporting_notebook: exporting_notebook.clone(),
+                            dragged_notes: dragged_notes.clone(),
+                            ondropnotes: move |(target, copy)| drop_notes_on(target, copy),
+                            dragged_notebook: dragged_notebook.clone(),
+                            ondropnotebook: move |parent| nest_notebook(Some(parent)),
+                        }
+                    },
+                    if (*creating_notebook.get()) {
+                        rsx! {
+                            div {
+                                class: "py-1 px-2",
+                                input {
+                                    class: "w-full bg-gray-700 border border-gray-600 rounded-md shrink focus:outline-none focus:ring-0",
+                                    value: "{new_notebook_name}",
+                                    onkeydown: submit_notebook,
+                                    oninput: move |evt| {
+                                        new_notebook_name.set(evt.value.clone())
+                                    },
+                                }
+                            },
+                        }
+                    },
+                    if !shared_rows.is_empty() {
+                        rsx! {
+                            div {
+                                class: "pl-2 pt-2 text-gray-400 text-sm",
+                                "Shared with me"
+                            },
+                            for row in shared_rows {
+                                NotebookRow {
+                                    notebook: row.notebook.clone(),
+                                    depth: row.depth,
+                                    has_children: row.has_children,
+                                    collapsed_notebooks: collapsed_notebooks.clone(),
+                                    selected_notebook: selected_notebook.clone(),
+                                    deleting_notebook: deleting_notebook.clone(),
+                                    sharing_notebook: sharing_notebook.clone(),
+                                    exporting_notebook: exporting_notebook.clone(),
+                                    dragged_notes: dragged_notes.clone(),
+                                    ondropnotes: move |(target, copy)| drop_notes_on(target, copy),
+                                    dragged_notebook: dragged_notebook.clone(),
+                                    ondropnotebook: move |parent| nest_notebook(Some(parent)),
+                                }
+                            }
+                        }
+                    },
+                },
+            }
+        }
+        Some(Err(e)) => rsx! {
+            ErrorToast {
+                error: NotesError::from_server_fn_error(e),
+                onretry: move |_| notebooks.restart(),
+            }
+        },
+        None => rsx! { "" },
+    };
+
+    let tags_list = match tags.value() {
         Some(Ok(list)) => rsx! {
             div {
                 class: "flex flex-col justify-h w-[200px] overflow-hidden",
-                for notebook in list {
+                for tag in list {
                     div {
-                        class: if let Some(selected) = selected_notebook.current().as_ref() {
-                            if selected.id == notebook.id {
-                                SELECTED_NOTE_STYLE
-                            } else {
-                                UNSELECTED_NOTE_STYLE
-                            }
+                        class: if selected_tag.get().as_ref().map(|t| &t.id) == Some(&tag.id) {
+                            SELECTED_NOTE_STYLE
                         } else {
                             UNSELECTED_NOTE_STYLE
                         },
                         onclick: move |_| {
-                            selected_notebook.set(Some(notebook.clone()))
+                            // clicking the selected tag again clears the filter
+                            if selected_tag.get().as_ref().map(|t| &t.id) == Some(&tag.id) {
+                                selected_tag.set(None);
+                            } else {
+                                selected_tag.set(Some(tag.clone()));
+                                if selected_notebook.get().as_ref().is_none_or(|nb| nb.is_trash()) {
+                                    selected_notebook.set(Some(Notebook::all()));
+                                }
+                            }
                         },
                         div {
                             class: "grow",
-                            "{notebook.name}",
+                            "#{tag.name}",
                         },
                         Counter {
-                           count: notebook.count.unwrap_or(0)
-                        },
-                    }
-                },
-                if (*creating_notebook.get()) {
-                    rsx! {
-                        div {
-                            class: "py-1 px-2",
-                            input {
-                                class: "w-full bg-gray-700 border border-gray-600 rounded-md shrink focus:outline-none focus:ring-0",
-                                value: "{new_notebook_name}",
-                                onkeydown: submit_notebook,
-                                oninput: move |evt| {
-                                    new_notebook_name.set(evt.value.clone())
-                                },
-                            }
+                           count: tag.count.unwrap_or(0)
                         },
                     }
-                },
-            },
+                }
+            }
+        },
+        Some(Err(e)) => rsx! {
+            ErrorToast {
+                error: NotesError::from_server_fn_error(e),
+                onretry: move |_| tags.restart(),
+            }
         },
-        _ => rsx! {"error"},
+        None => rsx! { "" },
     };
 
-    rsx! {
+    render! {
         div {
             class: "flex flex-col bg-gray-800 cursor-default",
             div {
@@ -122,8 +275,24 @@ pub fn NotebookBar<'a>(
                 }
             },
 
+            // dropping a notebook on the heading moves it back to the top level
             div {
-                class: "flex flex-row flex-nowrap content-center items-center",
+                class: if *top_level_hovered.get() {
+                    "flex flex-row flex-nowrap content-center items-center bg-gray-600"
+                } else {
+                    "flex flex-row flex-nowrap content-center items-center"
+                },
+                prevent_default: "ondragover ondrop",
+                ondragover: move |_| {
+                    if !*top_level_hovered.get() && dragged_notebook.get().is_some() {
+                        top_level_hovered.set(true);
+                    }
+                },
+                ondragleave: move |_| top_level_hovered.set(false),
+                ondrop: move |_| {
+                    top_level_hovered.set(false);
+                    nest_notebook(None);
+                },
                 svg {
                     class: "shrink h-4 px-2",
                     stroke: "white",
@@ -138,6 +307,48 @@ pub fn NotebookBar<'a>(
                     class: "grow",
                     "Notebooks"
                 },
+                // a hidden file input behind the upload icon, so clicking the icon opens the picker
+                label {
+                    class: "shrink pr-2",
+                    title: "Import a notebook",
+                    svg {
+                        class: "h-4",
+                        stroke: "white",
+                        fill: "white",
+                        xmlns: "http://www.w3.org/2000/svg",
+                        view_box: "0 0 512 512",
+                        path {
+                            d: "M288 109.3V352c0 17.7-14.3 32-32 32s-32-14.3-32-32V109.3l-73.4 73.4c-12.5 12.5-32.8 12.5-45.3 0s-12.5-32.8 0-45.3l128-128c12.5-12.5 32.8-12.5 45.3 0l128 128c12.5 12.5 12.5 32.8 0 45.3s-32.8 12.5-45.3 0L288 109.3zM64 352H192c0 35.3 28.7 64 64 64s64-28.7 64-64H448c35.3 0 64 28.7 64 64v32c0 35.3-28.7 64-64 64H64c-35.3 0-64-28.7-64-64V416c0-35.3 28.7-64 64-64zM432 456a24 24 0 1 0 0-48 24 24 0 1 0 0 48z"
+                        }
+                    },
+                    input {
+                        r#type: "file",
+                        class: "hidden",
+                        accept: ".zip,.json",
+                        onchange: move |evt: Event<FormData>| {
+                            let files = evt.files.clone();
+                            cx.spawn({
+                                to_owned!(notebooks);
+                                to_owned!(import_error);
+                                async move {
+                                    let Some(files) = files else {
+                                        return;
+                                    };
+                                    for file_name in files.files() {
+                                        let Some(bytes) = files.read_file(&file_name).await else {
+                                            continue;
+                                        };
+                                        match import_notebook(file_name, bytes).await {
+                                            Ok(_) => import_error.set(None),
+                                            Err(e) => import_error.set(Some(NotesError::from_server_fn_error(&e))),
+                                        }
+                                    }
+                                    notebooks.restart();
+                                }
+                            })
+                        },
+                    }
+                },
                 svg {
                     class: "shrink h-[20px] pr-2",
                     stroke: "white",
@@ -153,6 +364,126 @@ pub fn NotebookBar<'a>(
                 },
             },
             notebooks_list,
+            div {
+                class: if selected_notebook.current().as_ref().as_ref().is_some_and(|nb| nb.is_trash()) {
+                    SELECTED_ALL_STYLE
+                } else {
+                    UNSELECTED_ALL_STYLE
+                },
+                onclick: move |_| {
+                    selected_notebook.set(Some(Notebook::trash()));
+                },
+                svg {
+                    class: "shrink h-4 px-2",
+                    stroke: "white",
+                    fill: "white",
+                    xmlns: "http://www.w3.org/2000/svg",
+                    view_box: "0 0 448 512",
+                    path {
+                        d: "M135.2 17.7L128 32H32C14.3 32 0 46.3 0 64S14.3 96 32 96H416c17.7 0 32-14.3 32-32s-14.3-32-32-32H320l-7.2-14.3C307.4 6.8 296.3 0 284.2 0H163.8c-12.1 0-23.2 6.8-28.6 17.7zM416 128H32L53.2 467c1.6 25.3 22.6 45 47.9 45H346.9c25.3 0 46.3-19.7 47.9-45L416 128z"
+                    },
+                },
+                div {
+                    class: "grow",
+                    "Trash",
+                },
+            },
+            if let Some(error) = import_error.get() {
+                rsx! {
+                    div {
+                        class: "pl-4 pr-2 text-red-500 text-sm",
+                        onclick: move |_| import_error.set(None),
+                        "Import failed: {error}"
+                    }
+                }
+            },
+            if let Some(error) = drop_error.get() {
+                rsx! {
+                    div {
+                        class: "pl-4 pr-2 text-red-500 text-sm",
+                        onclick: move |_| drop_error.set(None),
+                        "Moving failed: {error}"
+                    }
+                }
+            },
+
+            div {
+                class: "flex flex-row flex-nowrap content-center items-center pt-4",
+                svg {
+                    class: "shrink h-4 px-2",
+                    stroke: "white",
+                    fill: "white",
+                    xmlns: "http://www.w3.org/2000/svg",
+                    view_box: "0 0 448 512",
+                    path {
+                        d: "M0 80V229.5c0 17 6.7 33.3 18.7 45.3l176 176c25 25 65.5 25 90.5 0L418.7 317.3c25-25 25-65.5 0-90.5l-176-176c-12-12-28.3-18.7-45.3-18.7H48C21.5 32 0 53.5 0 80zm112 32a32 32 0 1 1 0 64 32 32 0 1 1 0-64z",
+                    },
+                }
+                div {
+                    class: "grow",
+                    "Tags"
+                },
+            },
+            tags_list,
+            div {
+                class: "grow"
+            },
+            if let Some(Ok(Some(user))) = current_user.value() {
+                rsx! {
+                    div {
+                        class: "flex flex-row items-center pl-4 pb-2 text-gray-400 text-sm",
+                        div {
+                            class: "grow truncate",
+                            "{user.username}"
+                        },
+                        div {
+                            class: "pr-2",
+                            onclick: move |_| {
+                                cx.spawn({
+                                    to_owned!(current_user);
+                                    to_owned!(selected_notebook);
+                                    to_owned!(selected_tag);
+                                    async move {
+                                        if let Err(e) = log_out().await {
+                                            log::error!("error logging out {:?}", e);
+                                        }
+                                        selected_notebook.set(None);
+                                        selected_tag.set(None);
+                                        current_user.restart();
+                                    }
+                                })
+                            },
+                            "Log out"
+                        }
+                    }
+                }
+            },
+            if let Some(notebook) = deleting_notebook.get() {
+                rsx! {
+                    DeleteNotebookDialog {
+                        notebook: notebook.clone(),
+                        notebooks: notebooks,
+                        selected_notebook: selected_notebook.clone(),
+                        deleting_notebook: deleting_notebook.clone(),
+                    }
+                }
+            },
+            if let Some(notebook) = sharing_notebook.get() {
+                rsx! {
+                    ShareNotebookDialog {
+                        notebook: notebook.clone(),
+                        sharing_notebook: sharing_notebook.clone(),
+                    }
+                }
+            },
+            if let Some(notebook) = exporting_notebook.get() {
+                rsx! {
+                    ExportNotebookDialog {
+                        notebook: notebook.clone(),
+                        exporting_notebook: exporting_notebook.clone(),
+                    }
+                }
+            }
         }
     }
 }
@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+
+use dioxus::{html::input_data::keyboard_types::Modifiers, prelude::*};
+
+use crate::{component::counter::Counter, model::notebook::Notebook};
+
+/// A notebook in the notebook bar's tree, indented `depth` levels. Notes dragged from the
+/// notes bar can be dropped on it, `ondropnotes` is told whether to copy them rather than move
+/// them. Owned notebooks can also be dragged onto each other to nest them, `ondropnotebook`
+/// gets the new parent.
+#[component]
+pub fn NotebookRow<'a>(
+    cx: Scope,
+    notebook: Notebook,
+    depth: usize,
+    has_children: bool,
+    collapsed_notebooks: UseState<HashSet<String>>,
+    selected_notebook: UseState<Option<Notebook>>,
+    deleting_notebook: UseState<Option<Notebook>>,
+    sharing_notebook: UseState<Option<Notebook>>,
+    exporting_notebook: UseState<Option<Notebook>>,
+    dragged_notes: UseState<Vec<String>>,
+    ondropnotes: EventHandler<'a, (Notebook, bool)>,
+    dragged_notebook: UseState<Option<String>>,
+    ondropnotebook: EventHandler<'a, Notebook>,
+) -> Element {
+    let drop_hovered = use_state(cx, || false);
+    let id = notebook.id.clone().unwrap_or_default();
+    let collapsed = collapsed_notebooks.get().contains(&id);
+    // a notebook can't go under itself, the server also refuses its descendants
+    let nesting = notebook.is_owned()
+        && dragged_notebook
+            .get()
+            .as_ref()
+            .is_some_and(|dragged| *dragged != id);
+    let accepts_drop = nesting || (notebook.can_edit() && !dragged_notes.get().is_empty());
+    // a rem per level on top of the rows' usual padding
+    let indent = 1 + *depth;
+    let toggle = match (*has_children, collapsed) {
+        (false, _) => "",
+        (true, true) => "▸",
+        (true, false) => "▾",
+    };
+
+    const SELECTED_NOTE_STYLE: &str = "flex flex-row content-center items-center bg-gray-900 pl-4";
+    const UNSELECTED_NOTE_STYLE: &str = "flex flex-row content-center items-center pl-4";
+    const DROP_TARGET_STYLE: &str = "flex flex-row content-center items-center bg-gray-600 pl-4";
+
+    render! {
+        div {
+            class: if *drop_hovered.get() {
+                DROP_TARGET_STYLE
+            } else if let Some(selected) = selected_notebook.current().as_ref() {
+                if selected.id == notebook.id {
+                    SELECTED_NOTE_STYLE
+                } else {
+                    UNSELECTED_NOTE_STYLE
+                }
+            } else {
+                UNSELECTED_NOTE_STYLE
+            },
+            style: "padding-left: {indent}rem",
+            onclick: move |_| {
+                selected_notebook.set(Some(notebook.clone()))
+            },
+            draggable: if notebook.is_owned() { "true" } else { "false" },
+            ondragstart: move |_| dragged_notebook.set(notebook.id.clone()),
+            ondragend: move |_| dragged_notebook.set(None),
+            // viewers can't add notes, so the browser is left to refuse the drop
+            prevent_default: if notebook.can_edit() { "ondragover ondrop" } else { "" },
+            ondragover: move |_| {
+                // fires continuously while hovering, only the first one needs a render
+                if !*drop_hovered.get() && accepts_drop {
+                    drop_hovered.set(true);
+                }
+            },
+            ondragleave: move |_| drop_hovered.set(false),
+            ondrop: move |evt: DragEvent| {
+                drop_hovered.set(false);
+                if nesting {
+                    ondropnotebook.call(notebook.clone());
+                } else if notebook.can_edit() {
+                    let copy = evt.mouse.modifiers().intersects(Modifiers::ALT | Modifiers::CONTROL);
+                    ondropnotes.call((notebook.clone(), copy));
+                }
+            },
+            div {
+                class: "w-4 shrink-0 text-xs text-gray-400",
+                onclick: move |evt| {
+                    if !*has_children {
+                        return;
+                    }
+                    evt.stop_propagation();
+                    let mut collapsed_ids = collapsed_notebooks.get().clone();
+                    if !collapsed_ids.remove(&id) {
+                        collapsed_ids.insert(id.clone());
+                    }
+                    collapsed_notebooks.set(collapsed_ids);
+                },
+                "{toggle}"
+            },
+            div {
+                class: "grow",
+                "{notebook.name}",
+            },
+            // the notebooks nested in it count too, collapsed or not
+            Counter {
+               count: notebook.total.or(notebook.count).unwrap_or(0)
+            },
+            svg {
+                class: "shrink h-3 pr-2",
+                stroke: "rgb(156 163 175 / var(--tw-text-opacity))",
+                fill: "rgb(156 163 175 / var(--tw-text-opacity))",
+                xmlns: "http://www.w3.org/2000/svg",
+                view_box: "0 0 512 512",
+                onclick: move |evt| {
+                    evt.stop_propagation();
+                    exporting_notebook.set(Some(notebook.clone()));
+                },
+                path {
+                    d: "M288 32c0-17.7-14.3-32-32-32s-32 14.3-32 32V274.7l-73.4-73.4c-12.5-12.5-32.8-12.5-45.3 0s-12.5 32.8 0 45.3l128 128c12.5 12.5 32.8 12.5 45.3 0l128-128c12.5-12.5 12.5-32.8 0-45.3s-32.8-12.5-45.3 0L288 274.7V32zM64 352c-35.3 0-64 28.7-64 64v32c0 35.3 28.7 64 64 64H448c35.3 0 64-28.7 64-64V416c0-35.3-28.7-64-64-64H346.5l-45.3 45.3c-25 25-65.5 25-90.5 0L165.5 352H64zm368 56a24 24 0 1 1 0 48 24 24 0 1 1 0-48z"
+                }
+            },
+            // only the owner gets to share or delete a notebook
+            if notebook.is_owned() {
+                rsx! {
+                    svg {
+                        class: "shrink h-3 pr-2",
+                        stroke: "rgb(156 163 175 / var(--tw-text-opacity))",
+                        fill: "rgb(156 163 175 / var(--tw-text-opacity))",
+                        xmlns: "http://www.w3.org/2000/svg",
+                        view_box: "0 0 448 512",
+                        onclick: move |evt| {
+                            evt.stop_propagation();
+                            sharing_notebook.set(Some(notebook.clone()));
+                        },
+                        path {
+                            d: "M352 224c53 0 96-43 96-96s-43-96-96-96s-96 43-96 96c0 4 .2 8 .7 11.9l-94.1 47C145.4 170.2 121.9 160 96 160c-53 0-96 43-96 96s43 96 96 96c25.9 0 49.4-10.2 66.6-26.9l94.1 47c-.5 3.9-.7 7.8-.7 11.9c0 53 43 96 96 96s96-43 96-96s-43-96-96-96c-25.9 0-49.4 10.2-66.6 26.9l-94.1-47c.5-3.9 .7-7.8 .7-11.9s-.2-8-.7-11.9l94.1-47C302.6 213.8 326.1 224 352 224z"
+                        }
+                    },
+                    svg {
+                        class: "shrink h-3 pr-2",
+                        stroke: "rgb(156 163 175 / var(--tw-text-opacity))",
+                        fill: "rgb(156 163 175 / var(--tw-text-opacity))",
+                        xmlns: "http://www.w3.org/2000/svg",
+                        view_box: "0 0 448 512",
+                        onclick: move |evt| {
+                            evt.stop_propagation();
+                            deleting_notebook.set(Some(notebook.clone()));
+                        },
+                        path {
+                            d: "M135.2 17.7L128 32H32C14.3 32 0 46.3 0 64S14.3 96 32 96H416c17.7 0 32-14.3 32-32s-14.3-32-32-32H320l-7.2-14.3C307.4 6.8 296.3 0 284.2 0H163.8c-12.1 0-23.2 6.8-28.6 17.7zM416 128H32L53.2 467c1.6 25.3 22.6 45 47.9 45H346.9c25.3 0 46.3-19.7 47.9-45L416 128z"
+                        }
+                    },
+                }
+            } else {
+                rsx! {
+                    div {
+                        class: "pr-2 text-xs text-gray-400",
+                        "{notebook.role.map(|role| role.label()).unwrap_or_default()}"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,45 @@
 use std::rc::Rc;
 
-use dioxus::prelude::*;
+use dioxus::{html::input_data::keyboard_types::Modifiers, prelude::*};
 use dioxus_fullstack::prelude::*;
 
 use crate::{
-    model::{note::Note, notebook::Notebook},
-    upsert_note,
+    add_note,
+    component::{error_toast::ErrorToast, templates_dialog::TemplatesDialog},
+    error::NotesError,
+    get_note_summaries, get_templates,
+    model::{
+        note::{Note, NoteSort},
+        notebook::Notebook,
+        search::{highlight_segments, NoteSearchHit},
+        tag::Tag,
+        template::Template,
+    },
+    search_notes, NOTE_PAGE_SIZE,
 };
+
+const NOTES_LIST_ID: &str = "notes-list";
+// how close to the end of the list, in pixels, the next page starts loading
+const LOAD_MORE_THRESHOLD_PX: i32 = 200;
+
+/// Whether the notes list has been scrolled close enough to its end to want the next page.
+#[cfg(feature = "web")]
+fn near_list_end() -> bool {
+    let Some(list) = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(NOTES_LIST_ID))
+    else {
+        return false;
+    };
+    list.scroll_top() + list.client_height() >= list.scroll_height() - LOAD_MORE_THRESHOLD_PX
+}
+
+// nothing scrolls on the server
+#[cfg(not(feature = "web"))]
+fn near_list_end() -> bool {
+    false
+}
+
 #[component]
 pub fn NotesBar<'a>(
     cx: Scope,
@@ -14,81 +47,426 @@ pub fn NotesBar<'a>(
     notebooks: &'a UseFuture<Result<Vec<Notebook>, ServerFnError>>,
     selected_note: UseState<Option<Note>>,
     selected_notebook: Notebook,
+    selected_tag: UseState<Option<Tag>>,
+    note_sort: UseState<NoteSort>,
+    next_cursor: UseState<Option<String>>,
+    dragged_notes: UseState<Vec<String>>,
+    include_children: UseState<bool>,
 ) -> Element {
     //apparently these are double references for reasons i don't fully understand
     let note_summaries = *note_summaries;
     let notebooks = *notebooks;
 
+    let search_query: &UseState<String> = use_state(cx, || "".to_string());
+    let notebook_id = selected_notebook.id.clone();
+    let search_results: &UseFuture<Result<Vec<NoteSearchHit>, ServerFnError>> = use_future(
+        cx,
+        (search_query, &notebook_id),
+        |(search_query, notebook_id)| async move {
+            search_notes(search_query.current().to_string(), notebook_id).await
+        },
+    );
+    let searching = !search_query.get().trim().is_empty();
+    let has_children = match (notebooks.value(), notebook_id.as_ref()) {
+        (Some(Ok(list)), Some(id)) => list.iter().any(|nb| nb.parent.as_ref() == Some(id)),
+        _ => false,
+    };
+    let loading_more: &UseState<bool> = use_state(cx, || false);
+    // ctrl or shift clicked notes, dragged onto a notebook together
+    let marked_notes: &UseState<Vec<String>> = use_state(cx, Vec::new);
+    let templates: &UseFuture<Result<Vec<Template>, ServerFnError>> =
+        use_future(cx, (), |_| get_templates());
+    // what the new note button starts from, a blank note when `None`
+    let new_note_template: &UseState<Option<String>> = use_state(cx, || None);
+    let editing_templates: &UseState<bool> = use_state(cx, || false);
+    let template_list: &[Template] = match templates.value() {
+        Some(Ok(list)) => list,
+        _ => &[],
+    };
+
+    use_effect(cx, (&notebook_id,), |_| {
+        to_owned!(marked_notes);
+        async move {
+            marked_notes.set(vec![]);
+        }
+    });
+
+    // appends the next page to what's already listed, rather than reloading all of it
+    let load_more = move || {
+        let Some(cursor) = next_cursor.get().clone() else {
+            return;
+        };
+        if *loading_more.get() || searching {
+            return;
+        }
+        loading_more.set(true);
+        cx.spawn({
+            to_owned!(note_summaries);
+            to_owned!(next_cursor);
+            to_owned!(loading_more);
+            to_owned!(notebook_id);
+            let tag_id = selected_tag.get().as_ref().and_then(|tag| tag.id.clone());
+            let sort = *note_sort.get();
+            let include_children = *include_children.get();
+            async move {
+                match get_note_summaries(
+                    notebook_id,
+                    include_children,
+                    tag_id,
+                    sort,
+                    Some(cursor.clone()),
+                    NOTE_PAGE_SIZE,
+                )
+                .await
+                {
+                    // the list started over meanwhile, this page belongs to the old one
+                    Ok(_) if next_cursor.current().as_ref() != &Some(cursor) => {}
+                    Ok(page) => {
+                        if let Some(Ok(notes)) = note_summaries.value() {
+                            let mut notes = notes.clone();
+                            notes.extend(page.notes);
+                            note_summaries.set(Ok(notes));
+                            next_cursor.set(page.next_cursor);
+                        }
+                    }
+                    Err(e) => log::error!("error loading more notes {:?}", e),
+                }
+                loading_more.set(false);
+            }
+        })
+    };
+
     const SELECTED_NOTE_STYLE: &str =
         "w-full flex flex-col pl-2 bg-gray-900 border-t border-gray-600 select-none w-64 max-w-64 ";
     const UNSELECTED_NOTE_STYLE: &str =
         "w-full flex flex-col pl-2 border-t border-gray-600 select-none w-64 max-w-64";
 
+    const MARKED_NOTE_STYLE: &str =
+        "w-full flex flex-col pl-2 bg-gray-600 border-t border-gray-600 select-none w-64 max-w-64";
+
+    const MATCH_STYLE: &str = "bg-yellow-600 text-white";
+    const NO_MATCH_STYLE: &str = "";
+
+    let search_list = match search_results.value() {
+        Some(Ok(hits)) if hits.is_empty() => rsx! {
+            div {
+                class: "pl-2 text-gray-400",
+                "No matching notes"
+            }
+        },
+        Some(Ok(hits)) => rsx! {
+            for hit in hits {
+                div {
+                    class: if let Some(selected) = selected_note.current().as_ref() {
+                        if selected.id.as_ref() == Some(&hit.id) {
+                            SELECTED_NOTE_STYLE
+                        } else {
+                            UNSELECTED_NOTE_STYLE
+                        }
+                    } else {
+                        UNSELECTED_NOTE_STYLE
+                    },
+                    onclick: move |_| {
+                        selected_note.set(Some(Note {
+                            id: Some(hit.id.clone()),
+                            title: hit.plain_title(),
+                            ..Note::new(hit.notebook.clone())
+                        }))
+                    },
+                    div {
+                        for (text, matched) in highlight_segments(&hit.title) {
+                            span {
+                                class: if matched { MATCH_STYLE } else { NO_MATCH_STYLE },
+                                "{text}"
+                            }
+                        }
+                    },
+                    div {
+                        class: "text-gray-400 text-sm",
+                        for (text, matched) in highlight_segments(&hit.snippet) {
+                            span {
+                                class: if matched { MATCH_STYLE } else { NO_MATCH_STYLE },
+                                "{text}"
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        Some(Err(e)) => {
+            log::error!("error searching notes {:?}", e);
+            rsx! {
+                div {
+                    class: "pl-2 text-red-500",
+                    "Search failed"
+                }
+            }
+        }
+        None => rsx! {
+            div {
+                class: "pl-2 text-gray-400",
+                "Searching..."
+            }
+        },
+    };
+
     if let Some(Ok(summaries)) = note_summaries.value().as_ref() {
-        rsx! {
+        render! {
             div {
+                id: NOTES_LIST_ID,
                 class: "w-64 flex-shrink-0 h-full overflow-y-auto bg-gray-700 cursor-default",
+                onscroll: move |_| {
+                    if near_list_end() {
+                        load_more();
+                    }
+                },
                 div {
                     class: "flex flex-row items-center",
                     div {
                         class: "text-xl grow flex flex-row justify-center",
                         "{selected_notebook.name}",
                     },
-                    svg {
-                        class: "shrink h-4 px-2",
-                        stroke: "white",
-                        fill: "white",
-                        xmlns:"http://www.w3.org/2000/svg",
-                        view_box: "0 0 512 512",
-                        onclick: move |_| {
-                            cx.spawn({
-                                to_owned!(selected_notebook);
-                                to_owned!(note_summaries);
-                                to_owned!(notebooks);
-                                async move {
-                                    if let Some(notebook_id) = selected_notebook.id {
-                                        upsert_note(Note::new(notebook_id)).await;
-                                        note_summaries.restart();
-                                        // this is really really inefficient, it'd be better to just increment
-                                        // the count locally, but for now reload the entire notebooks future
-                                        // to ensure the note count is updated
-                                        notebooks.restart();
-                                    }
+                    if let Some(tag) = selected_tag.get() {
+                        rsx! {
+                            div {
+                                class: "rounded-full bg-gray-800 text-xs px-2 flex flex-row items-center gap-1",
+                                "#{tag.name}",
+                                span {
+                                    class: "text-gray-400",
+                                    onclick: move |_| {
+                                        selected_tag.set(None);
+                                    },
+                                    "×"
                                 }
-                            })
-                        },
-                        path {
-                            d: "M441 58.9L453.1 71c9.4 9.4 9.4 24.6 0 33.9L424 134.1 377.9 88 407 58.9c9.4-9.4 24.6-9.4 33.9 0zM209.8 256.2L344 121.9 390.1 168 255.8 302.2c-2.9 2.9-6.5 5-10.4 6.1l-58.5 16.7 16.7-58.5c1.1-3.9 3.2-7.5 6.1-10.4zM373.1 25L175.8 222.2c-8.7 8.7-15 19.4-18.3 31.1l-28.6 100c-2.4 8.4-.1 17.4 6.1 23.6s15.2 8.5 23.6 6.1l100-28.6c11.8-3.4 22.5-9.7 31.1-18.3L487 138.9c28.1-28.1 28.1-73.7 0-101.8L474.9 25C446.8-3.1 401.2-3.1 373.1 25zM88 64C39.4 64 0 103.4 0 152V424c0 48.6 39.4 88 88 88H360c48.6 0 88-39.4 88-88V312c0-13.3-10.7-24-24-24s-24 10.7-24 24V424c0 22.1-17.9 40-40 40H88c-22.1 0-40-17.9-40-40V152c0-22.1 17.9-40 40-40H200c13.3 0 24-10.7 24-24s-10.7-24-24-24H88z",
-                        },
+                            }
+                        }
+                    },
+                    // viewers can read a shared notebook but not add to it
+                    if selected_notebook.can_edit() {
+                        rsx! {
+                            svg {
+                                class: "shrink h-4 px-2",
+                                stroke: "white",
+                                fill: "white",
+                                xmlns:"http://www.w3.org/2000/svg",
+                                view_box: "0 0 512 512",
+                                onclick: move |_| {
+                                    let template = template_list
+                                        .iter()
+                                        .find(|template| template.id == *new_note_template.get())
+                                        .cloned();
+                                    cx.spawn({
+                                        to_owned!(selected_notebook);
+                                        to_owned!(selected_note);
+                                        to_owned!(note_summaries);
+                                        to_owned!(notebooks);
+                                        async move {
+                                            if let Err(e) = add_note(selected_notebook, template, selected_note, note_summaries, notebooks).await {
+                                                log::error!("error adding note {:?}", e);
+                                            }
+                                        }
+                                    })
+                                },
+                                path {
+                                    d: "M441 58.9L453.1 71c9.4 9.4 9.4 24.6 0 33.9L424 134.1 377.9 88 407 58.9c9.4-9.4 24.6-9.4 33.9 0zM209.8 256.2L344 121.9 390.1 168 255.8 302.2c-2.9 2.9-6.5 5-10.4 6.1l-58.5 16.7 16.7-58.5c1.1-3.9 3.2-7.5 6.1-10.4zM373.1 25L175.8 222.2c-8.7 8.7-15 19.4-18.3 31.1l-28.6 100c-2.4 8.4-.1 17.4 6.1 23.6s15.2 8.5 23.6 6.1l100-28.6c11.8-3.4 22.5-9.7 31.1-18.3L487 138.9c28.1-28.1 28.1-73.7 0-101.8L474.9 25C446.8-3.1 401.2-3.1 373.1 25zM88 64C39.4 64 0 103.4 0 152V424c0 48.6 39.4 88 88 88H360c48.6 0 88-39.4 88-88V312c0-13.3-10.7-24-24-24s-24 10.7-24 24V424c0 22.1-17.9 40-40 40H88c-22.1 0-40-17.9-40-40V152c0-22.1 17.9-40 40-40H200c13.3 0 24-10.7 24-24s-10.7-24-24-24H88z",
+                                },
+                            }
+                        }
                     },
                 },
-                for note in summaries {
-                    div {
-                        class: if let Some(selected) = selected_note.current().as_ref() {
-                            if selected.id == note.id {
-                                SELECTED_NOTE_STYLE
-                            } else {
-                                UNSELECTED_NOTE_STYLE
+                if selected_notebook.can_edit() {
+                    rsx! {
+                        div {
+                            class: "pt-1 px-2 flex flex-row items-center gap-2 text-sm text-gray-400",
+                            "New from",
+                            select {
+                                class: "grow bg-gray-800 border border-gray-600 rounded-md text-white focus:outline-none focus:ring-0",
+                                onchange: move |evt| {
+                                    new_note_template.set(Some(evt.value.clone()).filter(|id| !id.is_empty()));
+                                },
+                                option {
+                                    value: "",
+                                    // also covers a template deleted while it was picked
+                                    selected: !template_list.iter().any(|template| template.id == *new_note_template.get()),
+                                    "Blank note"
+                                },
+                                for template in template_list {
+                                    option {
+                                        value: "{template.id.clone().unwrap_or_default()}",
+                                        selected: template.id == *new_note_template.get(),
+                                        "{template.name}"
+                                    }
+                                }
+                            },
+                            span {
+                                class: "hover:text-white",
+                                title: "Edit templates",
+                                onclick: move |_| editing_templates.set(true),
+                                "Edit"
                             }
-                        } else {
-                            UNSELECTED_NOTE_STYLE
-                        },
-                        onclick: move |_| {
-                            selected_note.set(Some(note.clone()))
+                        }
+                    }
+                },
+                if *editing_templates.get() {
+                    rsx! {
+                        TemplatesDialog {
+                            templates: templates,
+                            editing_templates: editing_templates.clone(),
+                        }
+                    }
+                },
+                div {
+                    class: "py-1 px-2",
+                    input {
+                        placeholder: "Search",
+                        class: "w-full bg-gray-800 border border-gray-600 rounded-md shrink focus:outline-none focus:ring-0",
+                        value: "{search_query}",
+                        oninput: move |evt| {
+                            search_query.set(evt.value.clone())
                         },
+                    }
+                },
+                if !searching {
+                    rsx! {
                         div {
-                            "{note.title}"
-                        },
+                            class: "pb-1 px-2 flex flex-row items-center gap-2 text-sm text-gray-400",
+                            "Sort by",
+                            select {
+                                class: "grow bg-gray-800 border border-gray-600 rounded-md text-white focus:outline-none focus:ring-0",
+                                onchange: move |evt| {
+                                    if let Some(sort) = NoteSort::from_value(&evt.value) {
+                                        note_sort.set(sort);
+                                    }
+                                },
+                                for sort in NoteSort::ALL {
+                                    option {
+                                        value: sort.value(),
+                                        selected: sort == *note_sort.get(),
+                                        "{sort.label()}"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                if !searching && has_children {
+                    rsx! {
+                        label {
+                            class: "pb-1 px-2 flex flex-row items-center gap-2 text-sm text-gray-400",
+                            input {
+                                r#type: "checkbox",
+                                checked: *include_children.get(),
+                                onchange: move |_| include_children.set(!*include_children.get()),
+                            },
+                            "Include sub-notebooks"
+                        }
+                    }
+                },
+                if !searching && !marked_notes.get().is_empty() {
+                    rsx! {
                         div {
-                            class: "text-gray-400 text-nowrap truncate",
-                            "{note.content}"
+                            class: "pb-1 px-2 flex flex-row items-center gap-2 text-sm text-gray-400",
+                            div {
+                                class: "grow",
+                                title: "Drag them onto a notebook to move them, hold Alt to copy instead",
+                                "{marked_notes.get().len()} selected"
+                            },
+                            span {
+                                onclick: move |_| marked_notes.set(vec![]),
+                                "×"
+                            }
+                        }
+                    }
+                },
+                if searching {
+                    search_list
+                } else {
+                    rsx! {
+                        for note in summaries {
+                            div {
+                                class: if note.id.as_ref().is_some_and(|id| marked_notes.get().contains(id)) {
+                                    MARKED_NOTE_STYLE
+                                } else if let Some(selected) = selected_note.current().as_ref() {
+                                    if selected.id == note.id {
+                                        SELECTED_NOTE_STYLE
+                                    } else {
+                                        UNSELECTED_NOTE_STYLE
+                                    }
+                                } else {
+                                    UNSELECTED_NOTE_STYLE
+                                },
+                                draggable: "true",
+                                onclick: move |evt| {
+                                    let Some(id) = note.id.clone() else {
+                                        return;
+                                    };
+                                    if evt.modifiers().intersects(Modifiers::CONTROL | Modifiers::META | Modifiers::SHIFT) {
+                                        let mut marked = marked_notes.get().clone();
+                                        match marked.iter().position(|marked_id| *marked_id == id) {
+                                            Some(i) => {
+                                                marked.remove(i);
+                                            }
+                                            None => marked.push(id),
+                                        }
+                                        marked_notes.set(marked);
+                                    } else {
+                                        marked_notes.set(vec![]);
+                                        selected_note.set(Some(note.clone()))
+                                    }
+                                },
+                                // dragging a selected note takes the rest of the selection along
+                                ondragstart: move |_| {
+                                    let Some(id) = note.id.clone() else {
+                                        return;
+                                    };
+                                    if marked_notes.get().contains(&id) {
+                                        dragged_notes.set(marked_notes.get().clone());
+                                    } else {
+                                        dragged_notes.set(vec![id]);
+                                    }
+                                },
+                                // the notebook it was dropped on empties dragged_notes, a drag
+                                // that went nowhere leaves them
+                                ondragend: move |_| {
+                                    if dragged_notes.current().is_empty() {
+                                        marked_notes.set(vec![]);
+                                    } else {
+                                        dragged_notes.set(vec![]);
+                                    }
+                                },
+                                div {
+                                    "{note.title}"
+                                },
+                                div {
+                                    class: "text-gray-400 text-nowrap truncate",
+                                    "{note.content}"
+                                }
+                            }
+                        },
+                        if *loading_more.get() {
+                            rsx! {
+                                div {
+                                    class: "pl-2 py-1 text-gray-400 border-t border-gray-600",
+                                    "Loading..."
+                                }
+                            }
                         }
                     }
                 }
             }
         }
+    } else if let Some(Err(e)) = note_summaries.value() {
+        render! {
+            div {
+                class: "w-64 flex-shrink-0 h-full bg-gray-700",
+                ErrorToast {
+                    error: NotesError::from_server_fn_error(e),
+                    onretry: move |_| note_summaries.restart(),
+                }
+            }
+        }
     } else {
-        rsx! {
+        render! {
             div {
                 "Loading..."
             }
@@ -1,38 +1,177 @@
 use dioxus::prelude::*;
 use dioxus_fullstack::prelude::*;
+#[cfg(feature = "web")]
+use gloo_timers::future::TimeoutFuture;
 
+#[cfg(feature = "web")]
+use crate::{drafts, drops};
 use crate::{
-    component::loading::Loading,
-    delete_note, get_note,
-    model::{note::Note, notebook::Notebook},
-    upsert_note, NOTE_TABLE,
+    component::{
+        backlinks::Backlinks,
+        error_toast::ErrorToast,
+        loading::Loading,
+        markdown_preview::MarkdownPreview,
+        merge_dialog::MergeDialog,
+        note_history::NoteHistory,
+        note_tags::NoteTags,
+        related_notes::RelatedNotes,
+        save_indicator::{SaveIndicator, SaveState},
+    },
+    delete_note,
+    error::NotesError,
+    get_note, list_note_revisions,
+    model::{
+        draft::Draft,
+        note::Note,
+        notebook::Notebook,
+        revision::NoteRevision,
+        tag::Tag,
+    },
+    upload_attachment, upsert_note, NOTE_TABLE,
 };
 
+/// How long typing has to pause before the note is saved.
+#[cfg(feature = "web")]
+const AUTOSAVE_DELAY_MS: u32 = 1000;
+const CONTENT_ID: &str = "note-content";
+
+/// The editor state a save reads and updates. Saves outlive the render that started them, so
+/// this gets cloned into them.
+#[derive(Clone)]
+struct Editor {
+    selected_note: UseState<Option<Note>>,
+    title: UseState<String>,
+    content: UseState<String>,
+    base_version: UseState<u64>,
+    conflict: UseState<Option<Note>>,
+    save_state: UseState<SaveState>,
+    // bumped to schedule another autosave when there were edits during the last one
+    autosave: UseState<u64>,
+    note_summaries: UseFuture<Result<Vec<Note>, ServerFnError>>,
+    revisions: UseFuture<Result<Vec<NoteRevision>, ServerFnError>>,
+}
+
+impl Editor {
+    fn selected_id(&self) -> Option<String> {
+        self.selected_note
+            .current()
+            .as_ref()
+            .as_ref()
+            .and_then(|note| note.id.clone())
+    }
+
+    async fn save(self) {
+        // a second save would go out with the same base version and conflict with the first,
+        // the one in flight picks up newer edits itself. a conflict waits on the user
+        if *self.save_state.current() == SaveState::Saving || self.conflict.current().is_some() {
+            return;
+        }
+        let Some(Note {
+            id: Some(id),
+            notebook,
+            ..
+        }) = self.selected_note.current().as_ref().clone()
+        else {
+            return;
+        };
+        let title = self.title.current().as_ref().clone();
+        let content = self.content.current().as_ref().clone();
+        self.save_state.set(SaveState::Saving);
+
+        let res = upsert_note(Note {
+            id: Some(id.clone()),
+            title: title.clone(),
+            content: content.clone(),
+            version: *self.base_version.current(),
+            ..Note::new(notebook)
+        })
+        .await;
+        // another note was opened meanwhile and the editor belongs to that one now, whatever
+        // didn't make it is still in the draft
+        if self.selected_id().as_ref() != Some(&id) {
+            return;
+        }
+
+        match res {
+            Ok(_) => {
+                self.base_version.set(*self.base_version.current() + 1);
+                self.note_summaries.restart();
+                self.revisions.restart();
+                if *self.title.current() == title && *self.content.current() == content {
+                    self.save_state.set(SaveState::Saved);
+                    #[cfg(feature = "web")]
+                    drafts::clear(&id);
+                } else {
+                    self.save_state.set(SaveState::Unsaved);
+                    self.autosave.set(*self.autosave.current() + 1);
+                }
+            }
+            Err(e) => {
+                self.save_state.set(SaveState::Unsaved);
+                match NotesError::from_server_fn_error(&e) {
+                    NotesError::Conflict(theirs) => self.conflict.set(Some(*theirs)),
+                    e => log::error!("error saving note {}", e),
+                }
+            }
+        }
+    }
+}
+
 #[component]
 pub fn NotesView<'a>(
     cx: Scope,
     selected_note: UseState<Option<Note>>,
     note_summaries: &'a UseFuture<Result<Vec<Note>, ServerFnError>>,
     notebooks: &'a UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    tags: &'a UseFuture<Result<Vec<Tag>, ServerFnError>>,
+    save_requests: UseState<u64>,
 ) -> Element {
     let note_summaries = *note_summaries;
     let notebooks = *notebooks;
+    let tags = *tags;
     let new_title = use_state(cx, || "".to_string());
     let new_content = use_state(cx, || "".to_string());
+    let show_history = use_state(cx, || false);
+    let show_related = use_state(cx, || false);
+    let show_preview = use_state(cx, || true);
+    // the version the editor's text is based on, sent along with every save
+    let base_version = use_state(cx, || 0);
+    // the saved note when a save was rejected as stale
+    let conflict: &UseState<Option<Note>> = use_state(cx, || None);
+    let save_state = use_state(cx, || SaveState::Saved);
+    let autosave = use_state(cx, || 0);
+    // edits to the open note that an earlier session never saved
+    let recovered_draft: &UseState<Option<Draft>> = use_state(cx, || None);
+    let upload_error: &UseState<Option<NotesError>> = use_state(cx, || None);
 
     let new_content2 = new_content.clone();
     let new_title2 = new_title.clone();
+    let base_version2 = base_version.clone();
+    let conflict2 = conflict.clone();
+    let save_state2 = save_state.clone();
+    #[cfg(feature = "web")]
+    let recovered_draft2 = recovered_draft.clone();
     let full_note: &UseFuture<Option<Result<Note, ServerFnError>>> =
         use_future(cx, (selected_note), |selected_note| async move {
             if let Some(Note { id: Some(id), .. }) = selected_note.current().as_ref() {
                 let note_res = get_note(id.clone()).await;
-                match note_res.clone() {
-                    Ok(note) => {
-                        new_content2.set(note.content);
-                        new_title2.set(note.title);
-                    }
-                    _ => {}
-                };
+                if let Ok(note) = note_res.clone() {
+                    #[cfg(feature = "web")]
+                    recovered_draft2.set(match drafts::load(id) {
+                        Some(draft)
+                            if draft.title == note.title && draft.content == note.content =>
+                        {
+                            drafts::clear(id);
+                            None
+                        }
+                        draft => draft,
+                    });
+                    new_content2.set(note.content);
+                    new_title2.set(note.title);
+                    base_version2.set(note.version);
+                    conflict2.set(None);
+                    save_state2.set(SaveState::Saved);
+                }
 
                 Some(note_res)
             } else {
@@ -40,73 +179,343 @@ pub fn NotesView<'a>(
             }
         });
 
+    let revisions: &UseFuture<Result<Vec<NoteRevision>, ServerFnError>> = use_future(
+        cx,
+        (selected_note, show_history),
+        |(selected_note, show_history)| async move {
+            match selected_note.current().as_ref() {
+                Some(Note { id: Some(id), .. }) if *show_history.get() => {
+                    list_note_revisions(id.clone()).await
+                }
+                _ => Ok(vec![]),
+            }
+        },
+    );
+
+    let editor = Editor {
+        selected_note: selected_note.clone(),
+        title: new_title.clone(),
+        content: new_content.clone(),
+        base_version: base_version.clone(),
+        conflict: conflict.clone(),
+        save_state: save_state.clone(),
+        autosave: autosave.clone(),
+        note_summaries: note_summaries.clone(),
+        revisions: revisions.clone(),
+    };
+
+    // the save shortcut, which like the Save button doesn't wait for the autosave
+    use_effect(cx, (save_requests), |_| {
+        to_owned!(editor);
+        async move {
+            if *editor.save_state.current() == SaveState::Unsaved
+                && editor.conflict.current().is_none()
+            {
+                editor.save().await;
+            }
+        }
+    });
+
+    // every edit restarts this, so it only gets to save once typing pauses. the draft is
+    // written straight away so closing the tab mid-pause doesn't lose anything
+    #[cfg(feature = "web")]
+    use_future(cx, (new_title, new_content, conflict, autosave), |_| {
+        to_owned!(editor);
+        async move {
+            if *editor.save_state.current() == SaveState::Saved {
+                return;
+            }
+            let Some(id) = editor.selected_id() else {
+                return;
+            };
+            drafts::store(
+                &id,
+                &Draft {
+                    title: editor.title.current().as_ref().clone(),
+                    content: editor.content.current().as_ref().clone(),
+                    base_version: *editor.base_version.current(),
+                },
+            );
+            // a save in flight picks up newer edits itself, and a conflict waits on the user
+            if *editor.save_state.current() != SaveState::Unsaved
+                || editor.conflict.current().is_some()
+            {
+                return;
+            }
+
+            TimeoutFuture::new(AUTOSAVE_DELAY_MS).await;
+            // the Save button or the shortcut may have saved or started saving meanwhile
+            if *editor.save_state.current() != SaveState::Unsaved {
+                return;
+            }
+            // spawned apart from this future, so the next keystroke can't cancel it halfway
+            wasm_bindgen_futures::spawn_local(editor.save());
+        }
+    });
+
+    // viewers of a shared notebook can read and browse the history but not change anything
+    let read_only = match (full_note.value(), notebooks.value()) {
+        (Some(Some(Ok(note))), Some(Ok(list))) => !list
+            .iter()
+            .any(|nb| nb.id.as_ref() == Some(&note.notebook) && nb.can_edit()),
+        _ => true,
+    };
+    // the note dropped files get attached to, once its textarea is on the page
+    let drop_target = match full_note.state() {
+        UseFutureState::Complete(Some(Ok(note))) if !read_only => note.id.clone(),
+        _ => None,
+    };
+
+    // dropped files are uploaded and linked at the end of the note
+    #[cfg(feature = "web")]
+    use_future(cx, (&drop_target,), |(drop_target,)| {
+        to_owned!(new_content);
+        to_owned!(save_state);
+        to_owned!(upload_error);
+        async move {
+            let Some(note_id) = drop_target else {
+                return;
+            };
+            let Some(mut drops) = drops::FileDrops::listen(CONTENT_ID) else {
+                return;
+            };
+            while let Some((file_name, bytes)) = drops.next().await {
+                match upload_attachment(note_id.clone(), file_name, bytes).await {
+                    Ok(attachment) => {
+                        let mut content = new_content.current().as_ref().clone();
+                        if !content.is_empty() && !content.ends_with('\n') {
+                            content.push('\n');
+                        }
+                        content.push_str(&attachment.markdown());
+                        content.push('\n');
+                        new_content.set(content);
+                        if *save_state.current() == SaveState::Saved {
+                            save_state.set(SaveState::Unsaved);
+                        }
+                        upload_error.set(None);
+                    }
+                    Err(e) => upload_error.set(Some(NotesError::from_server_fn_error(&e))),
+                }
+            }
+        }
+    });
+
+    let preview_label = if *show_preview.get() {
+        "Hide preview"
+    } else {
+        "Preview"
+    };
+    let history_label = if *show_history.get() {
+        "Hide history"
+    } else {
+        "History"
+    };
+    let related_label = if *show_related.get() {
+        "Hide related"
+    } else {
+        "Related"
+    };
+
     match full_note.state() {
         UseFutureState::Complete(Some(Ok(note))) => {
             render! {
                 div {
                     class:"h-full bg-gray-800 flex flex-col items-center justify-center p-8 gap-4 text-white grow",
-                    input {
-                        placeholder: "Title",
-                        class: "w-full p-2 bg-gray-700 border border-gray-600 rounded-md shrink focus:outline-none focus:ring-0",
-                        value: "{new_title}",
-                        oninput: move |evt: Event<FormData>| {
-                            new_title.set(evt.value.clone());
+                    if let Some(draft) = recovered_draft.get() {
+                        rsx! {
+                            div {
+                                class: "w-full flex flex-row items-center gap-4 p-2 bg-gray-700 border border-yellow-600 rounded-md",
+                                div {
+                                    class: "grow",
+                                    "This note has changes from an earlier visit that weren't saved"
+                                },
+                                button {
+                                    class: "px-4 py-1 bg-blue-500 hover:bg-blue-600 rounded-md",
+                                    onclick: move |_| {
+                                        new_title.set(draft.title.clone());
+                                        new_content.set(draft.content.clone());
+                                        // a newer save since then shows up as a conflict
+                                        base_version.set(draft.base_version);
+                                        save_state.set(SaveState::Unsaved);
+                                        recovered_draft.set(None);
+                                    },
+                                    "Restore"
+                                },
+                                button {
+                                    class: "text-gray-400",
+                                    onclick: move |_| {
+                                        #[cfg(feature = "web")]
+                                        if let Some(id) = note.id.as_ref() {
+                                            drafts::clear(id);
+                                        }
+                                        recovered_draft.set(None);
+                                    },
+                                    "Discard"
+                                }
+                            }
+                        }
+                    },
+                    div {
+                        class: "w-full flex flex-row items-center gap-4",
+                        input {
+                            placeholder: "Title",
+                            class: "grow p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                            value: "{new_title}",
+                            readonly: read_only,
+                            oninput: move |evt: Event<FormData>| {
+                                new_title.set(evt.value.clone());
+                                if *save_state.get() == SaveState::Saved {
+                                    save_state.set(SaveState::Unsaved);
+                                }
+                            },
                         },
+                        if !read_only {
+                            rsx! {
+                                SaveIndicator {
+                                    state: *save_state.get(),
+                                }
+                            }
+                        }
+                    },
+                    if let Some(id) = note.id.as_ref() {
+                        rsx! {
+                            NoteTags {
+                                note_id: id.clone(),
+                                tags: tags,
+                                note_summaries: note_summaries,
+                                read_only: read_only,
+                            },
+                            Backlinks {
+                                note_id: id.clone(),
+                                title: new_title.clone(),
+                                saved: *save_state.get() == SaveState::Saved,
+                                selected_note: selected_note.clone(),
+                                note_summaries: note_summaries,
+                                read_only: read_only,
+                            }
+                        }
                     },
-                    textarea {
-                        placeholder: "Content",
-                        value: "{new_content}",
-                        class: "w-full p-2 bg-gray-700 border border-gray-600 rounded-md resize-none grow focus:outline-none focus:ring-0",
-                        oninput: move |evt: Event<FormData>| {
-                            new_content.set(evt.value.clone());
+                    div {
+                        class: "w-full grow flex flex-row gap-4 min-h-0",
+                        textarea {
+                            id: CONTENT_ID,
+                            placeholder: "Content",
+                            value: "{new_content}",
+                            readonly: read_only,
+                            class: "w-full h-full p-2 bg-gray-700 border border-gray-600 rounded-md resize-none focus:outline-none focus:ring-0 font-mono",
+                            oninput: move |evt: Event<FormData>| {
+                                new_content.set(evt.value.clone());
+                                if *save_state.get() == SaveState::Saved {
+                                    save_state.set(SaveState::Unsaved);
+                                }
+                            },
                         },
+                        if *show_preview.get() {
+                            rsx! {
+                                MarkdownPreview {
+                                    content: new_content.clone(),
+                                    save_state: save_state.clone(),
+                                    read_only: read_only,
+                                }
+                            }
+                        }
+                    },
+                    if let Some(error) = upload_error.get() {
+                        rsx! {
+                            div {
+                                class: "text-red-500",
+                                onclick: move |_| upload_error.set(None),
+                                "Upload failed: {error}"
+                            }
+                        }
+                    },
+                    if !read_only {
+                        rsx! {
+                            button {
+                                class: "px-4 py-2 bg-blue-500 hover:bg-blue-600 rounded-md shrink disabled:bg-neutral-600",
+                                // saves right away instead of waiting for the autosave
+                                disabled: *save_state.get() != SaveState::Unsaved || conflict.get().is_some(),
+                                onclick: move |_| cx.spawn(editor.clone().save()),
+                                "Save"
+                            },
+                            button {
+                                class: "text-red-500",
+                                onclick: move |_| {
+                                    cx.spawn({
+                                        to_owned!(note);
+                                        to_owned!(note_summaries);
+                                        to_owned!(notebooks);
+                                        to_owned!(tags);
+                                        to_owned!(selected_note);
+                                        async move {
+                                            if let Some(id) = note.id {
+                                                let res = delete_note(id).await;
+                                                if let Ok(()) = res {
+                                                    selected_note.set(None);
+                                                    note_summaries.restart();
+                                                    notebooks.restart();
+                                                    tags.restart();
+                                                } else {
+                                                    log::error!("error deleting note");
+                                                }
+                                            }
+                                        }
+                                    })
+                                },
+                                "Delete",
+                            }
+                        }
                     },
                     button {
-                        class: "px-4 py-2 bg-blue-500 hover:bg-blue-600 rounded-md shrink disabled:bg-neutral-600",
-                        disabled: &note.title == new_title.current().as_ref() && &note.content == new_content.current().as_ref(),
+                        class: "text-gray-400",
                         onclick: move |_| {
-                            cx.spawn({
-                                to_owned!(new_content);
-                                to_owned!(new_title);
-                                to_owned!(note_summaries);
-                                to_owned!(note);
-                                async move {
-                                    let new_note = Note {
-                                        id: note.id.clone(),
-                                        title: new_title.current().as_ref().clone(),
-                                        content: new_content.current().as_ref().clone(),
-                                        notebook: note.notebook.clone(),
-                                    };
-                                    let _ = upsert_note(new_note).await;
-                                    note_summaries.restart();
-                                }
-                            })
+                            show_preview.set(!show_preview.get());
                         },
-                        "Save"
+                        "{preview_label}"
                     },
                     button {
-                        class: "text-red-500",
+                        class: "text-gray-400",
                         onclick: move |_| {
-                            cx.spawn({
-                                to_owned!(note);
-                                to_owned!(note_summaries);
-                                to_owned!(notebooks);
-                                to_owned!(selected_note);
-                                async move {
-                                    if let Some(id) = note.id {
-                                        let res = delete_note(id).await;
-                                        if let Ok(()) = res {
-                                            selected_note.set(None);
-                                            note_summaries.restart();
-                                            notebooks.restart();
-                                        } else {
-                                            log::error!("error deleting note");
-                                        }
-                                    }
-                                }
-                            })
+                            show_history.set(!show_history.get());
                         },
-                        "Delete",
+                        "{history_label}"
+                    },
+                    button {
+                        class: "text-gray-400",
+                        onclick: move |_| {
+                            show_related.set(!show_related.get());
+                        },
+                        "{related_label}"
+                    },
+                    if *show_history.get() {
+                        rsx! {
+                            NoteHistory {
+                                revisions: revisions,
+                                full_note: full_note,
+                                note_summaries: note_summaries,
+                                read_only: read_only,
+                            }
+                        }
+                    },
+                    if let (true, Some(id)) = (*show_related.get(), note.id.as_ref()) {
+                        rsx! {
+                            RelatedNotes {
+                                note_id: id.clone(),
+                                selected_note: selected_note.clone(),
+                            }
+                        }
+                    },
+                    if let Some(theirs) = conflict.get() {
+                        rsx! {
+                            MergeDialog {
+                                theirs: theirs.clone(),
+                                new_title: new_title.clone(),
+                                new_content: new_content.clone(),
+                                base_version: base_version.clone(),
+                                conflict: conflict.clone(),
+                                save_state: save_state.clone(),
+                            }
+                        }
                     }
                 }
             }
@@ -135,6 +544,17 @@ pub fn NotesView<'a>(
                 }
             }
         }
+        UseFutureState::Complete(Some(Err(e))) => {
+            render! {
+                div {
+                    class: "h-full w-full bg-gray-800",
+                    ErrorToast {
+                        error: NotesError::from_server_fn_error(e),
+                        onretry: move |_| full_note.restart(),
+                    }
+                }
+            }
+        }
         _ => {
             render! {
                 Loading {
@@ -0,0 +1,66 @@
+use dioxus::prelude::*;
+use dioxus_fullstack::prelude::*;
+
+use crate::{
+    model::{note::Note, related::RelatedNote},
+    related_notes,
+};
+
+const RELATED_NOTES_SHOWN: u32 = 10;
+
+#[component]
+pub fn RelatedNotes(cx: Scope, note_id: String, selected_note: UseState<Option<Note>>) -> Element {
+    let related: &UseFuture<Result<Vec<RelatedNote>, ServerFnError>> =
+        use_future(cx, (note_id), |note_id| {
+            related_notes(note_id, RELATED_NOTES_SHOWN)
+        });
+
+    match related.value() {
+        Some(Ok(list)) if list.is_empty() => render! {
+            div {
+                class: "text-gray-400",
+                "Nothing related yet"
+            }
+        },
+        Some(Ok(list)) => render! {
+            div {
+                class: "w-full max-h-[40%] flex flex-col overflow-y-auto border border-gray-600 rounded-md",
+                for note in list {
+                    div {
+                        class: "flex flex-row items-center gap-2 px-2 border-b border-gray-600 hover:bg-gray-700 cursor-default",
+                        onclick: move |_| {
+                            selected_note.set(Some(Note {
+                                id: Some(note.id.clone()),
+                                title: note.title.clone(),
+                                ..Note::new(note.notebook.clone())
+                            }))
+                        },
+                        div {
+                            class: "grow truncate",
+                            "{note.title}"
+                        },
+                        div {
+                            class: "text-gray-400 text-sm",
+                            "{note.similarity()}"
+                        }
+                    }
+                }
+            }
+        },
+        Some(Err(e)) => {
+            log::error!("error finding related notes {:?}", e);
+            render! {
+                div {
+                    class: "text-red-500",
+                    "Couldn't find related notes"
+                }
+            }
+        }
+        None => render! {
+            div {
+                class: "text-gray-400",
+                "Finding related notes..."
+            }
+        },
+    }
+}
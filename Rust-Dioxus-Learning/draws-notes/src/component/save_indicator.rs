@@ -0,0 +1,25 @@
+use dioxus::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaveState {
+    Saved,
+    /// edited since the last save, an autosave is on its way
+    Unsaved,
+    Saving,
+}
+
+#[component]
+pub fn SaveIndicator(cx: Scope, state: SaveState) -> Element {
+    let (style, label) = match state {
+        SaveState::Saved => ("text-gray-400", "Saved"),
+        SaveState::Unsaved => ("text-yellow-500", "Unsaved changes"),
+        SaveState::Saving => ("text-gray-400", "Saving..."),
+    };
+
+    render! {
+        div {
+            class: "text-sm {style}",
+            "{label}"
+        }
+    }
+}
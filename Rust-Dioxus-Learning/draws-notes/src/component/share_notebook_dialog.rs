@@ -0,0 +1,178 @@
+use dioxus::prelude::*;
+use dioxus_fullstack::prelude::*;
+
+use crate::{
+    error::NotesError,
+    get_notebook_shares,
+    model::{
+        notebook::Notebook,
+        share::{NotebookShare, Role},
+    },
+    share_notebook, unshare_notebook,
+};
+
+#[component]
+pub fn ShareNotebookDialog(
+    cx: Scope,
+    notebook: Notebook,
+    sharing_notebook: UseState<Option<Notebook>>,
+) -> Element {
+    let username: &UseState<String> = use_state(cx, || "".to_string());
+    let role: &UseState<Role> = use_state(cx, || Role::Viewer);
+    let error: &UseState<Option<String>> = use_state(cx, || None);
+
+    let notebook_id = notebook.id.clone().unwrap_or_default();
+    let shares: &UseFuture<Result<Vec<NotebookShare>, ServerFnError>> =
+        use_future(cx, (&notebook_id), get_notebook_shares);
+
+    let shares_list = match shares.value() {
+        Some(Ok(list)) if list.is_empty() => rsx! {
+            div {
+                class: "text-gray-400",
+                "Not shared with anyone yet"
+            }
+        },
+        Some(Ok(list)) => rsx! {
+            for share in list {
+                div {
+                    class: "flex flex-row items-center gap-2",
+                    div {
+                        class: "grow",
+                        "{share.username}"
+                    },
+                    div {
+                        class: "text-gray-400 text-sm",
+                        "{share.role.label()}"
+                    },
+                    button {
+                        class: "text-red-500 text-sm",
+                        onclick: move |_| {
+                            cx.spawn({
+                                to_owned!(share);
+                                to_owned!(shares);
+                                to_owned!(error);
+                                async move {
+                                    match unshare_notebook(share.notebook, share.username).await {
+                                        Ok(()) => shares.restart(),
+                                        Err(e) => {
+                                            log::error!("error unsharing notebook {:?}", e);
+                                            error.set(Some("Couldn't remove access".to_string()));
+                                        }
+                                    }
+                                }
+                            })
+                        },
+                        "Remove"
+                    }
+                }
+            }
+        },
+        Some(Err(e)) => {
+            log::error!("error getting shares {:?}", e);
+            rsx! {
+                div {
+                    class: "text-red-500",
+                    "Couldn't load who this is shared with"
+                }
+            }
+        }
+        None => rsx! {
+            div {
+                class: "text-gray-400",
+                "Loading..."
+            }
+        },
+    };
+
+    render! {
+        div {
+            class: "fixed inset-0 bg-black/50 flex items-center justify-center z-10",
+            div {
+                class: "bg-gray-800 border border-gray-600 rounded-md p-4 flex flex-col gap-4 w-96",
+                div {
+                    class: "text-xl",
+                    "Share \"{notebook.name}\""
+                },
+                shares_list,
+                div {
+                    class: "flex flex-row gap-2",
+                    input {
+                        placeholder: "Username",
+                        class: "grow p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                        value: "{username}",
+                        oninput: move |evt| {
+                            username.set(evt.value.clone())
+                        },
+                    },
+                    select {
+                        class: "p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                        value: "{role.get().label()}",
+                        onchange: move |evt| {
+                            role.set(if evt.value == Role::Editor.label() {
+                                Role::Editor
+                            } else {
+                                Role::Viewer
+                            })
+                        },
+                        option {
+                            value: "{Role::Viewer.label()}",
+                            "Can view"
+                        },
+                        option {
+                            value: "{Role::Editor.label()}",
+                            "Can edit"
+                        }
+                    }
+                },
+                if let Some(error) = error.get() {
+                    rsx! {
+                        div {
+                            class: "text-red-500",
+                            "{error}"
+                        }
+                    }
+                },
+                div {
+                    class: "flex flex-row justify-end gap-2",
+                    button {
+                        class: "px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-md",
+                        onclick: move |_| {
+                            sharing_notebook.set(None);
+                        },
+                        "Done"
+                    },
+                    button {
+                        class: "px-4 py-2 bg-blue-500 hover:bg-blue-600 rounded-md disabled:bg-neutral-600",
+                        disabled: username.get().trim().is_empty(),
+                        onclick: move |_| {
+                            cx.spawn({
+                                to_owned!(notebook_id);
+                                to_owned!(username);
+                                to_owned!(role);
+                                to_owned!(shares);
+                                to_owned!(error);
+                                async move {
+                                    match share_notebook(notebook_id, username.current().to_string(), *role.get()).await {
+                                        Ok(()) => {
+                                            username.set("".to_string());
+                                            error.set(None);
+                                            shares.restart();
+                                        }
+                                        Err(e) => match NotesError::from_server_fn_error(&e) {
+                                            NotesError::Db(_) => {
+                                                log::error!("error sharing notebook {:?}", e);
+                                                error.set(Some("Couldn't share the notebook".to_string()));
+                                            }
+                                            e => error.set(Some(e.to_string())),
+                                        },
+                                    }
+                                }
+                            })
+                        },
+                        "Share"
+                    }
+                }
+            }
+        }
+    }
+}
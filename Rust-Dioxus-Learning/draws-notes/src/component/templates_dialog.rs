@@ -0,0 +1,193 @@
+use dioxus::prelude::*;
+use dioxus_fullstack::prelude::*;
+
+use crate::{delete_template, error::NotesError, model::template::Template, upsert_template};
+
+const TEMPLATE_STYLE: &str = "px-2 py-1 rounded-md hover:bg-gray-700";
+const SELECTED_TEMPLATE_STYLE: &str = "px-2 py-1 rounded-md bg-gray-700";
+
+/// Lists the user's templates next to a form for editing the one picked, or a new one.
+#[component]
+pub fn TemplatesDialog<'a>(
+    cx: Scope,
+    templates: &'a UseFuture<Result<Vec<Template>, ServerFnError>>,
+    editing_templates: UseState<bool>,
+) -> Element {
+    let templates = *templates;
+    let draft: &UseState<Template> = use_state(cx, Template::new);
+    let error: &UseState<Option<NotesError>> = use_state(cx, || None);
+
+    let templates_list = match templates.value() {
+        Some(Ok(list)) => rsx! {
+            for template in list {
+                div {
+                    class: if template.id == draft.get().id { SELECTED_TEMPLATE_STYLE } else { TEMPLATE_STYLE },
+                    onclick: move |_| {
+                        draft.set(template.clone());
+                        error.set(None);
+                    },
+                    "{template.name}"
+                }
+            }
+        },
+        Some(Err(e)) => {
+            log::error!("error getting templates {:?}", e);
+            rsx! {
+                div {
+                    class: "text-red-500",
+                    "Couldn't load templates"
+                }
+            }
+        }
+        None => rsx! {
+            div {
+                class: "text-gray-400",
+                "Loading..."
+            }
+        },
+    };
+
+    render! {
+        div {
+            class: "fixed inset-0 bg-black/50 flex items-center justify-center z-10",
+            div {
+                class: "bg-gray-800 border border-gray-600 rounded-md p-4 flex flex-col gap-4 w-[40rem]",
+                div {
+                    class: "text-xl",
+                    "Templates"
+                },
+                div {
+                    class: "flex flex-row gap-4",
+                    div {
+                        class: "w-40 flex flex-col gap-1 overflow-y-auto max-h-80",
+                        templates_list,
+                        div {
+                            class: "{TEMPLATE_STYLE} text-gray-400",
+                            onclick: move |_| {
+                                draft.set(Template::new());
+                                error.set(None);
+                            },
+                            "+ New template"
+                        }
+                    },
+                    div {
+                        class: "grow flex flex-col gap-2",
+                        input {
+                            placeholder: "Template name",
+                            class: "p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                            value: "{draft.get().name}",
+                            oninput: move |evt| {
+                                draft.set(Template {
+                                    name: evt.value.clone(),
+                                    ..draft.get().clone()
+                                })
+                            },
+                        },
+                        input {
+                            placeholder: "Note title",
+                            class: "p-2 bg-gray-700 border border-gray-600 rounded-md focus:outline-none focus:ring-0",
+                            value: "{draft.get().title}",
+                            oninput: move |evt| {
+                                draft.set(Template {
+                                    title: evt.value.clone(),
+                                    ..draft.get().clone()
+                                })
+                            },
+                        },
+                        textarea {
+                            placeholder: "Note content",
+                            class: "h-48 p-2 bg-gray-700 border border-gray-600 rounded-md resize-none focus:outline-none focus:ring-0 font-mono",
+                            value: "{draft.get().content}",
+                            oninput: move |evt| {
+                                draft.set(Template {
+                                    content: evt.value.clone(),
+                                    ..draft.get().clone()
+                                })
+                            },
+                        },
+                        div {
+                            class: "text-gray-400 text-sm",
+                            "{{{{date}}}}, {{{{time}}}} and {{{{notebook}}}} are filled in when a note is made from it"
+                        }
+                    }
+                },
+                if let Some(error) = error.get() {
+                    rsx! {
+                        div {
+                            class: "text-red-500",
+                            "{error}"
+                        }
+                    }
+                },
+                div {
+                    class: "flex flex-row justify-end gap-2",
+                    if let Some(template_id) = draft.get().id.clone() {
+                        rsx! {
+                            button {
+                                class: "px-4 py-2 text-red-500 hover:bg-gray-700 rounded-md",
+                                onclick: move |_| {
+                                    cx.spawn({
+                                        to_owned!(template_id);
+                                        to_owned!(templates);
+                                        to_owned!(draft);
+                                        to_owned!(error);
+                                        async move {
+                                            match delete_template(template_id).await {
+                                                Ok(()) => {
+                                                    draft.set(Template::new());
+                                                    error.set(None);
+                                                    templates.restart();
+                                                }
+                                                Err(e) => {
+                                                    log::error!("error deleting template {:?}", e);
+                                                    error.set(Some(NotesError::from_server_fn_error(&e)));
+                                                }
+                                            }
+                                        }
+                                    })
+                                },
+                                "Delete"
+                            }
+                        }
+                    },
+                    button {
+                        class: "px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded-md",
+                        onclick: move |_| {
+                            editing_templates.set(false);
+                        },
+                        "Done"
+                    },
+                    button {
+                        class: "px-4 py-2 bg-blue-500 hover:bg-blue-600 rounded-md disabled:bg-neutral-600",
+                        disabled: draft.get().name.trim().is_empty(),
+                        onclick: move |_| {
+                            cx.spawn({
+                                to_owned!(templates);
+                                to_owned!(draft);
+                                to_owned!(error);
+                                async move {
+                                    let template = draft.current().as_ref().clone();
+                                    match upsert_template(template.clone()).await {
+                                        Ok(id) => {
+                                            draft.set(Template {
+                                                id: Some(id),
+                                                ..template
+                                            });
+                                            error.set(None);
+                                            templates.restart();
+                                        }
+                                        Err(e) => {
+                                            log::error!("error saving template {:?}", e);
+                                            error.set(Some(NotesError::from_server_fn_error(&e)));
+                                        }
+                                    }
+                                }
+                            })
+                        },
+                        "Save"
+                    }
+                }
+            }
+        }
+    }
+}
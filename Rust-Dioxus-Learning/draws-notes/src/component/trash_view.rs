@@ -0,0 +1,118 @@
+use dioxus::prelude::*;
+use dioxus_fullstack::prelude::*;
+
+use crate::{
+    component::{error_toast::ErrorToast, loading::Loading},
+    delete_permanently,
+    error::NotesError,
+    get_trash,
+    model::{notebook::Notebook, tag::Tag, trash::TrashItem},
+    restore_from_trash,
+};
+
+#[component]
+pub fn TrashView<'a>(
+    cx: Scope,
+    notebooks: &'a UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    tags: &'a UseFuture<Result<Vec<Tag>, ServerFnError>>,
+) -> Element {
+    let notebooks = *notebooks;
+    let tags = *tags;
+    let trash: &UseFuture<Result<Vec<TrashItem>, ServerFnError>> =
+        use_future(cx, (), |_| get_trash());
+    let error: &UseState<Option<NotesError>> = use_state(cx, || None);
+
+    // restoring or deleting changes the counts and tags on the notebook bar either way
+    let act = move |item: TrashItem, restore: bool| {
+        cx.spawn({
+            to_owned!(trash);
+            to_owned!(notebooks);
+            to_owned!(tags);
+            to_owned!(error);
+            async move {
+                let res = if restore {
+                    restore_from_trash(item.id).await
+                } else {
+                    delete_permanently(item.id).await
+                };
+                match res {
+                    Ok(()) => {
+                        error.set(None);
+                        trash.restart();
+                        notebooks.restart();
+                        tags.restart();
+                    }
+                    Err(e) => error.set(Some(NotesError::from_server_fn_error(&e))),
+                }
+            }
+        })
+    };
+
+    let items = match trash.value() {
+        Some(Ok(items)) if items.is_empty() => rsx! {
+            div {
+                class: "text-gray-400",
+                "The trash is empty"
+            }
+        },
+        Some(Ok(items)) => rsx! {
+            for item in items {
+                div {
+                    class: "w-full flex flex-row items-center gap-4 py-2 border-t border-gray-600",
+                    div {
+                        class: "grow flex flex-col min-w-0",
+                        div {
+                            class: "truncate",
+                            "{item.name}"
+                        },
+                        div {
+                            class: "text-gray-400 text-sm",
+                            "{item.description()}, deleted {item.deleted_on()}, gone for good after {item.purged_on()}"
+                        }
+                    },
+                    button {
+                        class: "px-4 py-1 bg-gray-700 hover:bg-gray-600 rounded-md",
+                        onclick: move |_| act(item.clone(), true),
+                        "Restore"
+                    },
+                    button {
+                        class: "text-red-500",
+                        onclick: move |_| act(item.clone(), false),
+                        "Delete permanently"
+                    }
+                }
+            }
+        },
+        Some(Err(e)) => rsx! {
+            ErrorToast {
+                error: NotesError::from_server_fn_error(e),
+                onretry: move |_| trash.restart(),
+            }
+        },
+        None => rsx! {
+            Loading {
+                fullscreen: false,
+            }
+        },
+    };
+
+    render! {
+        div {
+            class: "h-full grow bg-gray-800 flex flex-col p-8 gap-4 text-white overflow-y-auto",
+            div {
+                class: "text-xl",
+                "Trash"
+            },
+            if let Some(message) = error.get() {
+                rsx! {
+                    div {
+                        class: "text-red-500",
+                        onclick: move |_| error.set(None),
+                        "{message}"
+                    }
+                }
+            },
+            items
+        }
+    }
+}
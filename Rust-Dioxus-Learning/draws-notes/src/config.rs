@@ -0,0 +1,204 @@
+use std::{collections::HashMap, env, fs, path::PathBuf, time::Duration};
+
+use serde::Deserialize;
+
+use crate::model::shortcut::{ShortcutAction, Shortcuts};
+
+const CONFIG_FILE: &str = "Dioxus.toml";
+const ENV_PREFIX: &str = "DRAWS_DB_";
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8000";
+const DEFAULT_PATH: &str = "data/notes.db";
+const DEFAULT_NAMESPACE: &str = "test";
+const DEFAULT_DATABASE: &str = "test";
+const ATTACHMENTS_ENV: &str = "DRAWS_ATTACHMENTS_PATH";
+const DEFAULT_ATTACHMENTS_PATH: &str = "data/attachments";
+const TRASH_RETENTION_ENV: &str = "DRAWS_TRASH_RETENTION_DAYS";
+const DEFAULT_TRASH_RETENTION_DAYS: u64 = 30;
+const EMBEDDINGS_MODEL_ENV: &str = "DRAWS_EMBEDDINGS_MODEL";
+const DEFAULT_EMBEDDINGS_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+/// Where the notes are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Storage {
+    /// a separately running surrealdb server, reached over websockets
+    Remote(String),
+    RocksDb(PathBuf),
+    SurrealKv(PathBuf),
+    /// gone when the process exits, for tests and trying things out
+    Memory,
+}
+
+impl Storage {
+    /// The endpoint surrealdb's `any` engine understands for this storage.
+    pub fn endpoint(&self) -> String {
+        match self {
+            Storage::Remote(address) => format!("ws://{}", address),
+            Storage::RocksDb(path) => format!("rocksdb://{}", path.display()),
+            Storage::SurrealKv(path) => format!("surrealkv://{}", path.display()),
+            Storage::Memory => "mem://".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbConfig {
+    pub storage: Storage,
+    pub namespace: String,
+    pub database: String,
+}
+
+/// The `[database]` table of Dioxus.toml. Every key can be overridden by the matching
+/// `DRAWS_DB_*` environment variable, e.g. `DRAWS_DB_ENGINE=memory`.
+#[derive(Debug, Default, Deserialize)]
+struct DatabaseSection {
+    engine: Option<String>,
+    address: Option<String>,
+    path: Option<String>,
+    namespace: Option<String>,
+    database: Option<String>,
+}
+
+/// The `[attachments]` table of Dioxus.toml, `path` can be overridden by
+/// `DRAWS_ATTACHMENTS_PATH`.
+#[derive(Debug, Default, Deserialize)]
+struct AttachmentsSection {
+    path: Option<String>,
+}
+
+/// The `[trash]` table of Dioxus.toml, `retention_days` can be overridden by
+/// `DRAWS_TRASH_RETENTION_DAYS`.
+#[derive(Debug, Default, Deserialize)]
+struct TrashSection {
+    retention_days: Option<u64>,
+}
+
+/// The `[embeddings]` table of Dioxus.toml, `model` can be overridden by
+/// `DRAWS_EMBEDDINGS_MODEL`.
+#[derive(Debug, Default, Deserialize)]
+struct EmbeddingsSection {
+    model: Option<String>,
+}
+
+/// The `[shortcuts]` table of Dioxus.toml, from an action like `new_note` to its key binding
+/// like `"alt+n"`. Actions left out keep their default.
+#[derive(Debug, Default, Deserialize)]
+struct ShortcutsSection(HashMap<String, String>);
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    database: DatabaseSection,
+    #[serde(default)]
+    attachments: AttachmentsSection,
+    #[serde(default)]
+    trash: TrashSection,
+    #[serde(default)]
+    embeddings: EmbeddingsSection,
+    #[serde(default)]
+    shortcuts: ShortcutsSection,
+}
+
+fn read_config_file() -> Result<ConfigFile, String> {
+    // no config file just means the defaults
+    match fs::read_to_string(CONFIG_FILE) {
+        Ok(text) => toml::from_str::<ConfigFile>(&text)
+            .map_err(|e| format!("couldn't parse {}: {}", CONFIG_FILE, e)),
+        Err(_) => Ok(ConfigFile::default()),
+    }
+}
+
+/// The directory uploaded attachments are kept in.
+pub fn attachments_dir() -> Result<PathBuf, String> {
+    let file = read_config_file()?.attachments;
+    Ok(env::var(ATTACHMENTS_ENV)
+        .ok()
+        .or(file.path)
+        .unwrap_or_else(|| DEFAULT_ATTACHMENTS_PATH.to_string())
+        .into())
+}
+
+/// How long deleted notes and notebooks stay in the trash before they're gone for good.
+pub fn trash_retention() -> Result<Duration, String> {
+    let days = match env::var(TRASH_RETENTION_ENV) {
+        Ok(days) => days
+            .parse()
+            .map_err(|_| format!("{} should be a number of days", TRASH_RETENTION_ENV))?,
+        Err(_) => read_config_file()?
+            .trash
+            .retention_days
+            .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS),
+    };
+    Ok(Duration::from_secs(days * 24 * 60 * 60))
+}
+
+/// The Hugging Face model notes are embedded with to find related ones, `None` when that's
+/// turned off with `model = "none"`.
+pub fn embeddings_model() -> Result<Option<String>, String> {
+    let model = match env::var(EMBEDDINGS_MODEL_ENV) {
+        Ok(model) => model,
+        Err(_) => read_config_file()?
+            .embeddings
+            .model
+            .unwrap_or_else(|| DEFAULT_EMBEDDINGS_MODEL.to_string()),
+    };
+    Ok(match model.trim() {
+        "" | "none" => None,
+        model => Some(model.to_string()),
+    })
+}
+
+/// The app's key bindings, the defaults overridden by whatever the `[shortcuts]` table sets.
+pub fn shortcuts() -> Result<Shortcuts, String> {
+    let mut file = read_config_file()?.shortcuts.0;
+    let mut shortcuts = Shortcuts::default();
+    for (action, binding) in shortcuts.bindings.iter_mut() {
+        if let Some(text) = file.remove(action.config_key()) {
+            *binding = text
+                .parse()
+                .map_err(|e| format!("invalid shortcut for {}: {}", action.config_key(), e))?;
+        }
+    }
+    match file.keys().next() {
+        Some(unknown) => Err(format!("unknown shortcut {}", unknown)),
+        None => Ok(shortcuts),
+    }
+}
+
+impl DbConfig {
+    pub fn load() -> Result<DbConfig, String> {
+        let file = read_config_file()?.database;
+        let setting = |key: &str, from_file: Option<String>, default: &str| {
+            env::var(format!("{}{}", ENV_PREFIX, key))
+                .ok()
+                .or(from_file)
+                .unwrap_or_else(|| default.to_string())
+        };
+
+        let engine = setting("ENGINE", file.engine, "remote");
+        let path = setting("PATH", file.path, DEFAULT_PATH);
+        let storage = match engine.as_str() {
+            "remote" => Storage::Remote(setting("ADDRESS", file.address, DEFAULT_ADDRESS)),
+            "rocksdb" if cfg!(feature = "rocksdb") => Storage::RocksDb(path.into()),
+            "surrealkv" if cfg!(feature = "surrealkv") => Storage::SurrealKv(path.into()),
+            "rocksdb" | "surrealkv" => {
+                return Err(format!(
+                    "the {} engine isn't built in, build with --features {}",
+                    engine, engine
+                ))
+            }
+            "memory" => Storage::Memory,
+            other => {
+                return Err(format!(
+                    "unknown database engine {}, expected remote, rocksdb, surrealkv or memory",
+                    other
+                ))
+            }
+        };
+
+        Ok(DbConfig {
+            storage,
+            namespace: setting("NAMESPACE", file.namespace, DEFAULT_NAMESPACE),
+            database: setting("DATABASE", file.database, DEFAULT_DATABASE),
+        })
+    }
+}
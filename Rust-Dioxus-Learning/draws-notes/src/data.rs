@@ -0,0 +1,495 @@
+//! The reads and writes behind both the server functions and the REST api. Each function
+//! takes the user it runs as and checks their role itself, so neither caller has to.
+
+use std::{collections::HashMap, str::FromStr};
+
+use dioxus_fullstack::prelude::ServerFnError;
+use serde::{Deserialize, Serialize};
+use surrealdb::sql::Thing;
+
+use crate::{
+    auth, embeddings,
+    error::NotesError,
+    links, markdown,
+    model::{
+        note::{Note, NotePage, NoteSort},
+        notebook::{descendant_ids, Notebook, NotebookNoteCount},
+        share::Role,
+    },
+    Record, DB, MAX_NOTE_PAGE_SIZE, NOTEBOOK_TABLE, NOTE_TABLE, PREVIEW_LEN, PREVIEW_SOURCE_LEN,
+    SHARE_TABLE,
+};
+
+/// Where the next page of summaries starts, the sort key and id of the last note handed out.
+/// Opaque to the client, it only ever passes it back.
+#[derive(Debug, Serialize, Deserialize)]
+struct SummaryCursor {
+    key: String,
+    id: String,
+}
+
+/// Parses a record id from the client. One that doesn't parse can't name anything, so it's
+/// reported like a record that doesn't exist.
+pub fn thing(id: &str) -> Result<Thing, NotesError> {
+    Thing::from_str(id).map_err(|_| NotesError::NotFound(id.to_string()))
+}
+
+/// Parses a record id from the client that has to name a record in `table`. One from any other
+/// table isn't what the caller is looking for, so it's not found either.
+pub fn thing_in(table: &str, id: &str) -> Result<Thing, NotesError> {
+    let thing = thing(id)?;
+    if thing.tb != table {
+        return Err(NotesError::NotFound(id.to_string()));
+    }
+    Ok(thing)
+}
+
+/// Errors out unless `record` exists and isn't in the trash. Something in the trash can be
+/// restored or purged, but nothing new goes in or onto it.
+pub async fn require_not_trashed(record: &Thing) -> Result<(), NotesError> {
+    let con = DB.get().await;
+    let live: Vec<Thing> = con
+        .query("SELECT VALUE id FROM $record WHERE deleted_at IS NONE;")
+        .bind(("record", record.clone()))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+    if live.is_empty() {
+        return Err(NotesError::NotFound(record.to_string()));
+    }
+    Ok(())
+}
+
+pub async fn get_notebooks(user: &Thing) -> Result<Vec<Notebook>, ServerFnError> {
+    let con = DB.get().await;
+
+    // really don't want this to be two queries, but this seemed like the lesser of evils
+    // owned notebooks come first, then the ones shared with the caller
+    let mut res: surrealdb::Response = con
+        .query("SELECT type::string(id) as id, type::string(name) as name, 'owner' as role, (IF parent THEN type::string(parent) END) as parent FROM type::table($table) WHERE owner = $user AND deleted_at IS NONE ORDER BY name")
+        .query("SELECT type::string(notebook) as id, type::string(notebook.name) as name, role, (IF notebook.parent THEN type::string(notebook.parent) END) as parent FROM type::table($share_table) WHERE user = $user AND notebook.deleted_at IS NONE ORDER BY name")
+        .bind(("table", NOTEBOOK_TABLE))
+        .bind(("share_table", SHARE_TABLE))
+        .bind(("user", user.clone()))
+        .await
+        .map_err(NotesError::from)?;
+
+    let owned: Result<Vec<Notebook>, _> = res.take(0);
+    let shared: Result<Vec<Notebook>, _> = res.take(1);
+    let res = match (owned, shared) {
+        (Ok(mut owned), Ok(shared)) => {
+            owned.extend(shared);
+            Ok(owned)
+        }
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    };
+
+    match res {
+        Ok(mut notebooks) => {
+            let notebook_things = auth::accessible_notebooks(user).await?;
+            //now grab the counts
+            let mut counts: surrealdb::Response = con
+                .query("SELECT type::string(notebook) as id, count(id) as count FROM type::table($table) WHERE notebook IN $notebooks AND deleted_at IS NONE GROUP BY id")
+                .bind(("table", NOTE_TABLE))
+                .bind(("notebooks", notebook_things))
+                .await
+                .map_err(NotesError::from)?;
+
+            let counts: Result<Vec<NotebookNoteCount>, _> = counts.take(0);
+            match counts {
+                Ok(counts) => {
+                    //turn the notebooks into a map from id -> Notebook
+                    let count_map: HashMap<String, NotebookNoteCount> = counts
+                        .into_iter()
+                        .map(|notebook| (notebook.id.clone(), notebook))
+                        .collect();
+
+                    notebooks.iter_mut().for_each(|notebook| {
+                        let id = notebook.id.as_ref();
+                        if let Some(id) = id {
+                            let ct: Option<&NotebookNoteCount> = count_map.get(id);
+                            notebook.count = Some(ct.map(|nbct| nbct.count).unwrap_or(0));
+                        } else {
+                            notebook.count = Some(0);
+                        }
+                    });
+
+                    let totals: Vec<u32> = notebooks
+                        .iter()
+                        .map(|notebook| {
+                            let descendants = notebook
+                                .id
+                                .as_deref()
+                                .map(|id| descendant_ids(&notebooks, id))
+                                .unwrap_or_default();
+                            notebooks
+                                .iter()
+                                .filter(|nb| {
+                                    nb.id == notebook.id
+                                        || nb.id.as_ref().is_some_and(|id| descendants.contains(id))
+                                })
+                                .map(|nb| nb.count.unwrap_or(0))
+                                .sum()
+                        })
+                        .collect();
+                    notebooks
+                        .iter_mut()
+                        .zip(totals)
+                        .for_each(|(notebook, total)| notebook.total = Some(total));
+
+                    Ok(notebooks)
+                }
+                Err(e) => {
+                    log::error!("issue getting note counts {:?}", e);
+                    Err(NotesError::from(e).into())
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("error getting notebooks {:?}", e);
+            Err(NotesError::from(e).into())
+        }
+    }
+}
+
+pub async fn upsert_notebook(user: &Thing, notebook: Notebook) -> Result<String, ServerFnError> {
+    if notebook.name.trim().is_empty() {
+        return Err(NotesError::Validation("notebook name can't be empty".to_string()).into());
+    }
+    let con = DB.get().await;
+
+    let res: Vec<Record> = if let Some(id) = notebook.id {
+        auth::require_role(&id, user, Role::Owner).await?;
+        con.query("UPDATE ONLY type::thing($id) SET name = $name")
+            .bind(("id", id))
+            .bind(("name", notebook.name))
+            .await
+            .map_err(NotesError::from)?
+            .take(0)
+            .map_err(NotesError::from)?
+    } else {
+        // moving an existing notebook goes through `set_notebook_parent`, which checks for cycles
+        if let Some(parent) = notebook.parent.as_ref() {
+            auth::require_role(parent, user, Role::Owner).await?;
+        }
+        let parent = notebook.parent.map(|parent| thing(&parent)).transpose()?;
+        con.query("CREATE notebook SET name = $name, owner = $owner, parent = $parent;")
+            .bind(("name", notebook.name))
+            .bind(("owner", user.clone()))
+            .bind(("parent", parent))
+            .await
+            .map_err(NotesError::from)?
+            .take(0)
+            .map_err(NotesError::from)?
+    };
+
+    match res.first() {
+        Some(Record { id }) => Ok(id.to_string()),
+        _ => Err(NotesError::Db("couldn't get id".to_string()).into()),
+    }
+}
+
+/// Nests `notebook_id` under `parent`, or moves it to the top level. Both have to be the
+/// user's own, and a notebook can't end up under itself or one of its descendants.
+pub async fn set_notebook_parent(
+    user: &Thing,
+    notebook_id: String,
+    parent: Option<String>,
+) -> Result<(), ServerFnError> {
+    auth::require_role(&notebook_id, user, Role::Owner).await?;
+    let con = DB.get().await;
+
+    let parent = match parent {
+        Some(parent) => {
+            auth::require_role(&parent, user, Role::Owner).await?;
+            // trashed notebooks included, one could be restored into the middle of a cycle
+            let owned: Vec<Thing> = con
+                .query("SELECT VALUE id FROM type::table($table) WHERE owner = $user;")
+                .bind(("table", NOTEBOOK_TABLE))
+                .bind(("user", user.clone()))
+                .await
+                .map_err(NotesError::from)?
+                .take(0)
+                .map_err(NotesError::from)?;
+            let tree = notebook_tree(owned).await?;
+            if parent == notebook_id || descendant_ids(&tree, &notebook_id).contains(&parent) {
+                return Err(NotesError::Validation(
+                    "a notebook can't be nested under itself".to_string(),
+                )
+                .into());
+            }
+            Some(thing(&parent)?)
+        }
+        None => None,
+    };
+
+    con.query("UPDATE type::thing($id) SET parent = $parent;")
+        .bind(("id", notebook_id))
+        .bind(("parent", parent))
+        .await
+        .map_err(NotesError::from)?
+        .check()
+        .map_err(NotesError::from)?;
+    Ok(())
+}
+
+/// `notebooks` with only their ids, names and parents filled in, enough to walk the tree.
+async fn notebook_tree(notebooks: Vec<Thing>) -> Result<Vec<Notebook>, ServerFnError> {
+    let con = DB.get().await;
+    let tree: Vec<Notebook> = con
+        .query("SELECT type::string(id) as id, name, (IF parent THEN type::string(parent) END) as parent FROM $notebooks;")
+        .bind(("notebooks", notebooks))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+    Ok(tree)
+}
+
+/// A page of summaries, of every note the user can see or only the ones in `notebook_id`, and
+/// with `include_children` the notebooks nested under it too.
+pub async fn get_note_summaries(
+    user: &Thing,
+    notebook_id: Option<String>,
+    include_children: bool,
+    tag_id: Option<String>,
+    sort: NoteSort,
+    cursor: Option<String>,
+    limit: u32,
+) -> Result<NotePage, ServerFnError> {
+    if let Some(notebook_id) = notebook_id.as_ref() {
+        auth::require_role(notebook_id, user, Role::Viewer).await?;
+    }
+    let notebooks = auth::accessible_notebooks(user).await?;
+    let con = DB.get().await;
+
+    let cursor: Option<SummaryCursor> = cursor
+        .map(|cursor| serde_json::from_str(&cursor))
+        .transpose()
+        .map_err(|_| NotesError::Validation("invalid cursor".to_string()))?;
+    let limit = limit.clamp(1, MAX_NOTE_PAGE_SIZE);
+
+    // ties on the sort key are broken by id, so the cursor always points at exactly one note
+    let (sort_key, direction, after) = match sort {
+        NoteSort::Updated => (
+            "updated_at",
+            "DESC",
+            "(updated_at < <datetime> $cursor_key OR (updated_at = <datetime> $cursor_key AND id < $cursor_id))",
+        ),
+        NoteSort::Created => (
+            "created_at",
+            "DESC",
+            "(created_at < <datetime> $cursor_key OR (created_at = <datetime> $cursor_key AND id < $cursor_id))",
+        ),
+        NoteSort::Title => (
+            "string::lowercase(title)",
+            "ASC",
+            "(string::lowercase(title) > $cursor_key OR (string::lowercase(title) = $cursor_key AND id > $cursor_id))",
+        ),
+    };
+
+    let mut filters = vec!["notebook IN $notebooks", "deleted_at IS NONE"];
+    if notebook_id.is_some() {
+        filters.push("notebook IN $notebook_things");
+    }
+    if tag_id.is_some() {
+        filters.push("->tagged->tag CONTAINS $tag_thing");
+    }
+    if cursor.is_some() {
+        filters.push(after);
+    }
+    let where_clause = format!("WHERE {}", filters.join(" AND "));
+
+    // one extra note tells us whether there's another page
+    let mut req = con
+        .query(format!("SELECT type::string(id) as id, title, string::slice(content, 0, $preview_source_len) as content, type::string(notebook) as notebook, version ?? 0 as version, \
+            type::string(created_at) as created_at, type::string(updated_at) as updated_at, {} as sort_key \
+            FROM type::table($table) {} ORDER BY sort_key {}, id {} LIMIT $limit;", sort_key, where_clause, direction, direction))
+        .bind(("table", NOTE_TABLE))
+        .bind(("notebooks", notebooks.clone()))
+        .bind(("preview_source_len", PREVIEW_SOURCE_LEN))
+        .bind(("limit", limit + 1));
+
+    if let Some(notebook_id) = notebook_id {
+        let mut notebook_ids = vec![notebook_id.clone()];
+        if include_children {
+            let tree = notebook_tree(notebooks).await?;
+            notebook_ids.extend(descendant_ids(&tree, &notebook_id));
+        }
+        let notebook_things = notebook_ids
+            .iter()
+            .map(|id| thing(id))
+            .collect::<Result<Vec<Thing>, _>>()?;
+        req = req.bind(("notebook_things", notebook_things));
+    }
+    if let Some(tag_id) = tag_id {
+        let tag_thing = thing(&tag_id)?;
+        req = req.bind(("tag_thing", tag_thing));
+    }
+    if let Some(cursor) = cursor {
+        let cursor_id = Thing::from_str(&cursor.id)
+            .map_err(|_| NotesError::Validation("invalid cursor".to_string()))?;
+        req = req
+            .bind(("cursor_key", cursor.key))
+            .bind(("cursor_id", cursor_id));
+    }
+
+    let mut res: Vec<Note> = req
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    let next_cursor = if res.len() > limit as usize {
+        res.truncate(limit as usize);
+        res.last()
+            .map(|last| {
+                let key = match sort {
+                    NoteSort::Updated => last.updated_at.clone().unwrap_or_default(),
+                    NoteSort::Created => last.created_at.clone().unwrap_or_default(),
+                    NoteSort::Title => last.title.to_lowercase(),
+                };
+                serde_json::to_string(&SummaryCursor {
+                    key,
+                    id: last.id.clone().unwrap_or_default(),
+                })
+            })
+            .transpose()
+            .map_err(|e| NotesError::Db(e.to_string()))?
+    } else {
+        None
+    };
+
+    // the markdown is stripped after slicing, so grab more than we show to still have enough text
+    let notes: Vec<Note> = res
+        .into_iter()
+        .map(|note| Note {
+            content: markdown::strip(&note.content)
+                .chars()
+                .take(PREVIEW_LEN)
+                .collect(),
+            ..note
+        })
+        .collect();
+    Ok(NotePage { notes, next_cursor })
+}
+
+pub async fn get_note(user: &Thing, note_id: String) -> Result<Note, ServerFnError> {
+    auth::require_role(&note_id, user, Role::Viewer).await?;
+    let con = DB.get().await;
+    let res: Option<Note> = con
+        .query("SELECT type::string(id) as id, title, content, type::string(notebook) as notebook, version ?? 0 as version, type::string(created_at) as created_at, type::string(updated_at) as updated_at FROM type::thing($note_id)")
+        .bind(("note_id", note_id.clone()))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    res.ok_or(NotesError::NotFound(note_id).into())
+}
+
+pub async fn upsert_note(user: &Thing, note: Note) -> Result<String, ServerFnError> {
+    auth::require_role(&note.notebook, user, Role::Editor).await?;
+    let con = DB.get().await;
+
+    let title = note.title.clone();
+    let content = note.content.clone();
+    let mut renamed = true;
+
+    let res: Vec<Record> = if let Some(id) = note.id {
+        // checked against the note's current notebook, so moving a note needs both sides
+        auth::require_role(&id, user, Role::Editor).await?;
+        let previous_title: Option<String> = con
+            .query("SELECT VALUE title FROM type::thing($id)")
+            .bind(("id", id.clone()))
+            .await
+            .map_err(NotesError::from)?
+            .take(0)
+            .map_err(NotesError::from)?;
+        renamed = previous_title
+            .is_some_and(|previous| previous.trim().to_lowercase() != title.trim().to_lowercase());
+        // the version check and the bump happen in the same statement, so two saves can't
+        // both pass it. notes in the trash have to be restored before they can be edited
+        let res: Vec<Record> = con.query("UPDATE type::thing($id) SET notebook = type::thing($notebook), title = $title, content = $content, version = (version ?? 0) + 1 WHERE (version ?? 0) = $version AND deleted_at IS NONE;")
+        .bind(("id", id.clone()))
+        .bind(("notebook", note.notebook))
+        .bind(("content", note.content))
+        .bind(("title", note.title))
+        .bind(("version", note.version))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+        if res.is_empty() {
+            let current: Option<Note> = con
+                .query("SELECT type::string(id) as id, title, content, type::string(notebook) as notebook, version ?? 0 as version FROM type::thing($id) WHERE deleted_at IS NONE")
+                .bind(("id", id.clone()))
+                .await
+                .map_err(NotesError::from)?
+                .take(0)
+                .map_err(NotesError::from)?;
+            return Err(match current {
+                Some(current) => NotesError::Conflict(Box::new(current)).into(),
+                None => NotesError::NotFound(id).into(),
+            });
+        }
+        res
+    } else {
+        con.query("CREATE note SET notebook = type::thing($notebook), owner = $owner, title = $title, content = $content, version = 1;")
+        .bind(("notebook", note.notebook))
+        .bind(("owner", user.clone()))
+        .bind(("content", note.content))
+        .bind(("title", note.title))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?
+    };
+
+    match res.first() {
+        Some(Record { id }) => {
+            after_note_saved(user, id, &title, &content, renamed).await;
+            Ok(id.to_string())
+        }
+        _ => Err(NotesError::Db("couldn't get id".to_string()).into()),
+    }
+}
+
+pub async fn delete_note(user: &Thing, note_id: String) -> Result<(), ServerFnError> {
+    auth::require_role(&note_id, user, Role::Editor).await?;
+    let con = DB.get().await;
+
+    con.query("UPDATE type::thing($note_id) SET deleted_at = time::now();")
+        .bind(("note_id", note_id))
+        .await
+        .map_err(NotesError::from)?
+        .check()
+        .map_err(NotesError::from)?;
+
+    Ok(())
+}
+
+/// Keeps the `links_to` edges and the embedding in step with a note that was just saved. The
+/// save already went through by now, so a failure here is only logged.
+pub async fn after_note_saved(
+    user: &Thing,
+    note: &Thing,
+    title: &str,
+    content: &str,
+    renamed: bool,
+) {
+    embeddings::update(note.clone(), title.to_string(), content.to_string());
+    let res = async {
+        let notebooks = auth::accessible_notebooks(user).await?;
+        links::update_outbound(note, content, &notebooks).await?;
+        if renamed {
+            links::update_inbound(note, title, &notebooks).await?;
+        }
+        Ok::<(), ServerFnError>(())
+    };
+    if let Err(e) = res.await {
+        log::error!("error updating links of {} {:?}", note, e);
+    }
+}
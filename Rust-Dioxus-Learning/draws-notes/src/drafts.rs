@@ -0,0 +1,37 @@
+use web_sys::Storage;
+
+use crate::model::draft::Draft;
+
+const KEY_PREFIX: &str = "draws-notes:draft:";
+
+fn storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// The draft left behind for a note, if the last session didn't get to save it.
+pub fn load(note_id: &str) -> Option<Draft> {
+    let json = storage()?
+        .get_item(&format!("{}{}", KEY_PREFIX, note_id))
+        .ok()??;
+    serde_json::from_str(&json)
+        .map_err(|e| log::error!("error decoding draft {:?}", e))
+        .ok()
+}
+
+pub fn store(note_id: &str, draft: &Draft) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    let Ok(json) = serde_json::to_string(draft) else {
+        return;
+    };
+    if let Err(e) = storage.set_item(&format!("{}{}", KEY_PREFIX, note_id), &json) {
+        log::error!("error storing draft {:?}", e);
+    }
+}
+
+pub fn clear(note_id: &str) {
+    if let Some(storage) = storage() {
+        let _ = storage.remove_item(&format!("{}{}", KEY_PREFIX, note_id));
+    }
+}
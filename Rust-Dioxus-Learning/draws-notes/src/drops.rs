@@ -0,0 +1,76 @@
+use futures::{channel::mpsc, StreamExt};
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{DragEvent, Element, File};
+
+/// Files dropped onto an element. Dioxus' drag events don't carry the files, so this listens
+/// on the element itself. The listeners are removed when this is dropped.
+pub struct FileDrops {
+    element: Element,
+    files: mpsc::UnboundedReceiver<File>,
+    // the browser calls into these, so they have to live as long as the listeners
+    on_dragover: Closure<dyn FnMut(DragEvent)>,
+    on_drop: Closure<dyn FnMut(DragEvent)>,
+}
+
+impl FileDrops {
+    pub fn listen(element_id: &str) -> Option<Self> {
+        let element = web_sys::window()?
+            .document()?
+            .get_element_by_id(element_id)?;
+
+        let (sender, files) = mpsc::unbounded();
+        // the browser only lets something be dropped where dragover was cancelled
+        let on_dragover = Closure::<dyn FnMut(DragEvent)>::new(|event: DragEvent| {
+            event.prevent_default();
+        });
+        let on_drop = Closure::<dyn FnMut(DragEvent)>::new(move |event: DragEvent| {
+            // otherwise the browser navigates away to the file
+            event.prevent_default();
+            let Some(list) = event.data_transfer().and_then(|data| data.files()) else {
+                return;
+            };
+            for file in (0..list.length()).filter_map(|i| list.get(i)) {
+                let _ = sender.unbounded_send(file);
+            }
+        });
+
+        let drops = Self {
+            element,
+            files,
+            on_dragover,
+            on_drop,
+        };
+        for (event, listener) in [("dragover", &drops.on_dragover), ("drop", &drops.on_drop)] {
+            drops
+                .element
+                .add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())
+                .map_err(|e| log::error!("error listening for dropped files {:?}", e))
+                .ok()?;
+        }
+        Some(drops)
+    }
+
+    /// The name and contents of the next file dropped, skipping any the browser can't read.
+    pub async fn next(&mut self) -> Option<(String, Vec<u8>)> {
+        loop {
+            let file = self.files.next().await?;
+            match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => {
+                    return Some((file.name(), js_sys::Uint8Array::new(&buffer).to_vec()))
+                }
+                Err(e) => log::error!("error reading dropped file {:?}", e),
+            }
+        }
+    }
+}
+
+impl Drop for FileDrops {
+    fn drop(&mut self) {
+        for (event, listener) in [("dragover", &self.on_dragover), ("drop", &self.on_drop)] {
+            let _ = self
+                .element
+                .remove_event_listener_with_callback(event, listener.as_ref().unchecked_ref());
+        }
+    }
+}
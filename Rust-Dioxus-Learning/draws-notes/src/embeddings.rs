@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use async_once::AsyncOnce;
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use surrealdb::sql::Thing;
+use tokenizers::{Tokenizer, TruncationParams};
+use tokio::sync::Mutex;
+
+use crate::{attachments, config, markdown, DB, NOTE_EMBEDDING_TABLE, NOTE_TABLE};
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+// BERT models only have position embeddings this far, the rest of a long note is cut off
+const MAX_TOKENS: usize = 512;
+
+lazy_static! {
+    static ref EMBEDDER: AsyncOnce<Option<Arc<Embedder>>> = AsyncOnce::new(async {
+        let model = config::embeddings_model().expect("invalid embeddings config")?;
+        log::info!("loading embedding model {}", model);
+        let loaded = tokio::task::spawn_blocking(move || Embedder::load(&model))
+            .await
+            .map_err(Error::from)
+            .and_then(|loaded| loaded);
+        match loaded {
+            Ok(embedder) => Some(Arc::new(embedder)),
+            Err(e) => {
+                log::error!("error loading embedding model, related notes are off {:?}", e);
+                None
+            }
+        }
+    });
+    // held while a note is embedded, so two quick saves of one note are stored in order
+    static ref ORDER: Mutex<()> = Mutex::new(());
+}
+
+struct Embedder {
+    model_id: String,
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl Embedder {
+    /// Downloads the model from the Hugging Face hub, or takes it from the local cache.
+    fn load(model_id: &str) -> Result<Embedder, Error> {
+        let repo = Api::new()?.repo(Repo::new(model_id.to_string(), RepoType::Model));
+        let config: Config =
+            serde_json::from_str(&std::fs::read_to_string(repo.get("config.json")?)?)?;
+        let mut tokenizer = Tokenizer::from_file(repo.get("tokenizer.json")?)?;
+        tokenizer.with_truncation(Some(TruncationParams {
+            max_length: MAX_TOKENS,
+            ..Default::default()
+        }))?;
+        // one text at a time, so nothing to pad to
+        tokenizer.with_padding(None);
+
+        let device = Device::Cpu;
+        let weights = repo.get("model.safetensors")?;
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights], DTYPE, &device)? };
+        let model = BertModel::load(vb, &config)?;
+        Ok(Embedder {
+            model_id: model_id.to_string(),
+            model,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// The mean of the token embeddings, scaled to unit length so cosine similarity is just
+    /// the dot product.
+    fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+        let encoding = self.tokenizer.encode(text, true)?;
+        let ids = Tensor::new(encoding.get_ids(), &self.device)?.unsqueeze(0)?;
+        let type_ids = ids.zeros_like()?;
+        let tokens = self.model.forward(&ids, &type_ids)?;
+
+        let (_, len, _) = tokens.dims3()?;
+        let mean = (tokens.sum(1)? / len as f64)?;
+        let norm = mean.sqr()?.sum_keepdim(1)?.sqrt()?;
+        Ok(mean.broadcast_div(&norm)?.squeeze(0)?.to_vec1::<f32>()?)
+    }
+}
+
+/// Loads the model and embeds every note that doesn't have an embedding yet, ie. the ones
+/// saved before related notes were turned on.
+pub fn start() {
+    tokio::spawn(async {
+        if let Err(e) = embed_missing().await {
+            log::error!("error embedding existing notes {:?}", e);
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+struct NoteText {
+    id: Thing,
+    title: String,
+    content: String,
+}
+
+async fn embed_missing() -> Result<(), Error> {
+    if EMBEDDER.get().await.is_none() {
+        return Ok(());
+    }
+    let con = DB.get().await;
+    let notes: Vec<NoteText> = con
+        .query("SELECT id, title, content FROM type::table($note_table) \
+            WHERE deleted_at IS NONE AND id NOTINSIDE (SELECT VALUE note FROM type::table($embedding_table));")
+        .bind(("note_table", NOTE_TABLE))
+        .bind(("embedding_table", NOTE_EMBEDDING_TABLE))
+        .await?
+        .take(0)?;
+    // one note that can't be embedded shouldn't keep the rest from being
+    for note in notes {
+        if let Err(e) = embed_note(note.id.clone(), note.title, note.content).await {
+            log::error!("error embedding {} {:?}", note.id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Re-embeds a note that was just saved, in the background since it takes a moment.
+pub fn update(note: Thing, title: String, content: String) {
+    tokio::spawn(async move {
+        if let Err(e) = embed_note(note.clone(), title, content).await {
+            log::error!("error embedding {} {:?}", note, e);
+        }
+    });
+}
+
+async fn embed_note(note: Thing, title: String, content: String) -> Result<(), Error> {
+    let Some(embedder) = EMBEDDER.get().await.clone() else {
+        return Ok(());
+    };
+    let _turn = ORDER.lock().await;
+
+    let text = format!("{}\n\n{}", title, markdown::strip(&content));
+    // saves that only change the notebook or the markdown around the text keep the old one,
+    // switching models redoes every note as it's saved
+    let hash = attachments::hash(format!("{}\n{}", embedder.model_id, text).as_bytes());
+    let con = DB.get().await;
+    let stored: Option<String> = con
+        .query("SELECT VALUE hash FROM type::table($table) WHERE note = $note;")
+        .bind(("table", NOTE_EMBEDDING_TABLE))
+        .bind(("note", note.clone()))
+        .await?
+        .take(0)?;
+    if stored.as_ref() == Some(&hash) {
+        return Ok(());
+    }
+
+    let vector = tokio::task::spawn_blocking(move || embedder.embed(&text)).await??;
+    store(&note, vector, &hash).await?;
+    Ok(())
+}
+
+/// Saves `vector` as the note's embedding, keyed by the note's own id.
+pub async fn store(note: &Thing, vector: Vec<f32>, hash: &str) -> surrealdb::Result<()> {
+    let con = DB.get().await;
+    con.query("UPDATE type::thing($table, meta::id($note)) SET note = $note, vector = $vector, hash = $hash;")
+        .bind(("table", NOTE_EMBEDDING_TABLE))
+        .bind(("note", note.clone()))
+        .bind(("vector", vector))
+        .bind(("hash", hash.to_string()))
+        .await?
+        .check()?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+use dioxus_fullstack::prelude::ServerFnError;
+use serde::{Deserialize, Serialize};
+
+use crate::model::note::Note;
+
+// server fn errors only carry a string, ours are json behind this so the client can tell
+// them apart from the framework's own
+const ERROR_PREFIX: &str = "notes error: ";
+
+/// Everything the server functions fail with on purpose. Goes over the wire inside a
+/// `ServerFnError`, use `from_server_fn_error` to get it back on the client.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum NotesError {
+    NotFound(String),
+    /// a save carried a stale version, holds the note as it's saved now. Boxed so every other
+    /// result doesn't have to make room for a whole note
+    Conflict(Box<Note>),
+    Db(String),
+    Validation(String),
+    Unauthorized(String),
+}
+
+impl NotesError {
+    pub fn from_server_fn_error(error: &ServerFnError) -> NotesError {
+        match error {
+            ServerFnError::ServerError(msg) => msg
+                .strip_prefix(ERROR_PREFIX)
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_else(|| NotesError::Db(msg.clone())),
+            // anything that didn't come from us is a transport or framework failure, which
+            // the user can only retry just like a database one
+            other => NotesError::Db(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for NotesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotesError::NotFound(what) => write!(f, "{} not found", what),
+            NotesError::Conflict(note) => {
+                write!(f, "\"{}\" was changed somewhere else", note.title)
+            }
+            NotesError::Db(_) => write!(f, "Couldn't reach the database, try again"),
+            NotesError::Validation(msg) | NotesError::Unauthorized(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+// deliberately not std::error::Error, server_fn already turns every Error into an opaque
+// ServerFnError and that would clash with this
+impl From<NotesError> for ServerFnError {
+    fn from(error: NotesError) -> Self {
+        match serde_json::to_string(&error) {
+            Ok(json) => ServerFnError::ServerError(format!("{}{}", ERROR_PREFIX, json)),
+            Err(_) => ServerFnError::ServerError(error.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<surrealdb::Error> for NotesError {
+    fn from(error: surrealdb::Error) -> Self {
+        log::error!("database error {:?}", error);
+        NotesError::Db(error.to_string())
+    }
+}
@@ -0,0 +1,51 @@
+use futures::{channel::mpsc, StreamExt};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{EventSource, MessageEvent};
+
+use crate::{model::live::LiveChange, LIVE_EVENTS_PATH};
+
+/// The browser's end of the live event stream. The connection is closed when this is dropped.
+pub struct LiveEvents {
+    source: EventSource,
+    changes: mpsc::UnboundedReceiver<LiveChange>,
+    // the browser calls into this, so it has to live as long as the source
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl LiveEvents {
+    pub fn connect() -> Option<Self> {
+        let source = EventSource::new(LIVE_EVENTS_PATH)
+            .map_err(|e| log::error!("error opening live events {:?}", e))
+            .ok()?;
+
+        let (sender, changes) = mpsc::unbounded();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let Some(data) = event.data().as_string() else {
+                return;
+            };
+            match serde_json::from_str::<LiveChange>(&data) {
+                Ok(change) => {
+                    let _ = sender.unbounded_send(change);
+                }
+                Err(e) => log::error!("error decoding live change {:?}", e),
+            }
+        });
+        source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Some(Self {
+            source,
+            changes,
+            _on_message: on_message,
+        })
+    }
+
+    pub async fn next(&mut self) -> Option<LiveChange> {
+        self.changes.next().await
+    }
+}
+
+impl Drop for LiveEvents {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
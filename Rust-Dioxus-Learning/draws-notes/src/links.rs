@@ -0,0 +1,110 @@
+use serde::Deserialize;
+use surrealdb::sql::Thing;
+
+use crate::{markdown, DB, LINKS_TO_TABLE, NOTE_TABLE};
+
+#[derive(Debug, Deserialize)]
+struct LinkTarget {
+    id: Thing,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkSource {
+    id: Thing,
+    content: String,
+}
+
+/// Brings the `links_to` edges going out of `note` in line with the `[[Title]]` links in its
+/// content. An edge stays as long as its link is still in the content, even once no note has
+/// that title anymore, so a renamed note still knows which links use its old title.
+pub async fn update_outbound(
+    note: &Thing,
+    content: &str,
+    notebooks: &[Thing],
+) -> surrealdb::Result<()> {
+    let links = markdown::wiki_links(content);
+    let con = DB.get().await;
+    let linked: Vec<String> = con
+        .query("SELECT VALUE title FROM type::table($links_table) WHERE in = $note;")
+        .bind(("links_table", LINKS_TO_TABLE))
+        .bind(("note", note.clone()))
+        .await?
+        .take(0)?;
+    let new_links: Vec<String> = links
+        .iter()
+        .filter(|link| !linked.contains(link))
+        .cloned()
+        .collect();
+
+    let targets: Vec<LinkTarget> = if new_links.is_empty() {
+        vec![]
+    } else {
+        con.query("SELECT id, string::lowercase(string::trim(title)) as title FROM type::table($note_table) \
+            WHERE notebook IN $notebooks AND deleted_at IS NONE AND id != $note AND string::lowercase(string::trim(title)) IN $links;")
+            .bind(("note_table", NOTE_TABLE))
+            .bind(("notebooks", notebooks.to_vec()))
+            .bind(("note", note.clone()))
+            .bind(("links", new_links))
+            .await?
+            .take(0)?
+    };
+
+    let mut req = con
+        .query("BEGIN TRANSACTION;")
+        .query("DELETE type::table($links_table) WHERE in = $note AND title NOTINSIDE $links;");
+    for i in 0..targets.len() {
+        req = req.query(format!(
+            "RELATE $note->links_to->$target_{i} SET title = $title_{i};"
+        ));
+    }
+    req = req
+        .query("COMMIT TRANSACTION;")
+        .bind(("links_table", LINKS_TO_TABLE))
+        .bind(("note", note.clone()))
+        .bind(("links", links));
+    for (i, target) in targets.into_iter().enumerate() {
+        req = req
+            .bind((format!("target_{i}"), target.id))
+            .bind((format!("title_{i}"), target.title));
+    }
+    req.await?.check()?;
+    Ok(())
+}
+
+/// Links `note` from every note that already has a `[[title]]` link to it, for when a note
+/// is created or renamed after the notes linking to it were saved.
+pub async fn update_inbound(
+    note: &Thing,
+    title: &str,
+    notebooks: &[Thing],
+) -> surrealdb::Result<()> {
+    let title = title.trim().to_lowercase();
+    if title.is_empty() {
+        return Ok(());
+    }
+    let con = DB.get().await;
+    // the contains is only a rough cut, the markdown parser makes the call
+    let sources: Vec<LinkSource> = con
+        .query("SELECT id, content FROM type::table($note_table) \
+            WHERE notebook IN $notebooks AND deleted_at IS NONE AND id != $note AND string::contains(string::lowercase(content), $title) \
+            AND $note NOTINSIDE ->links_to->note;")
+        .bind(("note_table", NOTE_TABLE))
+        .bind(("notebooks", notebooks.to_vec()))
+        .bind(("note", note.clone()))
+        .bind(("title", title.clone()))
+        .await?
+        .take(0)?;
+
+    for source in sources {
+        if markdown::wiki_links(&source.content).contains(&title) {
+            con.query("RELATE $source->links_to->$note SET title = $title;")
+                .bind(("source", source.id))
+                .bind(("note", note.clone()))
+                .bind(("title", title.clone()))
+                .await?
+                .check()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use std::{collections::HashSet, convert::Infallible, time::Duration};
+
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use dioxus_fullstack::prelude::ServerFnError;
+use futures::{stream, Stream, StreamExt};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use surrealdb::{
+    sql::{Datetime, Thing},
+    Action, Notification,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    auth,
+    model::live::{LiveAction, LiveChange},
+    DB, NOTEBOOK_TABLE, NOTE_TABLE, SHARE_TABLE,
+};
+
+// a stream that falls this far behind skips the missed changes
+const CHANNEL_CAPACITY: usize = 256;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref CHANGES: broadcast::Sender<LiveChange> = broadcast::channel(CHANNEL_CAPACITY).0;
+}
+
+#[derive(Debug, Deserialize)]
+struct LiveRow {
+    id: Thing,
+    // only notes and shares have one, a notebook is its own notebook
+    #[serde(default)]
+    notebook: Option<Thing>,
+    #[serde(default)]
+    deleted_at: Option<Datetime>,
+}
+
+/// Starts one `LIVE SELECT` per table feeding the shared channel. They run as the server's
+/// root connection, so filtering by user happens per stream in `events`.
+pub fn start() {
+    for table in [NOTE_TABLE, NOTEBOOK_TABLE, SHARE_TABLE] {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = watch(table).await {
+                    log::error!("live query on {} failed {:?}", table, e);
+                }
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        });
+    }
+}
+
+async fn watch(table: &'static str) -> surrealdb::Result<()> {
+    let con = DB.get().await;
+    let mut notifications = con.select::<Vec<LiveRow>>(table).live().await?;
+
+    while let Some(notification) = notifications.next().await {
+        let Notification { action, data, .. } = notification?;
+        let action = match action {
+            Action::Create => LiveAction::Create,
+            // moving to the trash is an update, but to everyone watching it's gone
+            Action::Update if data.deleted_at.is_some() => LiveAction::Delete,
+            Action::Update => LiveAction::Update,
+            Action::Delete => LiveAction::Delete,
+            _ => continue,
+        };
+        let notebook = data.notebook.unwrap_or_else(|| data.id.clone());
+
+        // sharing or unsharing changes who sees the notebook, so it goes out as a change to it
+        let change = if table == SHARE_TABLE {
+            LiveChange {
+                table: NOTEBOOK_TABLE.to_string(),
+                action: LiveAction::Update,
+                id: notebook.to_string(),
+                notebook: notebook.to_string(),
+            }
+        } else {
+            LiveChange {
+                table: table.to_string(),
+                action,
+                id: data.id.to_string(),
+                notebook: notebook.to_string(),
+            }
+        };
+        // only fails when no one is listening
+        let _ = CHANGES.send(change);
+    }
+    Ok(())
+}
+
+/// `GET /live`, a server sent event stream of every change the session's user can see.
+pub async fn events(
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let token = auth::session_token_from(&headers).ok_or(StatusCode::UNAUTHORIZED)?;
+    let user = match auth::user_for_token(token).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            log::error!("error checking live session {:?}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let visible = visible_notebooks(&user).await.map_err(|e| {
+        log::error!("error getting live notebooks {:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let changes = CHANGES.subscribe();
+
+    let events = stream::unfold(
+        (changes, user, visible),
+        |(mut changes, user, mut visible)| async move {
+            loop {
+                let change = match changes.recv().await {
+                    Ok(change) => change,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("live stream skipped {} changes", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                };
+
+                if can_see(&change, &user, &mut visible).await {
+                    match Event::default().json_data(&change) {
+                        Ok(event) => return Some((Ok(event), (changes, user, visible))),
+                        Err(e) => log::error!("error encoding live change {:?}", e),
+                    }
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+async fn visible_notebooks(user: &Thing) -> Result<HashSet<String>, ServerFnError> {
+    Ok(auth::accessible_notebooks(user)
+        .await?
+        .into_iter()
+        .map(|notebook| notebook.to_string())
+        .collect())
+}
+
+/// Whether `change` goes to `user`. Only notebook changes, shares included, can change which
+/// notebooks the user sees, so the set is reloaded for those and note changes are checked
+/// against it without touching the database. A notebook that was just deleted or unshared
+/// still goes to the people who could see it before.
+async fn can_see(change: &LiveChange, user: &Thing, visible: &mut HashSet<String>) -> bool {
+    if change.table != NOTEBOOK_TABLE {
+        return visible.contains(&change.notebook);
+    }
+    let was_visible = visible.contains(&change.notebook);
+    match visible_notebooks(user).await {
+        Ok(notebooks) => *visible = notebooks,
+        Err(e) => log::error!("error refreshing live notebooks {:?}", e),
+    }
+    was_visible || visible.contains(&change.notebook)
+}
@@ -1,16 +1,17 @@
-#![allow(non_snake_case, unused)]
+#![allow(non_snake_case, unused, mismatched_lifetime_syntaxes)]
+#[cfg(feature = "ssr")]
 use async_once::AsyncOnce;
 use lazy_static::lazy_static;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "ssr")]
-use surrealdb::engine::remote::ws::Client;
+use surrealdb::engine::any::Any;
 
 #[cfg(feature = "ssr")]
 use surrealdb::sql::Thing;
 
 #[cfg(feature = "ssr")]
-use surrealdb::{engine::remote::ws::Ws, opt::auth::Root, Surreal};
+use surrealdb::Surreal;
 
 use std::{
     collections::{HashMap, HashSet},
@@ -18,37 +19,148 @@ use std::{
     time::Duration,
 };
 
-use crate::component::loading::Loading;
+use crate::component::{loading::Loading, login::Login};
 use crate::component::{notebook_bar::NotebookBar, notes_bar::NotesBar, notes_view::NotesView};
+use crate::component::command_palette::CommandPalette;
+use crate::component::trash_view::TrashView;
 use crate::model::notebook::NotebookNoteCount;
+use crate::model::tag::TagNoteCount;
 use dioxus::prelude::*;
 use dioxus_fullstack::prelude::{server_fn::error::ServerFnErrorErr, *};
+use error::NotesError;
 use log::LevelFilter;
-use model::{note::Note, notebook::Notebook};
+use model::{
+    attachment::Attachment,
+    backlink::Backlink,
+    export::{ExportFile, ExportFormat, ExportedNote, NotebookExport},
+    live::{LiveAction, LiveChange},
+    note::{Note, NotePage, NoteSort},
+    notebook::{descendant_ids, tree_rows, Notebook},
+    related::RelatedNote,
+    revision::NoteRevision,
+    search::NoteSearchHit,
+    share::{NotebookShare, Role},
+    shortcut::{ShortcutAction, Shortcuts},
+    tag::Tag,
+    template::{Template, TemplateVars},
+    trash::{TrashItem, TrashKind},
+    user::User,
+};
+#[cfg(feature = "ssr")]
+mod api;
+#[cfg(feature = "ssr")]
+mod archive;
+#[cfg(feature = "ssr")]
+mod attachments;
+#[cfg(feature = "ssr")]
+mod auth;
 pub mod component;
+#[cfg(feature = "ssr")]
+mod config;
+#[cfg(feature = "ssr")]
+mod data;
+#[cfg(feature = "web")]
+mod download;
+#[cfg(feature = "web")]
+mod drafts;
+#[cfg(feature = "web")]
+mod drops;
+#[cfg(feature = "ssr")]
+mod embeddings;
+pub mod error;
+#[cfg(feature = "web")]
+mod events;
+#[cfg(feature = "ssr")]
+mod links;
+#[cfg(feature = "ssr")]
+mod live;
+pub mod markdown;
+#[cfg(feature = "ssr")]
+mod migrations;
 pub mod model;
+#[cfg(all(test, feature = "ssr"))]
+mod tests;
+#[cfg(feature = "web")]
+mod shortcuts;
+#[cfg(feature = "ssr")]
+mod trash;
 
 fn main() {
     dioxus_logger::init(LevelFilter::Info).expect("failed to init logger");
+
+    // the server is set up by hand so the api and the live event stream can sit next to the app
+    #[cfg(feature = "ssr")]
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_stack_size(RUNTIME_STACK_SIZE)
+        .build()
+        .expect("failed to start the runtime")
+        .block_on(async {
+            live::start();
+            trash::start();
+            embeddings::start();
+            let router = axum::Router::new()
+                .nest(API_PATH, api::router())
+                .route(LIVE_EVENTS_PATH, axum::routing::get(live::events))
+                .route(
+                    &format!("{}/:hash", ATTACHMENTS_PATH),
+                    axum::routing::get(attachments::serve),
+                )
+                .serve_dioxus_application("", ServeConfigBuilder::new(app, ()));
+            axum::Server::bind(&SERVER_ADDR.parse().expect("invalid server address"))
+                .serve(router.into_make_service())
+                .await
+                .expect("server stopped");
+        });
+
+    #[cfg(not(feature = "ssr"))]
     LaunchBuilder::new(app).launch();
 }
 
 const NOTE_TABLE: &str = "note";
 const NOTEBOOK_TABLE: &str = "notebook";
+const NOTE_REVISION_TABLE: &str = "note_revision";
+const TAG_TABLE: &str = "tag";
+const TAGGED_TABLE: &str = "tagged";
+const SHARE_TABLE: &str = "share";
+const ATTACHMENT_TABLE: &str = "attachment";
+const LINKS_TO_TABLE: &str = "links_to";
+const NOTE_EMBEDDING_TABLE: &str = "note_embedding";
+const TEMPLATE_TABLE: &str = "template";
+const SERVER_ADDR: &str = "127.0.0.1:8080";
+const LIVE_EVENTS_PATH: &str = "/live";
+const API_PATH: &str = "/api/v1";
+const ATTACHMENTS_PATH: &str = "/attachments";
+const MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+const SEARCH_RESULT_LIMIT: u32 = 50;
+const MAX_RELATED_NOTES: u32 = 20;
+const PREVIEW_LEN: usize = 40;
+const PREVIEW_SOURCE_LEN: usize = 200;
+const NOTE_PAGE_SIZE: u32 = 50;
+// the most a single page request returns, the command palette asks for this many at once
+const MAX_NOTE_PAGE_SIZE: u32 = 5000;
+// surrealdb recurses deep enough in debug builds to overflow tokio's default 2 MiB
+const RUNTIME_STACK_SIZE: usize = 16 * 1024 * 1024;
 
 #[cfg(feature = "ssr")]
 lazy_static! {
-    static ref DB: AsyncOnce<Surreal<Client>> = {
+    static ref DB: AsyncOnce<Surreal<Any>> = {
         AsyncOnce::new(async {
-            let db: Surreal<Client> = Surreal::new::<Ws>("127.0.0.1:8000")
+            let config = config::DbConfig::load().expect("invalid database config");
+            log::info!("using {}", config.storage.endpoint());
+            let db: Surreal<Any> = surrealdb::engine::any::connect(config.storage.endpoint())
                 .await
                 .expect("couldn't connect to surrealdb");
 
-            db.use_ns("test")
-                .use_db("test")
+            db.use_ns(config.namespace)
+                .use_db(config.database)
                 .await
                 .expect("could not use ns and db");
 
+            migrations::run(&db)
+                .await
+                .expect("could not apply migrations");
+
             db
         })
     };
@@ -61,235 +173,1360 @@ struct Record {
     id: Thing,
 }
 
+#[cfg(feature = "ssr")]
+#[derive(Debug, Deserialize)]
+struct UserRow {
+    id: Thing,
+    username: String,
+    password_hash: String,
+}
+
 #[server]
-async fn get_note(note_id: String) -> Result<Note, ServerFnError> {
+async fn sign_up(username: String, password: String) -> Result<User, ServerFnError> {
+    let username = username.trim().to_string();
+    if username.is_empty() {
+        return Err(NotesError::Validation("username can't be empty".to_string()).into());
+    }
+    if password.chars().count() < auth::MIN_PASSWORD_LEN {
+        return Err(NotesError::Validation(format!(
+            "password needs at least {} characters",
+            auth::MIN_PASSWORD_LEN
+        ))
+        .into());
+    }
+
+    let password_hash = auth::hash_password(&password)?;
     let con = DB.get().await;
-    let res: Option<Note> = con
-        .query("SELECT type::string(id) as id, title, content, type::string(notebook) as notebook FROM type::thing($note_id)")
-        .bind(("note_id", note_id))
-        .await?
-        .take(0)?;
 
-    res.ok_or(ServerFnError::ServerError("couldn't get note".to_string()))
+    // the unique index on username is what rejects a taken name
+    let res: Option<UserRow> = con
+        .query("CREATE ONLY user SET username = $username, password_hash = $password_hash;")
+        .bind(("username", username))
+        .bind(("password_hash", password_hash))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(|_| NotesError::Validation("username is taken".to_string()))?;
+    let user = res.ok_or(NotesError::Db("couldn't create user".to_string()))?;
+
+    auth::start_session(user.id.clone()).await?;
+
+    Ok(User {
+        id: user.id.to_string(),
+        username: user.username,
+    })
+}
+
+#[server]
+async fn log_in(username: String, password: String) -> Result<User, ServerFnError> {
+    match auth::check_credentials(&username, &password).await? {
+        Some(user) => {
+            auth::start_session(user.clone()).await?;
+            Ok(User {
+                id: user.to_string(),
+                username: username.trim().to_string(),
+            })
+        }
+        None => Err(NotesError::Unauthorized("wrong username or password".to_string()).into()),
+    }
+}
+
+#[server]
+async fn log_out() -> Result<(), ServerFnError> {
+    auth::end_session().await
+}
+
+#[server]
+async fn get_current_user() -> Result<Option<User>, ServerFnError> {
+    let Some(user_id) = auth::current_user_id().await? else {
+        return Ok(None);
+    };
+
+    let con = DB.get().await;
+    let res: Option<User> = con
+        .query("SELECT type::string(id) as id, username FROM $user_id")
+        .bind(("user_id", user_id))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    Ok(res)
+}
+
+/// The key bindings from the `[shortcuts]` config, the same for everyone.
+#[server]
+async fn get_shortcuts() -> Result<Shortcuts, ServerFnError> {
+    config::shortcuts().map_err(|e| NotesError::Db(e).into())
 }
 
+#[server]
+async fn get_note(note_id: String) -> Result<Note, ServerFnError> {
+    let user = auth::require_user().await?;
+    data::get_note(&user, note_id).await
+}
+
+/// Creates the note, or saves it when `note.version` is still the stored one. A stale version
+/// fails with `NotesError::Conflict` instead of overwriting whatever was saved in between.
 #[server]
 async fn upsert_note(note: Note) -> Result<String, ServerFnError> {
+    let user = auth::require_user().await?;
+    data::upsert_note(&user, note).await
+}
+
+#[server]
+async fn upsert_notebook(notebook: Notebook) -> Result<String, ServerFnError> {
+    let user = auth::require_user().await?;
+    data::upsert_notebook(&user, notebook).await
+}
+
+/// Moves a notebook to the trash along with its notes, or moves the notes into `move_notes_to`
+/// first when it's set. Shares are kept so restoring the notebook brings them back.
+#[server]
+async fn delete_notebook(
+    notebook: Notebook,
+    move_notes_to: Option<String>,
+) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
     let con = DB.get().await;
 
-    let res: Vec<Record> = if let Some(id) = note.id {
-        con.query("UPDATE ONLY type::thing($id) SET notebook = type::thing($notebook), title = $title, content = $content;")
-        .bind(("id", id))
-        .bind(("notebook", note.notebook))
-        .bind(("content", note.content))
-        .bind(("title", note.title))
+    let notebook_id = notebook
+        .id
+        .ok_or(NotesError::Validation("can't delete All Notes".to_string()))?;
+    auth::require_role(&notebook_id, &user, Role::Owner).await?;
+    let notebook_thing = data::thing(&notebook_id)?;
+
+    let res = if let Some(target) = move_notes_to {
+        if target == notebook_id {
+            return Err(NotesError::Validation(
+                "can't move notes into the notebook being deleted".to_string(),
+            )
+            .into());
+        }
+        auth::require_role(&target, &user, Role::Editor).await?;
+        let target_thing = data::thing_in(NOTEBOOK_TABLE, &target)?;
+        data::require_not_trashed(&target_thing).await?;
+
+        // notes already in the trash move too, so restoring them puts them somewhere that exists
+        con.query("BEGIN TRANSACTION;")
+            .query("UPDATE type::table($table) SET notebook = $target_thing WHERE notebook = $notebook_thing;")
+            .query("UPDATE $notebook_thing SET deleted_at = time::now();")
+            .query("COMMIT TRANSACTION;")
+            .bind(("table", NOTE_TABLE))
+            .bind(("notebook_thing", notebook_thing))
+            .bind(("target_thing", target_thing))
+            .await
+            .map_err(NotesError::from)?
+    } else {
+        // the notes stay as they are, they're hidden with the notebook
+        con.query("UPDATE $notebook_thing SET deleted_at = time::now();")
+            .bind(("notebook_thing", notebook_thing))
+            .await
+            .map_err(NotesError::from)?
+    };
+
+    // errors inside the transaction don't fail the request, they show up on the statements
+    res.check().map_err(NotesError::from)?;
+
+    Ok(())
+}
+
+#[server]
+async fn get_notebook_shares(notebook_id: String) -> Result<Vec<NotebookShare>, ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&notebook_id, &user, Role::Owner).await?;
+    let con = DB.get().await;
+
+    let res: Vec<NotebookShare> = con
+        .query("SELECT type::string(notebook) as notebook, user.username as username, role FROM type::table($table) WHERE notebook = type::thing($notebook_id) ORDER BY username")
+        .bind(("table", SHARE_TABLE))
+        .bind(("notebook_id", notebook_id))
         .await
-        .expect("issue on await")
+        .map_err(NotesError::from)?
         .take(0)
-        .expect("issue on take")
-    } else {
-        con.query("CREATE note SET notebook = type::thing($notebook), title = $title, content = $content;")
-        .bind(("notebook", note.notebook))
-        .bind(("content", note.content))
-        .bind(("title", note.title))
+        .map_err(NotesError::from)?;
+
+    Ok(res)
+}
+
+/// Grants `username` a role on a notebook, replacing any role they already had.
+#[server]
+async fn share_notebook(
+    notebook_id: String,
+    username: String,
+    role: Role,
+) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&notebook_id, &user, Role::Owner).await?;
+    if role == Role::Owner {
+        return Err(
+            NotesError::Validation("a notebook can only have one owner".to_string()).into(),
+        );
+    }
+    let con = DB.get().await;
+
+    let targets: Vec<Thing> = con
+        .query("SELECT VALUE id FROM user WHERE username = $username;")
+        .bind(("username", username.trim().to_string()))
         .await
-        .expect("issue on await")
+        .map_err(NotesError::from)?
         .take(0)
-        .expect("issue on take")
-    };
+        .map_err(NotesError::from)?;
+    let target = targets
+        .into_iter()
+        .next()
+        .ok_or(NotesError::NotFound(format!("user {}", username)))?;
+    if target == user {
+        return Err(NotesError::Validation("you already own this notebook".to_string()).into());
+    }
 
-    match res.first() {
-        Some(Record { id }) => Ok(id.to_string()),
-        _ => Err(ServerFnError::ServerError("couldnt get id".to_string())),
+    con.query("BEGIN TRANSACTION;")
+        .query("DELETE type::table($table) WHERE notebook = type::thing($notebook_id) AND user = $target;")
+        .query("CREATE type::table($table) SET notebook = type::thing($notebook_id), user = $target, role = $role;")
+        .query("COMMIT TRANSACTION;")
+        .bind(("table", SHARE_TABLE))
+        .bind(("notebook_id", notebook_id))
+        .bind(("target", target))
+        .bind(("role", role))
+        .await
+        .map_err(NotesError::from)?
+        .check()
+        .map_err(NotesError::from)?;
+
+    Ok(())
+}
+
+#[server]
+async fn unshare_notebook(notebook_id: String, username: String) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&notebook_id, &user, Role::Owner).await?;
+    let con = DB.get().await;
+
+    con.query("DELETE type::table($table) WHERE notebook = type::thing($notebook_id) AND user.username = $username;")
+        .bind(("table", SHARE_TABLE))
+        .bind(("notebook_id", notebook_id))
+        .bind(("username", username))
+        .await
+        .map_err(NotesError::from)?
+        .check()
+        .map_err(NotesError::from)?;
+
+    Ok(())
+}
+
+#[server]
+async fn get_notebooks() -> Result<Vec<Notebook>, ServerFnError> {
+    let user = auth::require_user().await?;
+    data::get_notebooks(&user).await
+}
+
+/// A page of notes, without their full content, in `sort` order. `cursor` is the
+/// `next_cursor` of the page before, `None` for the first one. `include_children` adds the
+/// notes of the notebooks nested under `notebook_id`.
+#[server]
+async fn get_note_summaries(
+    notebook_id: Option<String>,
+    include_children: bool,
+    tag_id: Option<String>,
+    sort: NoteSort,
+    cursor: Option<String>,
+    limit: u32,
+) -> Result<NotePage, ServerFnError> {
+    let user = auth::require_user().await?;
+    data::get_note_summaries(
+        &user,
+        notebook_id,
+        include_children,
+        tag_id,
+        sort,
+        cursor,
+        limit,
+    )
+    .await
+}
+
+/// Nests a notebook under another of the user's notebooks, or back at the top level with
+/// `None`.
+#[server]
+async fn set_notebook_parent(
+    notebook_id: String,
+    parent: Option<String>,
+) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    data::set_notebook_parent(&user, notebook_id, parent).await
+}
+
+#[server]
+async fn get_tags() -> Result<Vec<Tag>, ServerFnError> {
+    let user = auth::require_user().await?;
+    let notebooks = auth::accessible_notebooks(&user).await?;
+    let con = DB.get().await;
+
+    // tags are shared by name, so only list the ones on notes the caller can see
+    let mut tags: Vec<Tag> = con
+        .query("SELECT type::string(id) as id, name FROM type::table($table) WHERE id IN (SELECT VALUE out FROM type::table($tagged_table) WHERE in.notebook IN $notebooks AND in.deleted_at IS NONE) ORDER BY name")
+        .bind(("table", TAG_TABLE))
+        .bind(("tagged_table", TAGGED_TABLE))
+        .bind(("notebooks", notebooks.clone()))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    // same approach as the notebook counts, group the edges by the tag they point at
+    let counts: Vec<TagNoteCount> = con
+        .query("SELECT type::string(out) as id, count() as count FROM type::table($table) WHERE in.notebook IN $notebooks AND in.deleted_at IS NONE GROUP BY id")
+        .bind(("table", TAGGED_TABLE))
+        .bind(("notebooks", notebooks))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    let count_map: HashMap<String, u32> = counts.into_iter().map(|ct| (ct.id, ct.count)).collect();
+    tags.iter_mut().for_each(|tag| {
+        tag.count = Some(
+            tag.id
+                .as_ref()
+                .and_then(|id| count_map.get(id))
+                .copied()
+                .unwrap_or(0),
+        );
+    });
+
+    Ok(tags)
+}
+
+#[server]
+async fn get_note_tags(note_id: String) -> Result<Vec<Tag>, ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Viewer).await?;
+    let con = DB.get().await;
+
+    let res: Vec<Tag> = con
+        .query("SELECT type::string(out) as id, out.name as name FROM type::table($table) WHERE in = type::thing($note_id) ORDER BY name")
+        .bind(("table", TAGGED_TABLE))
+        .bind(("note_id", note_id))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    Ok(res)
+}
+
+/// Tags a note, creating the tag if it doesn't exist yet. Returns the tag's id.
+#[server]
+async fn tag_note(note_id: String, tag_name: String) -> Result<String, ServerFnError> {
+    use crate::model::tag::normalize_tag_name;
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Editor).await?;
+    let con = DB.get().await;
+
+    let name = normalize_tag_name(&tag_name);
+    if name.is_empty() {
+        return Err(NotesError::Validation("tag name can't be empty".to_string()).into());
     }
+
+    // deleting any existing edge first keeps tagging idempotent
+    let res = con
+        .query("BEGIN TRANSACTION;")
+        .query("LET $note = type::thing($note_id);")
+        .query("LET $tag = type::thing($tag_table, $name);")
+        .query("UPDATE $tag SET name = $name;")
+        .query("DELETE type::table($tagged_table) WHERE in = $note AND out = $tag;")
+        .query("RELATE $note->tagged->$tag;")
+        .query("COMMIT TRANSACTION;")
+        .bind(("note_id", note_id))
+        .bind(("tag_table", TAG_TABLE))
+        .bind(("tagged_table", TAGGED_TABLE))
+        .bind(("name", name.clone()))
+        .await
+        .map_err(NotesError::from)?;
+    res.check().map_err(NotesError::from)?;
+
+    Ok(Thing::from((TAG_TABLE, name.as_str())).to_string())
 }
 
+/// Removes a tag from a note, and the tag itself once nothing is tagged with it anymore.
 #[server]
-async fn upsert_notebook(notebook: Notebook) -> Result<String, ServerFnError> {
+async fn untag_note(note_id: String, tag_id: String) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Editor).await?;
+    let con = DB.get().await;
+
+    let res = con
+        .query("BEGIN TRANSACTION;")
+        .query("LET $tag = type::thing($tag_id);")
+        .query("DELETE type::table($tagged_table) WHERE in = type::thing($note_id) AND out = $tag;")
+        .query("IF count((SELECT id FROM type::table($tagged_table) WHERE out = $tag)) = 0 { DELETE $tag; };")
+        .query("COMMIT TRANSACTION;")
+        .bind(("note_id", note_id))
+        .bind(("tag_id", tag_id))
+        .bind(("tagged_table", TAGGED_TABLE))
+        .await
+        .map_err(NotesError::from)?;
+    res.check().map_err(NotesError::from)?;
+
+    Ok(())
+}
+
+#[server]
+async fn get_templates() -> Result<Vec<Template>, ServerFnError> {
+    let user = auth::require_user().await?;
+    let con = DB.get().await;
+
+    let res: Vec<Template> = con
+        .query("SELECT type::string(id) as id, name, title, content FROM type::table($table) WHERE owner = $user ORDER BY name")
+        .bind(("table", TEMPLATE_TABLE))
+        .bind(("user", user))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    Ok(res)
+}
+
+/// Creates or updates one of the user's templates. Returns the template's id.
+#[server]
+async fn upsert_template(template: Template) -> Result<String, ServerFnError> {
+    let user = auth::require_user().await?;
+    if template.name.trim().is_empty() {
+        return Err(NotesError::Validation("template name can't be empty".to_string()).into());
+    }
     let con = DB.get().await;
 
-    let res: Vec<Record> = if let Some(id) = notebook.id {
-        con.query("UPDATE ONLY type::thing($id) SET name = $name")
-            .bind(("name", notebook.name))
+    let name = template.name.trim().to_string();
+    // the owner check is part of the update, someone else's template just isn't found
+    let res: Vec<Record> = if let Some(id) = template.id.clone() {
+        data::thing_in(TEMPLATE_TABLE, &id)?;
+        con.query("UPDATE type::thing($id) SET name = $name, title = $title, content = $content WHERE owner = $user;")
+            .bind(("id", id))
+            .bind(("name", name))
+            .bind(("title", template.title))
+            .bind(("content", template.content))
+            .bind(("user", user))
             .await
-            .expect("issue on await")
+            .map_err(NotesError::from)?
             .take(0)
-            .expect("issue on take")
+            .map_err(NotesError::from)?
     } else {
-        con.query("CREATE notebook SET name = $name;")
-            .bind(("name", notebook.name))
+        con.query("CREATE type::table($table) SET name = $name, title = $title, content = $content, owner = $user;")
+            .bind(("table", TEMPLATE_TABLE))
+            .bind(("name", name))
+            .bind(("title", template.title))
+            .bind(("content", template.content))
+            .bind(("user", user))
             .await
-            .expect("issue on await")
+            .map_err(NotesError::from)?
             .take(0)
-            .expect("issue on take")
+            .map_err(NotesError::from)?
     };
 
-    match res.first() {
-        Some(Record { id }) => Ok(id.to_string()),
-        _ => Err(ServerFnError::ServerError("couldnt get id".to_string())),
+    match (res.first(), template.id) {
+        (Some(Record { id }), _) => Ok(id.to_string()),
+        (None, Some(id)) => Err(NotesError::NotFound(id).into()),
+        (None, None) => Err(NotesError::Db("couldn't get id".to_string()).into()),
     }
 }
 
 #[server]
-async fn delete_notebook(notebook: Notebook) -> Result<(), ServerFnError> {
-    // {
-    //     let mut notebooks = NOTEBOOKS.write()?;
-    //     if !notebooks.remove(&notebook) {
-    //         return Err(ServerFnError::Request("Notebook not found".to_string()));
-    //     }
-    // }
+async fn delete_template(template_id: String) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    data::thing_in(TEMPLATE_TABLE, &template_id)?;
+    let con = DB.get().await;
 
-    // {
-    //     let mut notes = NOTES.write()?;
-    //     notes
-    //         .remove(&notebook.id)
-    //         .ok_or(ServerFnError::ServerError("note found".to_string()))?;
-    // }
+    let res: Vec<Record> = con
+        .query("DELETE type::thing($id) WHERE owner = $user RETURN BEFORE;")
+        .bind(("id", template_id.clone()))
+        .bind(("user", user))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
 
+    if res.is_empty() {
+        return Err(NotesError::NotFound(template_id).into());
+    }
     Ok(())
 }
 
 #[server]
-async fn get_notebooks() -> Result<Vec<Notebook>, ServerFnError> {
+async fn list_note_revisions(note_id: String) -> Result<Vec<NoteRevision>, ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Viewer).await?;
     let con = DB.get().await;
 
-    // really don't want this to be two queries, but this seemed like the lesser of evils
-    let mut res: surrealdb::Response = con
-        .query("SELECT type::string(id) as id, type::string(name) as name FROM type::table($table)")
-        .bind(("table", NOTEBOOK_TABLE))
+    let note_thing = data::thing(&note_id)?;
+
+    let res: Vec<NoteRevision> = con
+        .query("SELECT type::string(id) as id, type::string(note) as note, title, content, type::string(created_at) as created_at, created_at as sort_key FROM type::table($table) WHERE note = $note_thing ORDER BY sort_key DESC;")
+        .bind(("table", NOTE_REVISION_TABLE))
+        .bind(("note_thing", note_thing))
         .await
-        .expect("issue on await");
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
 
-    let mut res: Result<Vec<Notebook>, _> = res.take(0);
+    Ok(res)
+}
 
-    match res {
-        Ok(mut notebooks) => {
-            //now grab the counts
-            let mut counts: surrealdb::Response = con
-                .query("SELECT type::string(notebook) as id, count(id) as count FROM type::table($table) GROUP BY id")
-                .bind(("table", NOTE_TABLE))
-                .await
-                .expect("issue on await");
-
-            let counts: Result<Vec<NotebookNoteCount>, _> = counts.take(0);
-            match counts {
-                Ok(counts) => {
-                    //turn the notebooks into a map from id -> Notebook
-                    let count_map: HashMap<String, NotebookNoteCount> = counts
-                        .into_iter()
-                        .map(|notebook| (notebook.id.clone(), notebook))
-                        .collect();
-
-                    notebooks.iter_mut().for_each(|notebook| {
-                        let id = notebook.id.as_ref();
-                        if let Some(id) = id {
-                            let ct: Option<&NotebookNoteCount> = count_map.get(id);
-                            notebook.count = Some(ct.map(|nbct| nbct.count).unwrap_or(0));
-                        } else {
-                            notebook.count = Some(0);
-                        }
-                    });
+/// Puts a note back the way it was at `revision_id`. This is a normal save, so it shows up as
+/// the newest revision rather than rewinding the history.
+#[server]
+async fn restore_note_revision(revision_id: String) -> Result<Note, ServerFnError> {
+    let user = auth::require_user().await?;
+    data::thing_in(NOTE_REVISION_TABLE, &revision_id)?;
+    let con = DB.get().await;
 
-                    Ok(notebooks)
-                }
-                Err(e) => {
-                    log::error!("issue getting note counts {:?}", e);
-                    Err(e.into())
-                }
-            }
+    let revision: Option<NoteRevision> = con
+        .query("SELECT type::string(id) as id, type::string(note) as note, title, content, type::string(created_at) as created_at FROM type::thing($revision_id)")
+        .bind(("revision_id", revision_id.clone()))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+    let revision = revision.ok_or(NotesError::NotFound(revision_id))?;
+    auth::require_role(&revision.note, &user, Role::Editor).await?;
+
+    // a note in the trash has to be restored before one of its revisions can be
+    let note_thing = data::thing(&revision.note)?;
+    let res: Vec<Note> = con
+        .query("UPDATE type::thing($note_id) SET title = $title, content = $content, version = (version ?? 0) + 1 WHERE deleted_at IS NONE RETURN type::string(id) as id, title, content, type::string(notebook) as notebook, version;")
+        .bind(("note_id", revision.note))
+        .bind(("title", revision.title))
+        .bind(("content", revision.content))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    let note = res
+        .into_iter()
+        .next()
+        .ok_or(NotesError::NotFound(note_thing.to_string()))?;
+    data::after_note_saved(&user, &note_thing, &note.title, &note.content, true).await;
+    Ok(note)
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Debug, Deserialize)]
+struct BacklinkSource {
+    id: String,
+    title: String,
+    content: String,
+    version: u64,
+    link: String,
+}
+
+/// The notes with a `[[Title]]` link to this one, among those the user can see.
+#[server]
+async fn get_backlinks(note_id: String) -> Result<Vec<Backlink>, ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Viewer).await?;
+    let notebooks = auth::accessible_notebooks(&user).await?;
+    let con = DB.get().await;
+
+    let mut backlinks: Vec<Backlink> = con
+        .query("SELECT type::string(in) as note, in.title as title, type::string(in.notebook) as notebook, title as link FROM type::table($links_table) \
+            WHERE out = type::thing($note_id) AND in.deleted_at IS NONE AND in.notebook IN $notebooks;")
+        .bind(("links_table", LINKS_TO_TABLE))
+        .bind(("note_id", note_id))
+        .bind(("notebooks", notebooks))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+    backlinks.sort_by_key(|backlink| backlink.title.to_lowercase());
+    Ok(backlinks)
+}
+
+/// Rewrites the links to this note that still use a title it was renamed from, in the notes
+/// the user can edit. Returns how many notes were changed, a note edited in the meantime is
+/// left for the next go.
+#[server]
+async fn rewrite_backlinks(note_id: String) -> Result<u32, ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Viewer).await?;
+    let notebooks = auth::editable_notebooks(&user).await?;
+    let con = DB.get().await;
+
+    let mut res = con
+        .query("SELECT VALUE title FROM type::thing($note_id);")
+        .query("SELECT type::string(in) as id, in.title as title, in.content as content, in.version ?? 0 as version, title as link FROM type::table($links_table) \
+            WHERE out = type::thing($note_id) AND in.deleted_at IS NONE AND in.notebook IN $notebooks;")
+        .bind(("note_id", note_id.clone()))
+        .bind(("links_table", LINKS_TO_TABLE))
+        .bind(("notebooks", notebooks))
+        .await
+        .map_err(NotesError::from)?;
+    let title: Option<String> = res.take(0).map_err(NotesError::from)?;
+    let title = title.ok_or(NotesError::NotFound(note_id))?;
+    let sources: Vec<BacklinkSource> = res.take(1).map_err(NotesError::from)?;
+
+    let mut rewritten = 0;
+    for source in sources {
+        if source.link == title.trim().to_lowercase() {
+            continue;
         }
-        Err(e) => {
-            log::error!("error getting notebooks {:?}", e);
-            Err(e.into())
+        let content = markdown::rename_wiki_links(&source.content, &source.link, &title);
+        let res: Vec<Record> = con
+            .query("UPDATE type::thing($id) SET content = $content, version = (version ?? 0) + 1 WHERE (version ?? 0) = $version;")
+            .bind(("id", source.id.clone()))
+            .bind(("content", content.clone()))
+            .bind(("version", source.version))
+            .await
+            .map_err(NotesError::from)?
+            .take(0)
+            .map_err(NotesError::from)?;
+        if let Some(Record { id }) = res.first() {
+            data::after_note_saved(&user, id, &source.title, &content, false).await;
+            rewritten += 1;
         }
     }
+    Ok(rewritten)
 }
 
+/// The `k` notes closest in meaning to this one, by the cosine similarity of their
+/// embeddings. Empty until the note has been embedded, or when embeddings are turned off.
 #[server]
-async fn get_note_summaries(notebook_id: Option<String>) -> Result<Vec<Note>, ServerFnError> {
-    use std::str::FromStr;
+async fn related_notes(note_id: String, k: u32) -> Result<Vec<RelatedNote>, ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Viewer).await?;
+    let notebooks = auth::accessible_notebooks(&user).await?;
     let con = DB.get().await;
 
-    // probably a way to make this more concise
-    let res: Vec<Note> = if let Some(notebook_id) = notebook_id {
-        let notebook_thing = Thing::from_str(&notebook_id)
-            .map_err(|_| ServerFnError::ServerError("error making thing".to_string()))?;
-        con
-        .query("SELECT type::string(id) as id, title, string::slice(content, 0, 40) as content, type::string(notebook) as notebook FROM type::table($table) WHERE notebook=type::thing($notebook_thing);")
-        .bind(("table", NOTE_TABLE))
-        .bind(("notebook_thing", notebook_thing))
+    let vector: Option<Vec<f32>> = con
+        .query("SELECT VALUE vector FROM type::table($table) WHERE note = type::thing($note_id);")
+        .bind(("table", NOTE_EMBEDDING_TABLE))
+        .bind(("note_id", note_id.clone()))
         .await
-        .expect("issue on await")
+        .map_err(NotesError::from)?
         .take(0)
-        .expect("issue on take")
+        .map_err(NotesError::from)?;
+    let Some(vector) = vector else {
+        return Ok(vec![]);
+    };
+
+    let related: Vec<RelatedNote> = con
+        .query("SELECT type::string(note) as id, note.title as title, type::string(note.notebook) as notebook, \
+            vector::similarity::cosine(vector, $vector) as score FROM type::table($table) \
+            WHERE note != type::thing($note_id) AND note.deleted_at IS NONE AND note.notebook IN $notebooks \
+            AND array::len(vector) = array::len($vector) ORDER BY score DESC LIMIT $k;")
+        .bind(("table", NOTE_EMBEDDING_TABLE))
+        .bind(("note_id", note_id))
+        .bind(("vector", vector))
+        .bind(("notebooks", notebooks))
+        .bind(("k", k.clamp(1, MAX_RELATED_NOTES)))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+    Ok(related)
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Debug, Deserialize)]
+struct SearchRow {
+    id: String,
+    title: String,
+    notebook: String,
+    content: String,
+    score: f32,
+}
+
+#[server]
+async fn search_notes(
+    query: String,
+    notebook_id: Option<String>,
+) -> Result<Vec<NoteSearchHit>, ServerFnError> {
+    use crate::model::search::{make_snippet, HIGHLIGHT_END, HIGHLIGHT_START};
+
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let user = auth::require_user().await?;
+    let notebooks = auth::accessible_notebooks(&user).await?;
+    let con = DB.get().await;
+
+    let notebook_filter = if notebook_id.is_some() {
+        "AND notebook = type::thing($notebook_thing)"
     } else {
-        con
-        .query("SELECT type::string(id) as id, title, string::slice(content, 0, 40) as content, type::string(notebook) as notebook FROM type::table($table);")
+        ""
+    };
+
+    let sql = format!(
+        "SELECT type::string(id) as id, type::string(notebook) as notebook, \
+        (search::highlight($hl_start, $hl_end, 0) OR title) as title, \
+        (search::highlight($hl_start, $hl_end, 1) OR content) as content, \
+        (search::score(0) OR 0) * 2 + (search::score(1) OR 0) as score \
+        FROM type::table($table) WHERE (title @0@ $query OR content @1@ $query) AND notebook IN $notebooks AND deleted_at IS NONE {} \
+        ORDER BY score DESC LIMIT $limit;",
+        notebook_filter
+    );
+
+    let mut req = con
+        .query(sql)
         .bind(("table", NOTE_TABLE))
+        .bind(("query", query))
+        .bind(("notebooks", notebooks))
+        .bind(("hl_start", HIGHLIGHT_START.to_string()))
+        .bind(("hl_end", HIGHLIGHT_END.to_string()))
+        .bind(("limit", SEARCH_RESULT_LIMIT));
+
+    if let Some(notebook_id) = notebook_id {
+        let notebook_thing = data::thing(&notebook_id)?;
+        req = req.bind(("notebook_thing", notebook_thing));
+    }
+
+    let rows: Vec<SearchRow> = req
         .await
-        .expect("issue on await")
+        .map_err(NotesError::from)?
         .take(0)
-        .expect("issue on take")
-    };
+        .map_err(NotesError::from)?;
 
-    let res: Vec<Note> = res.into_iter().map(|notedb| notedb.into()).collect();
-    Ok(res)
+    Ok(rows
+        .into_iter()
+        .map(|row| NoteSearchHit {
+            id: row.id,
+            title: row.title,
+            notebook: row.notebook,
+            snippet: make_snippet(&row.content),
+            score: row.score,
+        })
+        .collect())
 }
 
+/// Moves a note to the trash, `restore_from_trash` brings it back.
 #[server]
 async fn delete_note(note_id: String) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    data::delete_note(&user, note_id).await
+}
+
+/// Moves notes into `target_notebook`, all of them or none. Their versions are bumped, so an
+/// editor still holding the old notebook gets a conflict instead of moving the note back.
+#[server]
+async fn move_notes(note_ids: Vec<String>, target_notebook: String) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&target_notebook, &user, Role::Editor).await?;
+    let mut notes = Vec::with_capacity(note_ids.len());
+    for note_id in note_ids {
+        auth::require_role(&note_id, &user, Role::Editor).await?;
+        notes.push(data::thing(&note_id)?);
+    }
+    let target_thing = data::thing(&target_notebook)?;
     let con = DB.get().await;
 
     let res = con
-        .query("DELETE type::thing($note_id)")
-        .bind(("note_id", note_id))
-        .await?;
+        .query("BEGIN TRANSACTION;")
+        .query("UPDATE $notes SET notebook = $target_thing, version = (version ?? 0) + 1 WHERE notebook != $target_thing AND deleted_at IS NONE;")
+        .query("COMMIT TRANSACTION;")
+        .bind(("notes", notes))
+        .bind(("target_thing", target_thing))
+        .await
+        .map_err(NotesError::from)?;
+    res.check().map_err(NotesError::from)?;
+
+    Ok(())
+}
+
+/// Copies notes into `target_notebook` with their tags and attachments, all of them or none.
+/// Returns the copies' ids in the order of `note_ids`.
+#[server]
+async fn copy_notes(
+    note_ids: Vec<String>,
+    target_notebook: String,
+) -> Result<Vec<String>, ServerFnError> {
+    use surrealdb::sql::Id;
+    let user = auth::require_user().await?;
+    auth::require_role(&target_notebook, &user, Role::Editor).await?;
+    // the copies' ids are picked up front so the whole batch fits in one transaction
+    let mut pairs = Vec::with_capacity(note_ids.len());
+    for note_id in note_ids {
+        auth::require_role(&note_id, &user, Role::Viewer).await?;
+        let source = data::thing(&note_id)?;
+        pairs.push((source, Thing::from((NOTE_TABLE, Id::rand()))));
+    }
+    let target_thing = data::thing(&target_notebook)?;
+    let con = DB.get().await;
+
+    // a note in the trash has to be restored before it can be copied
+    let sources: Vec<Thing> = pairs.iter().map(|(source, _)| source.clone()).collect();
+    let trashed: Vec<String> = con
+        .query("SELECT VALUE type::string(id) FROM $sources WHERE deleted_at IS NOT NONE;")
+        .bind(("sources", sources))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+    if let Some(id) = trashed.into_iter().next() {
+        return Err(NotesError::NotFound(id).into());
+    }
+
+    let res = con
+        .query("BEGIN TRANSACTION;")
+        .query("FOR $pair IN $pairs {
+            LET $source = $pair[0];
+            LET $copy = $pair[1];
+            CREATE $copy SET notebook = $target_thing, owner = $owner, title = $source.title, content = $source.content, version = 1;
+            FOR $tag IN (SELECT VALUE out FROM type::table($tagged_table) WHERE in = $source) {
+                RELATE $copy->tagged->$tag;
+            };
+            FOR $attachment IN (SELECT * FROM type::table($attachment_table) WHERE note = $source) {
+                CREATE type::table($attachment_table) SET note = $copy, name = $attachment.name, mime = $attachment.mime, size = $attachment.size, hash = $attachment.hash;
+            };
+        };")
+        .query("COMMIT TRANSACTION;")
+        .bind(("pairs", pairs.clone()))
+        .bind(("target_thing", target_thing))
+        .bind(("owner", user.clone()))
+        .bind(("tagged_table", TAGGED_TABLE))
+        .bind(("attachment_table", ATTACHMENT_TABLE))
+        .await
+        .map_err(NotesError::from)?;
+    res.check().map_err(NotesError::from)?;
+
+    let mut copies = Vec::with_capacity(pairs.len());
+    for (_, copy) in pairs {
+        let note = data::get_note(&user, copy.to_string()).await?;
+        data::after_note_saved(&user, &copy, &note.title, &note.content, false).await;
+        copies.push(copy.to_string());
+    }
+    Ok(copies)
+}
+
+/// What's in the trash for the user: their own deleted notebooks and the deleted notes in
+/// notebooks they can edit, most recently deleted first.
+#[server]
+async fn get_trash() -> Result<Vec<TrashItem>, ServerFnError> {
+    let user = auth::require_user().await?;
+    let notebooks = auth::editable_notebooks(&user).await?;
+    let retention = config::trash_retention().map_err(NotesError::Db)?;
+    let con = DB.get().await;
+
+    let mut res = con
+        .query("SELECT type::string(id) as id, 'notebook' as kind, name, type::string(deleted_at) as deleted_at, type::string(deleted_at + $retention) as purge_at FROM type::table($notebook_table) WHERE owner = $user AND deleted_at IS NOT NONE;")
+        .query("SELECT type::string(id) as id, 'note' as kind, title as name, notebook.name as notebook, type::string(deleted_at) as deleted_at, type::string(deleted_at + $retention) as purge_at FROM type::table($note_table) WHERE notebook IN $notebooks AND deleted_at IS NOT NONE;")
+        .bind(("notebook_table", NOTEBOOK_TABLE))
+        .bind(("note_table", NOTE_TABLE))
+        .bind(("user", user))
+        .bind(("notebooks", notebooks))
+        .bind(("retention", surrealdb::sql::Duration::from(retention)))
+        .await
+        .map_err(NotesError::from)?;
+
+    let mut items: Vec<TrashItem> = res.take(0).map_err(NotesError::from)?;
+    let notes: Vec<TrashItem> = res.take(1).map_err(NotesError::from)?;
+    items.extend(notes);
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(items)
+}
+
+#[cfg(feature = "ssr")]
+fn trash_kind(item_id: &str) -> Result<(Thing, TrashKind), NotesError> {
+    let thing = data::thing(item_id)?;
+    let kind = match thing.tb.as_str() {
+        NOTE_TABLE => TrashKind::Note,
+        NOTEBOOK_TABLE => TrashKind::Notebook,
+        _ => return Err(NotesError::NotFound(item_id.to_string())),
+    };
+    Ok((thing, kind))
+}
+
+#[server]
+async fn restore_from_trash(item_id: String) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    let (thing, kind) = trash_kind(&item_id)?;
+    let con = DB.get().await;
+
+    let res: Vec<Record> = match kind {
+        TrashKind::Note => {
+            auth::require_role(&item_id, &user, Role::Editor).await?;
+            con.query("UPDATE $note SET deleted_at = NONE WHERE notebook.deleted_at IS NONE;")
+                .bind(("note", thing))
+                .await
+                .map_err(NotesError::from)?
+                .take(0)
+                .map_err(NotesError::from)?
+        }
+        TrashKind::Notebook => {
+            auth::require_role(&item_id, &user, Role::Owner).await?;
+            con.query("UPDATE $notebook SET deleted_at = NONE;")
+                .bind(("notebook", thing))
+                .await
+                .map_err(NotesError::from)?
+                .take(0)
+                .map_err(NotesError::from)?
+        }
+    };
+
+    if res.is_empty() {
+        return Err(NotesError::Validation(
+            "the note's notebook is in the trash, restore that first".to_string(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Deletes something in the trash for good, without waiting for the purge.
+#[server]
+async fn delete_permanently(item_id: String) -> Result<(), ServerFnError> {
+    let user = auth::require_user().await?;
+    let (thing, kind) = trash_kind(&item_id)?;
+    let min = match kind {
+        TrashKind::Note => Role::Editor,
+        TrashKind::Notebook => Role::Owner,
+    };
+    auth::require_role(&item_id, &user, min).await?;
+    let con = DB.get().await;
+
+    let deleted: Vec<Record> = con
+        .query("SELECT id FROM $thing WHERE deleted_at IS NOT NONE;")
+        .bind(("thing", thing.clone()))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+    if deleted.is_empty() {
+        return Err(NotesError::Validation(
+            "only things in the trash can be deleted permanently".to_string(),
+        )
+        .into());
+    }
+
+    match kind {
+        TrashKind::Note => trash::purge_note(thing).await,
+        TrashKind::Notebook => trash::purge_notebook(thing).await,
+    }
+    .map_err(NotesError::from)?;
+    Ok(())
+}
+
+/// Stores a file for a note. The contents are kept by hash, so the same file attached twice
+/// is only on disk once.
+#[server]
+async fn upload_attachment(
+    note_id: String,
+    file_name: String,
+    bytes: Vec<u8>,
+) -> Result<Attachment, ServerFnError> {
+    let user = auth::require_user().await?;
+    auth::require_role(&note_id, &user, Role::Editor).await?;
+    data::require_not_trashed(&data::thing_in(NOTE_TABLE, &note_id)?).await?;
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err(NotesError::Validation(format!(
+            "attachments can be at most {} MB",
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        ))
+        .into());
+    }
+
+    let hash = attachments::store(&bytes).await.map_err(|e| {
+        log::error!("error storing attachment {:?}", e);
+        NotesError::Db("couldn't store the attachment".to_string())
+    })?;
+    let mime = attachments::mime_for(&file_name).to_string();
+    let con = DB.get().await;
+
+    let res: Vec<Record> = con
+        .query("CREATE type::table($table) SET note = type::thing($note_id), name = $name, mime = $mime, size = $size, hash = $hash;")
+        .bind(("table", ATTACHMENT_TABLE))
+        .bind(("note_id", note_id.clone()))
+        .bind(("name", file_name.clone()))
+        .bind(("mime", mime.clone()))
+        .bind(("size", bytes.len()))
+        .bind(("hash", hash.clone()))
+        .await
+        .map_err(NotesError::from)?
+        .take(0)
+        .map_err(NotesError::from)?;
+
+    match res.first() {
+        Some(Record { id }) => Ok(Attachment {
+            id: id.to_string(),
+            note: note_id,
+            name: file_name,
+            mime,
+            size: bytes.len() as u64,
+            hash,
+        }),
+        _ => Err(NotesError::Db("couldn't get id".to_string()).into()),
+    }
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Debug, Deserialize)]
+struct ExportRow {
+    id: String,
+    title: String,
+    content: String,
+    notebook: String,
+    version: u64,
+    tags: Vec<String>,
+    created_at: String,
+    updated_at: String,
+}
+
+/// The whole notebook in one of the export formats, ready for the browser to download.
+#[server]
+async fn export_notebook(
+    notebook_id: String,
+    format: ExportFormat,
+) -> Result<ExportFile, ServerFnError> {
+    let user = auth::require_user().await?;
+    let role = auth::require_role(&notebook_id, &user, Role::Viewer).await?;
+    let con = DB.get().await;
+
+    let notebook_thing = data::thing(&notebook_id)?;
 
+    let mut res = con
+        .query("SELECT type::string(id) as id, name FROM $notebook_thing;")
+        .query("SELECT type::string(id) as id, title, content, type::string(notebook) as notebook, version ?? 0 as version, ->tagged->tag.name as tags, \
+            type::string(created_at) as created_at, type::string(updated_at) as updated_at \
+            FROM type::table($table) WHERE notebook = $notebook_thing AND deleted_at IS NONE ORDER BY title;")
+        .bind(("notebook_thing", notebook_thing))
+        .bind(("table", NOTE_TABLE))
+        .await
+        .map_err(NotesError::from)?;
+
+    let notebook: Option<Notebook> = res.take(0).map_err(NotesError::from)?;
+    let notebook = notebook.ok_or(NotesError::NotFound(notebook_id))?;
+    let rows: Vec<ExportRow> = res.take(1).map_err(NotesError::from)?;
+
+    let notes: Vec<ExportedNote> = rows
+        .into_iter()
+        .map(|row| ExportedNote {
+            created_at: Some(row.created_at.clone()),
+            updated_at: Some(row.updated_at.clone()),
+            tags: row.tags,
+            note: Note {
+                id: Some(row.id),
+                title: row.title,
+                content: row.content,
+                notebook: row.notebook,
+                version: row.version,
+                created_at: Some(row.created_at),
+                updated_at: Some(row.updated_at),
+            },
+        })
+        .collect();
+    let export = NotebookExport {
+        notebook: Notebook {
+            count: Some(notes.len() as u32),
+            role: Some(role),
+            ..notebook
+        },
+        notes,
+    };
+
+    let bytes = match format {
+        ExportFormat::Markdown => archive::to_markdown_zip(&export)?,
+        ExportFormat::Json => archive::to_json(&export)?,
+    };
+    Ok(ExportFile {
+        file_name: archive::file_name(&export.notebook, format),
+        bytes,
+    })
+}
+
+/// Creates a new notebook from an uploaded export, picking the format from the file name.
+/// Imported notes get new ids, so importing the same file twice gives two notebooks. They're
+/// created and saved at the time of the import, the timestamps in the file are dropped.
+#[server]
+async fn import_notebook(file_name: String, bytes: Vec<u8>) -> Result<String, ServerFnError> {
+    let format = ExportFormat::from_file_name(&file_name).ok_or(NotesError::Validation(
+        "only .zip and .json files can be imported".to_string(),
+    ))?;
+    let export = match format {
+        ExportFormat::Markdown => archive::from_markdown_zip(&file_name, &bytes)?,
+        ExportFormat::Json => archive::from_json(&bytes)?,
+    };
+
+    let notebook_id = upsert_notebook(Notebook {
+        id: None,
+        name: export.notebook.name.clone(),
+        count: None,
+        role: None,
+        parent: None,
+        total: None,
+    })
+    .await?;
+
+    // a failed import shouldn't leave half a notebook behind, not even in the trash
+    if let Err(e) = import_notes(&notebook_id, export.notes).await {
+        if let Err(cleanup) = trash::purge_notebook(data::thing(&notebook_id)?).await {
+            log::error!("error cleaning up a failed import {:?}", cleanup);
+        }
+        return Err(e);
+    }
+
+    Ok(notebook_id)
+}
+
+#[cfg(feature = "ssr")]
+async fn import_notes(notebook_id: &str, notes: Vec<ExportedNote>) -> Result<(), ServerFnError> {
+    for exported in notes {
+        let note_id = upsert_note(Note {
+            id: None,
+            notebook: notebook_id.to_string(),
+            version: 0,
+            ..exported.note
+        })
+        .await?;
+        for tag in exported.tags {
+            tag_note(note_id.clone(), tag).await?;
+        }
+    }
     Ok(())
 }
 
 fn app(cx: Scope) -> Element {
+    let current_user: &UseFuture<Result<Option<User>, ServerFnError>> =
+        use_future(cx, (), |_| get_current_user());
+    // everything below is per user, so reload it whenever someone logs in or out
+    let user_id: Option<String> = match current_user.value() {
+        Some(Ok(Some(user))) => Some(user.id.clone()),
+        _ => None,
+    };
     let notebooks: &UseFuture<Result<Vec<Notebook>, ServerFnError>> =
-        use_future(cx, (), |_| get_notebooks());
+        use_future(cx, (&user_id,), |(user_id,)| async move {
+            if user_id.is_some() {
+                get_notebooks().await
+            } else {
+                Ok(vec![])
+            }
+        });
     let mut selected_notebook: &UseState<Option<Notebook>> = use_state(cx, || None);
-    let mut selected_note = use_state(cx, || None);
-    let mut note_summaries: &UseFuture<Result<Vec<Note>, ServerFnError>> =
-        use_future(cx, (selected_notebook), |selected_notebook| async move {
-            if let Some(Notebook { id, .. }) = selected_notebook.current().as_ref() {
-                get_note_summaries(id.clone()).await
+    let tags: &UseFuture<Result<Vec<Tag>, ServerFnError>> =
+        use_future(cx, (&user_id,), |(user_id,)| async move {
+            if user_id.is_some() {
+                get_tags().await
             } else {
                 Ok(vec![])
             }
         });
+    let selected_tag: &UseState<Option<Tag>> = use_state(cx, || None);
+    let mut selected_note: &UseState<Option<Note>> = use_state(cx, || None);
+    let note_sort: &UseState<NoteSort> = use_state(cx, NoteSort::default);
+    // where the page after the listed notes starts, the notes bar appends it when scrolled down
+    let next_cursor: &UseState<Option<String>> = use_state(cx, || None);
+    // how many notes the next load of the list covers, a live change sets it to as many as are
+    // listed so the pages already scrolled through stay put
+    let reload_len: &UseRef<u32> = use_ref(cx, || 0);
+    let include_children: &UseState<bool> = use_state(cx, || false);
+    let palette_open: &UseState<bool> = use_state(cx, || false);
+    // the ids of the notes being dragged from the notes bar onto a notebook
+    let dragged_notes: &UseState<Vec<String>> = use_state(cx, Vec::new);
+    // bumped by the save shortcut, the editor saves whenever it changes
+    let save_requests: &UseState<u64> = use_state(cx, || 0);
+    let shortcuts: &UseFuture<Result<Shortcuts, ServerFnError>> =
+        use_future(cx, (), |_| get_shortcuts());
+    // the defaults still work when the config can't be read
+    let shortcut_bindings = match shortcuts.value() {
+        Some(Ok(bindings)) => bindings.clone(),
+        _ => Shortcuts::default(),
+    };
+    // only the first page unless a live change is reloading more, a different notebook, tag or
+    // order starts the list over
+    let mut note_summaries: &UseFuture<Result<Vec<Note>, ServerFnError>> = use_future(
+        cx,
+        (selected_notebook, selected_tag, note_sort, include_children),
+        |(selected_notebook, selected_tag, note_sort, include_children)| {
+            to_owned!(next_cursor);
+            let page_size = std::mem::take(&mut *reload_len.write_silent()).max(NOTE_PAGE_SIZE);
+            async move {
+                let tag_id = selected_tag
+                    .current()
+                    .as_ref()
+                    .as_ref()
+                    .and_then(|tag| tag.id.clone());
+                // `Some(None)` is All Notes
+                let notebook_id = match selected_notebook.current().as_ref() {
+                    // the trash lists its own items
+                    Some(notebook) if notebook.is_trash() => None,
+                    Some(Notebook { id, .. }) => Some(id.clone()),
+                    None => None,
+                };
+                let Some(notebook_id) = notebook_id else {
+                    next_cursor.set(None);
+                    return Ok(vec![]);
+                };
+                let page = get_note_summaries(
+                    notebook_id,
+                    *include_children.current(),
+                    tag_id,
+                    *note_sort.current(),
+                    None,
+                    page_size,
+                )
+                .await?;
+                next_cursor.set(page.next_cursor);
+                Ok(page.notes)
+            }
+        },
+    );
+
+    // other sessions' changes arrive over the live event stream, refetch whatever they touch
+    #[cfg(feature = "web")]
+    use_future(cx, (&user_id,), |(user_id,)| {
+        to_owned!(notebooks);
+        to_owned!(tags);
+        to_owned!(note_summaries);
+        to_owned!(reload_len);
+        to_owned!(selected_notebook);
+        async move {
+            if user_id.is_none() {
+                return;
+            }
+            let Some(mut changes) = events::LiveEvents::connect() else {
+                return;
+            };
+            while let Some(change) = changes.next().await {
+                apply_live_change(
+                    change,
+                    &notebooks,
+                    &tags,
+                    &note_summaries,
+                    &reload_len,
+                    &selected_notebook,
+                );
+            }
+        }
+    });
+
+    #[cfg(feature = "web")]
+    use_future(cx, (&user_id, &shortcut_bindings), |(user_id, shortcut_bindings)| {
+        to_owned!(notebooks);
+        to_owned!(note_summaries);
+        to_owned!(selected_notebook);
+        to_owned!(selected_note);
+        to_owned!(palette_open);
+        to_owned!(save_requests);
+        async move {
+            if user_id.is_none() {
+                return;
+            }
+            let Some(mut keys) = shortcuts::ShortcutKeys::listen(shortcut_bindings) else {
+                return;
+            };
+            while let Some(action) = keys.next().await {
+                match action {
+                    ShortcutAction::CommandPalette => palette_open.set(!*palette_open.current()),
+                    ShortcutAction::Save => save_requests.set(*save_requests.current() + 1),
+                    ShortcutAction::NewNote => {
+                        let Some(notebook) = selected_notebook.current().as_ref().clone() else {
+                            continue;
+                        };
+                        if let Err(e) = add_note(
+                            notebook,
+                            None,
+                            selected_note.clone(),
+                            note_summaries.clone(),
+                            notebooks.clone(),
+                        )
+                        .await
+                        {
+                            log::error!("error adding note {:?}", e);
+                        }
+                    }
+                    ShortcutAction::NextNotebook => step_notebook(&notebooks, &selected_notebook, true),
+                    ShortcutAction::PreviousNotebook => {
+                        step_notebook(&notebooks, &selected_notebook, false)
+                    }
+                }
+            }
+        }
+    });
+
+    // a note opened together with its own notebook, like from the palette, stays open
+    use_effect(cx, (selected_notebook), |selected_notebook| {
+        to_owned!(selected_note);
+        async move {
+            let in_notebook = match (
+                selected_note.current().as_ref(),
+                selected_notebook.current().as_ref(),
+            ) {
+                (Some(note), Some(notebook)) => notebook.id.as_ref() == Some(&note.notebook),
+                _ => false,
+            };
+            if !in_notebook {
+                selected_note.set(None);
+            }
+        }
+    });
 
-    use_effect(cx, (selected_notebook,), |(selected_notebook,)| {
+    use_effect(cx, (selected_tag), |selected_tag| {
         to_owned!(selected_note);
         async move {
             selected_note.set(None);
         }
     });
 
+    match current_user.value() {
+        Some(Ok(Some(_))) => {}
+        Some(_) => {
+            return render! {
+                Login {
+                    current_user: current_user,
+                }
+            };
+        }
+        None => {
+            return render! {
+                Loading {
+                    fullscreen: true,
+                }
+            };
+        }
+    }
+
     match notebooks.state() {
-        UseFutureState::Complete(_) => {
-            rsx! {
+        // keep showing the old list while a live change reloads it
+        UseFutureState::Complete(_) | UseFutureState::Reloading(_) => {
+            render! {
                 div {
                     class: "flex h-screen text-white",
                     NotebookBar {
+                        current_user: current_user,
                         notebooks: notebooks,
+                        tags: tags,
                         selected_notebook: selected_notebook.clone(),
+                        selected_tag: selected_tag.clone(),
+                        note_summaries: note_summaries,
+                        selected_note: selected_note.clone(),
+                        dragged_notes: dragged_notes.clone(),
                     },
-                    if let Some(selected_notebook) = selected_notebook.current().as_ref() {
+                    if selected_notebook.get().as_ref().is_some_and(|nb| nb.is_trash()) {
+                        rsx! {
+                            TrashView {
+                                notebooks: notebooks,
+                                tags: tags,
+                            }
+                        }
+                    } else if let Some(selected_notebook) = selected_notebook.get() {
                         rsx! {
                             NotesBar {
                                 note_summaries: note_summaries,
                                 notebooks: notebooks,
                                 selected_note: selected_note.clone(),
                                 selected_notebook: selected_notebook.clone(),
+                                selected_tag: selected_tag.clone(),
+                                note_sort: note_sort.clone(),
+                                next_cursor: next_cursor.clone(),
+                                dragged_notes: dragged_notes.clone(),
+                                include_children: include_children.clone(),
                             },
                             NotesView {
                                 notebooks: notebooks,
+                                tags: tags,
                                 selected_note: selected_note.clone(),
                                 note_summaries: note_summaries,
+                                save_requests: save_requests.clone(),
                             }
                         }
                     } else {
@@ -315,12 +1552,25 @@ fn app(cx: Scope) -> Element {
                                 }
                             }
                         }
+                    },
+                    if *palette_open.get() {
+                        rsx! {
+                            CommandPalette {
+                                notebooks: notebooks,
+                                note_summaries: note_summaries,
+                                tags: tags,
+                                selected_notebook: selected_notebook.clone(),
+                                selected_note: selected_note.clone(),
+                                palette_open: palette_open.clone(),
+                                shortcuts: shortcut_bindings,
+                            }
+                        }
                     }
                 }
             }
         }
         _ => {
-            rsx! {
+            render! {
                 Loading {
                     fullscreen: true,
                 }
@@ -328,3 +1578,169 @@ fn app(cx: Scope) -> Element {
         }
     }
 }
+
+/// Today's date and the time right now on the user's clock, as `YYYY-MM-DD` and `HH:MM`.
+#[cfg(feature = "web")]
+fn local_date_time() -> (String, String) {
+    let now = js_sys::Date::new_0();
+    (
+        format!(
+            "{:04}-{:02}-{:02}",
+            now.get_full_year(),
+            now.get_month() + 1,
+            now.get_date()
+        ),
+        format!("{:02}:{:02}", now.get_hours(), now.get_minutes()),
+    )
+}
+
+// notes are only ever added from the browser
+#[cfg(not(feature = "web"))]
+fn local_date_time() -> (String, String) {
+    (String::new(), String::new())
+}
+
+/// Creates a note in `notebook` and opens it, for the new note button, shortcut and palette
+/// action alike. It starts out empty, or from `template` with its variables filled in.
+async fn add_note(
+    notebook: Notebook,
+    template: Option<Template>,
+    selected_note: UseState<Option<Note>>,
+    note_summaries: UseFuture<Result<Vec<Note>, ServerFnError>>,
+    notebooks: UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+) -> Result<(), ServerFnError> {
+    // All Notes and the notebooks shared read only have nowhere to put it
+    let (Some(notebook_id), true) = (notebook.id.clone(), notebook.can_edit()) else {
+        return Ok(());
+    };
+    let mut note = Note::new(notebook_id);
+    if let Some(template) = template {
+        let (date, time) = local_date_time();
+        let vars = TemplateVars {
+            date,
+            time,
+            notebook: notebook.name,
+        };
+        (note.title, note.content) = template.instantiate(&vars);
+    }
+    let id = upsert_note(note.clone()).await?;
+    selected_note.set(Some(Note { id: Some(id), ..note }));
+    note_summaries.restart();
+    // this is really really inefficient, it'd be better to just increment the count locally,
+    // but for now reload the entire notebooks future to ensure the note count is updated
+    notebooks.restart();
+    Ok(())
+}
+
+/// Moves or copies the notes dropped onto `target`, then refreshes everything counting them.
+async fn drop_notes(
+    note_ids: Vec<String>,
+    target: Notebook,
+    copy: bool,
+    selected_note: UseState<Option<Note>>,
+    note_summaries: UseFuture<Result<Vec<Note>, ServerFnError>>,
+    notebooks: UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    tags: UseFuture<Result<Vec<Tag>, ServerFnError>>,
+) -> Result<(), ServerFnError> {
+    let (Some(target_id), false) = (target.id.clone(), note_ids.is_empty()) else {
+        return Ok(());
+    };
+    if copy {
+        copy_notes(note_ids, target_id).await?;
+        // the copies carry the tags along, so those counts go up too
+        tags.restart();
+    } else {
+        move_notes(note_ids.clone(), target_id).await?;
+        // the open note's version went up with the move, reload it before the editor saves
+        let open_id = selected_note
+            .current()
+            .as_ref()
+            .as_ref()
+            .and_then(|note| note.id.clone());
+        if let Some(open_id) = open_id.filter(|id| note_ids.contains(id)) {
+            selected_note.set(Some(get_note(open_id).await?));
+        }
+    }
+    note_summaries.restart();
+    notebooks.restart();
+    Ok(())
+}
+
+/// Selects the notebook before or after the selected one, in the notebook bar's order and
+/// wrapping around at either end.
+fn step_notebook(
+    notebooks: &UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    selected_notebook: &UseState<Option<Notebook>>,
+    forward: bool,
+) {
+    let Some(Ok(list)) = notebooks.value() else {
+        return;
+    };
+    // owned notebooks first, then shared ones, each as a fully expanded tree
+    let (owned, shared): (Vec<Notebook>, Vec<Notebook>) =
+        list.iter().cloned().partition(|nb| nb.is_owned());
+    let mut order = vec![Notebook::all()];
+    for part in [owned, shared] {
+        order.extend(
+            tree_rows(&part, &HashSet::new())
+                .into_iter()
+                .map(|row| row.notebook),
+        );
+    }
+    order.push(Notebook::trash());
+
+    let current = selected_notebook
+        .current()
+        .as_ref()
+        .as_ref()
+        .and_then(|selected| order.iter().position(|nb| nb.id == selected.id));
+    let next = match (current, forward) {
+        (None, true) => 0,
+        (None, false) => order.len() - 1,
+        (Some(i), true) => (i + 1) % order.len(),
+        (Some(i), false) => (i + order.len() - 1) % order.len(),
+    };
+    selected_notebook.set(Some(order[next].clone()));
+}
+
+fn apply_live_change(
+    change: LiveChange,
+    notebooks: &UseFuture<Result<Vec<Notebook>, ServerFnError>>,
+    tags: &UseFuture<Result<Vec<Tag>, ServerFnError>>,
+    note_summaries: &UseFuture<Result<Vec<Note>, ServerFnError>>,
+    reload_len: &UseRef<u32>,
+    selected_notebook: &UseState<Option<Notebook>>,
+) {
+    // `Some(None)` is All Notes, which shows every notebook
+    let selected_id = selected_notebook
+        .current()
+        .as_ref()
+        .as_ref()
+        .map(|notebook| notebook.id.clone());
+
+    if change.table == NOTEBOOK_TABLE {
+        if change.action == LiveAction::Delete && selected_id == Some(Some(change.id.clone())) {
+            selected_notebook.set(None);
+        }
+    } else {
+        // the list may include the notebooks nested under the selected one
+        let in_child = match (notebooks.value(), selected_id.as_ref()) {
+            (Some(Ok(list)), Some(Some(id))) => descendant_ids(list, id).contains(&change.notebook),
+            _ => false,
+        };
+        if selected_id == Some(None)
+            || selected_id == Some(Some(change.notebook.clone()))
+            || in_child
+        {
+            if let Some(Ok(notes)) = note_summaries.value() {
+                *reload_len.write_silent() = notes.len() as u32;
+            }
+            note_summaries.restart();
+        }
+        if change.action == LiveAction::Delete {
+            tags.restart();
+        }
+    }
+    // the counts change with every note that comes or goes
+    notebooks.restart();
+}
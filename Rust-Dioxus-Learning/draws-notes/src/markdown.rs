@@ -0,0 +1,384 @@
+use std::ops::Range;
+
+use lazy_static::lazy_static;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+const CODE_THEME: &str = "base16-ocean.dark";
+// prefixed so the sanitizer can tell the highlighter's classes from anything else
+const CODE_CLASS_PREFIX: &str = "hl-";
+const CODE_CLASSES: ClassStyle = ClassStyle::SpacedPrefixed {
+    prefix: CODE_CLASS_PREFIX,
+};
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEMES: ThemeSet = ThemeSet::load_defaults();
+    static ref SANITIZER: ammonia::Builder<'static> = {
+        let mut builder = ammonia::Builder::default();
+        builder
+            // nested task lists still come out of push_html as disabled checkboxes
+            .add_tags(&["input"])
+            .add_tag_attributes("input", &["type", "checked", "disabled"])
+            // code blocks are coloured by the classes in CODE_CSS, any other class is dropped
+            .add_tag_attributes("span", &["class"])
+            .add_tag_attributes("pre", &["class"])
+            .attribute_filter(|_, attribute, value| match attribute {
+                "class" => {
+                    let classes: Vec<_> = value
+                        .split_whitespace()
+                        .filter(|class| class.starts_with(CODE_CLASS_PREFIX))
+                        .collect();
+                    (!classes.is_empty()).then(|| classes.join(" ").into())
+                }
+                _ => Some(value.into()),
+            });
+        builder
+    };
+    /// The stylesheet for highlighted code blocks, goes next to the rendered html.
+    pub static ref CODE_CSS: String =
+        css_for_theme_with_class_style(&THEMES.themes[CODE_THEME], CODE_CLASSES)
+            .unwrap_or_default();
+}
+
+/// A top level chunk of a rendered note. Task lists are kept apart from the html so the
+/// checkboxes can be real inputs that edit the markdown they came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Html(String),
+    TaskList(Vec<TaskItem>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskItem {
+    pub checked: bool,
+    /// byte range of the `[ ]` marker in the source
+    pub marker: Range<usize>,
+    pub html: String,
+}
+
+fn options() -> Options {
+    Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH
+}
+
+pub fn render(source: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut current = vec![];
+    let mut depth = 0;
+
+    for (event, range) in Parser::new_ext(source, options()).into_offset_iter() {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        current.push((event, range));
+
+        if depth == 0 {
+            let events = std::mem::take(&mut current);
+            match task_items(&events) {
+                Some(items) => blocks.push(Block::TaskList(items)),
+                None => blocks.push(Block::Html(to_html(
+                    events.into_iter().map(|(event, _)| event),
+                ))),
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Flip the task marker at `marker` between `[ ]` and `[x]`.
+pub fn toggle_task(source: &str, marker: &Range<usize>) -> String {
+    let mut toggled = source.to_string();
+    let Some(open) = source.get(marker.clone()).and_then(|slice| slice.find('[')) else {
+        return toggled;
+    };
+
+    let pos = marker.start + open + 1;
+    match source.get(pos..pos + 1) {
+        Some(" ") => toggled.replace_range(pos..pos + 1, "x"),
+        Some("x") | Some("X") => toggled.replace_range(pos..pos + 1, " "),
+        _ => {}
+    }
+    toggled
+}
+
+/// Plain text version of a note for the summaries, with all the markdown syntax dropped.
+pub fn strip(source: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new_ext(source, options()) {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak
+            | Event::HardBreak
+            | Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::Item)
+            | Event::End(TagEnd::CodeBlock) => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The `[[Title]]` links in a note with the byte range each one covers. Links inside code
+/// are left out, so documenting the syntax doesn't create links.
+fn wiki_link_spans(source: &str) -> Vec<(Range<usize>, String)> {
+    let code: Vec<Range<usize>> = Parser::new_ext(source, options())
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::Start(Tag::CodeBlock(_)) | Event::Code(_)))
+        .map(|(_, range)| range)
+        .collect();
+
+    let mut spans = vec![];
+    let mut rest = 0;
+    while let Some(open) = source[rest..].find("[[") {
+        let start = rest + open;
+        let Some(close) = source[start + 2..].find("]]") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        let title = &source[start + 2..end - 2];
+        // `[[[a]]` holds the link `[[a]]` one character on
+        if title.contains(['[', '\n']) {
+            rest = start + 1;
+            continue;
+        }
+        if !title.trim().is_empty() && !code.iter().any(|c| c.start < end && start < c.end) {
+            spans.push((start..end, title.trim().to_string()));
+        }
+        rest = end;
+    }
+    spans
+}
+
+/// The titles a note links to with `[[Title]]`, lowercased since links ignore case.
+pub fn wiki_links(source: &str) -> Vec<String> {
+    let mut links: Vec<String> = wiki_link_spans(source)
+        .into_iter()
+        .map(|(_, title)| title.to_lowercase())
+        .collect();
+    links.sort();
+    links.dedup();
+    links
+}
+
+/// Points every `[[from]]` link at `[[to]]` instead.
+pub fn rename_wiki_links(source: &str, from: &str, to: &str) -> String {
+    let from = from.to_lowercase();
+    let mut renamed = source.to_string();
+    // back to front, so the ranges still to come stay where they were
+    for (range, title) in wiki_link_spans(source).into_iter().rev() {
+        if title.to_lowercase() == from {
+            renamed.replace_range(range, &format!("[[{}]]", to.trim()));
+        }
+    }
+    renamed
+}
+
+/// Splits a top level list into task items, or `None` if any item isn't a task.
+fn task_items(events: &[(Event, Range<usize>)]) -> Option<Vec<TaskItem>> {
+    if !matches!(events.first(), Some((Event::Start(Tag::List(_)), _))) {
+        return None;
+    }
+
+    let mut items = vec![];
+    let mut current = vec![];
+    let mut depth = 0;
+    for (event, range) in &events[1..events.len() - 1] {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        current.push((event.clone(), range.clone()));
+
+        if depth == 0 {
+            items.push(task_item(std::mem::take(&mut current))?);
+        }
+    }
+    Some(items)
+}
+
+fn task_item(events: Vec<(Event, Range<usize>)>) -> Option<TaskItem> {
+    let marker_pos = events
+        .iter()
+        .position(|(event, _)| matches!(event, Event::TaskListMarker(_)))?;
+
+    // a marker after a nested list belongs to the nested item, not this one
+    if events[..marker_pos]
+        .iter()
+        .any(|(event, _)| matches!(event, Event::Start(Tag::List(_))))
+    {
+        return None;
+    }
+
+    let (Event::TaskListMarker(checked), marker) = events[marker_pos].clone() else {
+        return None;
+    };
+
+    let body = events
+        .into_iter()
+        .enumerate()
+        .filter(|(i, (event, _))| {
+            *i != marker_pos
+                && !matches!(event, Event::Start(Tag::Item) | Event::End(TagEnd::Item))
+        })
+        .map(|(_, (event, _))| event);
+
+    Some(TaskItem {
+        checked,
+        marker,
+        html: to_html(body),
+    })
+}
+
+fn to_html<'a>(events: impl Iterator<Item = Event<'a>>) -> String {
+    let mut out = vec![];
+    // language and source of the code block we're inside, if any
+    let mut code: Option<(String, String)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => "".to_string(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::Text(text) if code.is_some() => {
+                if let Some((_, source)) = code.as_mut() {
+                    source.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, source)) = code.take() {
+                    out.push(Event::Html(highlight(&lang, &source).into()));
+                }
+            }
+            event => out.push(event),
+        }
+    }
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, out.into_iter());
+    SANITIZER.clean(&html).to_string()
+}
+
+fn highlight(lang: &str, source: &str) -> String {
+    let syntax = SYNTAXES
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CODE_CLASSES);
+    let code = LinesWithEndings::from(source)
+        .try_for_each(|line| generator.parse_html_for_line_which_includes_newline(line))
+        .map(|_| generator.finalize())
+        .unwrap_or_else(|_| ammonia::clean_text(source));
+    format!(
+        "<pre class=\"{}code\"><code>{}</code></pre>",
+        CODE_CLASS_PREFIX, code
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_splits_out_task_lists() {
+        let source = "# Groceries\n\n- [ ] milk\n- [x] *eggs*\n\nAnd\n\n- plain item\n";
+        let blocks = render(source);
+        assert_eq!(blocks.len(), 4);
+        assert!(matches!(&blocks[0], Block::Html(html) if html.contains("<h1>Groceries</h1>")));
+        let Block::TaskList(items) = &blocks[1] else {
+            panic!("expected a task list, got {:?}", blocks[1]);
+        };
+        assert_eq!(items.len(), 2);
+        assert!(!items[0].checked);
+        assert!(items[1].checked);
+        assert_eq!(&source[items[0].marker.clone()], "[ ]");
+        assert!(items[1].html.contains("<em>eggs</em>"));
+        // one plain item is enough to keep the whole list as html
+        assert!(matches!(&blocks[3], Block::Html(html) if html.contains("<li>plain item</li>")));
+    }
+
+    #[test]
+    fn render_sanitizes_and_highlights() {
+        let blocks = render(
+            "<script>alert(1)</script>\n\n\
+             <span style=\"position: fixed\" class=\"fixed hl-keyword\">hi</span>\n\n\
+             ```rust\nfn main() {}\n```\n",
+        );
+        let html: String = blocks
+            .into_iter()
+            .map(|block| match block {
+                Block::Html(html) => html,
+                Block::TaskList(_) => panic!("no task list in this note"),
+            })
+            .collect();
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("style="));
+        assert!(html.contains(r#"<span class="hl-keyword">hi</span>"#));
+        assert!(html.contains(r#"<pre class="hl-code">"#));
+        assert!(CODE_CSS.contains(".hl-code"));
+        assert!(html.contains("main"));
+    }
+
+    #[test]
+    fn toggle_task_flips_the_marker() {
+        let source = "- [ ] one\n- [X] two\n";
+        let Block::TaskList(items) = &render(source)[0] else {
+            panic!("expected a task list");
+        };
+        assert_eq!(
+            toggle_task(source, &items[0].marker),
+            "- [x] one\n- [X] two\n"
+        );
+        assert_eq!(
+            toggle_task(source, &items[1].marker),
+            "- [ ] one\n- [ ] two\n"
+        );
+        // a range that doesn't point at a marker leaves the note alone
+        assert_eq!(toggle_task(source, &(6..9)), source);
+        assert_eq!(toggle_task(source, &(100..103)), source);
+    }
+
+    #[test]
+    fn strip_drops_the_markdown() {
+        assert_eq!(
+            strip("# Title\n\nSome **bold** and `code`.\n\n- one\n- two\n"),
+            "Title Some bold and code. one two"
+        );
+        assert_eq!(strip(""), "");
+    }
+
+    #[test]
+    fn wiki_links_outside_code() {
+        let source = "See [[Recipes]] and [[ recipes ]], [[Soup]].\n\n\
+                      `[[Inline]]`\n\n```\n[[Fenced]]\n```\n\n[[]] [[[Nested]]";
+        assert_eq!(wiki_links(source), vec!["nested", "recipes", "soup"]);
+        assert!(wiki_links("[[unclosed").is_empty());
+    }
+
+    #[test]
+    fn rename_wiki_links_ignores_case() {
+        assert_eq!(
+            rename_wiki_links("[[Soup]], [[soup]] and [[Soups]]", "SOUP", " Stew "),
+            "[[Stew]], [[Stew]] and [[Soups]]"
+        );
+        assert_eq!(
+            rename_wiki_links("`[[Soup]]`", "soup", "Stew"),
+            "`[[Soup]]`"
+        );
+    }
+}
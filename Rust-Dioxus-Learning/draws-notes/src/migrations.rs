@@ -0,0 +1,207 @@
+use surrealdb::{engine::any::Any, Surreal};
+
+const MIGRATIONS_TABLE: &str = "_migrations";
+
+/// A batch of schema statements applied once, in version order, inside a transaction.
+struct Migration {
+    version: u32,
+    name: &'static str,
+    statements: &'static str,
+}
+
+// never edit a migration that has shipped, add a new one instead. The first four predate the
+// runner and were applied on every start, which is fine since DEFINE replaces an existing
+// definition.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "search indexes",
+        // full text indexes backing search_notes, title matches are weighted higher at query time
+        statements: "
+            DEFINE ANALYZER note_analyzer TOKENIZERS blank,class,punct FILTERS lowercase,ascii,snowball(english);
+            DEFINE INDEX note_title_search ON TABLE note FIELDS title SEARCH ANALYZER note_analyzer BM25 HIGHLIGHTS;
+            DEFINE INDEX note_content_search ON TABLE note FIELDS content SEARCH ANALYZER note_analyzer BM25 HIGHLIGHTS;
+        ",
+    },
+    Migration {
+        version: 2,
+        name: "note revisions",
+        // every save of a note snapshots it into note_revision, the events run inside the
+        // same transaction as the write so a revision can't go missing
+        statements: "
+            DEFINE TABLE note_revision SCHEMALESS PERMISSIONS FOR select, create FULL, FOR update, delete NONE;
+            DEFINE INDEX note_revision_note ON TABLE note_revision FIELDS note;
+            DEFINE EVENT note_revision_on_save ON TABLE note
+                WHEN $event = 'CREATE' OR ($event = 'UPDATE' AND ($before.title != $after.title OR $before.content != $after.content))
+                THEN (CREATE note_revision SET note = $after.id, title = $after.title, content = $after.content, created_at = time::now());
+            DEFINE EVENT note_revision_on_delete ON TABLE note
+                WHEN $event = 'DELETE'
+                THEN (DELETE note_revision WHERE note = $before.id);
+        ",
+    },
+    Migration {
+        version: 3,
+        name: "tag indexes",
+        statements: "
+            DEFINE INDEX tagged_out ON TABLE tagged FIELDS out;
+            DEFINE INDEX tagged_in ON TABLE tagged FIELDS in;
+        ",
+    },
+    Migration {
+        version: 4,
+        name: "users and shares",
+        statements: "
+            DEFINE INDEX user_username ON TABLE user FIELDS username UNIQUE;
+            DEFINE INDEX notebook_owner ON TABLE notebook FIELDS owner;
+            DEFINE INDEX note_owner ON TABLE note FIELDS owner;
+            DEFINE INDEX share_notebook_user ON TABLE share FIELDS notebook, user UNIQUE;
+            DEFINE INDEX share_user ON TABLE share FIELDS user;
+        ",
+    },
+    Migration {
+        version: 5,
+        name: "notebook references as record links",
+        // early notes kept their notebook as the id string, with or without the table prefix
+        statements: "
+            UPDATE note SET notebook = IF string::startsWith(notebook, 'notebook:') THEN type::thing(notebook) ELSE type::thing('notebook', notebook) END
+                WHERE type::is::string(notebook);
+        ",
+    },
+    Migration {
+        version: 6,
+        name: "note and notebook tables",
+        // older rows may have no owner or version, the queries read those as `?? 0`
+        statements: "
+            DEFINE TABLE notebook SCHEMAFULL;
+            DEFINE FIELD name ON TABLE notebook TYPE string;
+            DEFINE FIELD owner ON TABLE notebook TYPE option<record<user>>;
+
+            DEFINE TABLE note SCHEMAFULL;
+            DEFINE FIELD title ON TABLE note TYPE string DEFAULT '';
+            DEFINE FIELD content ON TABLE note TYPE string DEFAULT '';
+            DEFINE FIELD notebook ON TABLE note TYPE record<notebook>;
+            DEFINE FIELD owner ON TABLE note TYPE option<record<user>>;
+            DEFINE FIELD version ON TABLE note TYPE int DEFAULT 0;
+            DEFINE INDEX note_notebook ON TABLE note FIELDS notebook;
+        ",
+    },
+    Migration {
+        version: 7,
+        name: "attachments",
+        statements: "
+            DEFINE TABLE attachment SCHEMAFULL;
+            DEFINE FIELD note ON TABLE attachment TYPE record<note>;
+            DEFINE FIELD name ON TABLE attachment TYPE string;
+            DEFINE FIELD mime ON TABLE attachment TYPE string;
+            DEFINE FIELD size ON TABLE attachment TYPE int;
+            DEFINE FIELD hash ON TABLE attachment TYPE string;
+            DEFINE FIELD created_at ON TABLE attachment TYPE datetime DEFAULT time::now();
+            DEFINE INDEX attachment_note ON TABLE attachment FIELDS note;
+            DEFINE INDEX attachment_hash ON TABLE attachment FIELDS hash;
+        ",
+    },
+    Migration {
+        version: 8,
+        name: "soft delete",
+        statements: "
+            DEFINE FIELD deleted_at ON TABLE note TYPE option<datetime>;
+            DEFINE FIELD deleted_at ON TABLE notebook TYPE option<datetime>;
+            DEFINE INDEX note_deleted_at ON TABLE note FIELDS deleted_at;
+        ",
+    },
+    Migration {
+        version: 9,
+        name: "note timestamps",
+        // older notes take the times of their first and last revision. the fields only become
+        // server managed after that, their VALUE clauses would otherwise overwrite the backfill
+        statements: "
+            DEFINE FIELD created_at ON TABLE note TYPE datetime DEFAULT time::now();
+            DEFINE FIELD updated_at ON TABLE note TYPE datetime DEFAULT time::now();
+            UPDATE note SET
+                created_at = array::first(array::sort((SELECT VALUE created_at FROM note_revision WHERE note = $parent.id))) ?? time::now(),
+                updated_at = array::last(array::sort((SELECT VALUE created_at FROM note_revision WHERE note = $parent.id))) ?? time::now();
+            DEFINE FIELD created_at ON TABLE note TYPE datetime DEFAULT time::now() VALUE $before OR time::now();
+            DEFINE FIELD updated_at ON TABLE note TYPE datetime DEFAULT time::now() VALUE time::now();
+        ",
+    },
+    Migration {
+        version: 10,
+        name: "note links",
+        // `title` is the link as written, lowercased. notes saved before this get their links
+        // the next time they're saved
+        statements: "
+            DEFINE TABLE links_to SCHEMALESS;
+            DEFINE INDEX links_to_in ON TABLE links_to FIELDS in;
+            DEFINE INDEX links_to_out ON TABLE links_to FIELDS out;
+        ",
+    },
+    Migration {
+        version: 11,
+        name: "note embeddings",
+        // kept apart from the note so embedding one doesn't count as changing it. the length
+        // of `vector` depends on the configured model
+        statements: "
+            DEFINE TABLE note_embedding SCHEMAFULL;
+            DEFINE FIELD note ON TABLE note_embedding TYPE record<note>;
+            DEFINE FIELD vector ON TABLE note_embedding TYPE array<float>;
+            DEFINE FIELD hash ON TABLE note_embedding TYPE string;
+            DEFINE INDEX note_embedding_note ON TABLE note_embedding FIELDS note UNIQUE;
+        ",
+    },
+    Migration {
+        version: 12,
+        name: "nested notebooks",
+        // a notebook nests under one of its owner's notebooks, the server keeps it from ending
+        // up under itself
+        statements: "
+            DEFINE FIELD parent ON TABLE notebook TYPE option<record<notebook>>;
+            DEFINE INDEX notebook_parent ON TABLE notebook FIELDS parent;
+        ",
+    },
+    Migration {
+        version: 13,
+        name: "note templates",
+        // templates belong to a user rather than a notebook, the variables in them are left
+        // as written until a note is made from one
+        statements: "
+            DEFINE TABLE template SCHEMAFULL;
+            DEFINE FIELD name ON TABLE template TYPE string;
+            DEFINE FIELD title ON TABLE template TYPE string;
+            DEFINE FIELD content ON TABLE template TYPE string;
+            DEFINE FIELD owner ON TABLE template TYPE record<user>;
+            DEFINE INDEX template_owner ON TABLE template FIELDS owner;
+        ",
+    },
+];
+
+/// Applies every migration missing from `_migrations`. Runs before anything else touches the
+/// database, so a failure here stops the server rather than running on a half-built schema.
+pub async fn run(db: &Surreal<Any>) -> surrealdb::Result<()> {
+    let applied: Vec<u32> = db
+        .query("SELECT VALUE version FROM type::table($table);")
+        .bind(("table", MIGRATIONS_TABLE))
+        .await?
+        .take(0)?;
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+    {
+        log::info!(
+            "applying migration {} ({})",
+            migration.version,
+            migration.name
+        );
+        db.query("BEGIN TRANSACTION;")
+            .query(migration.statements)
+            .query("CREATE type::thing($table, $version) SET version = $version, name = $name, applied_at = time::now();")
+            .query("COMMIT TRANSACTION;")
+            .bind(("table", MIGRATIONS_TABLE))
+            .bind(("version", migration.version))
+            .bind(("name", migration.name))
+            .await?
+            .check()?;
+    }
+
+    Ok(())
+}
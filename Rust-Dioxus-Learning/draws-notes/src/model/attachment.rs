@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ATTACHMENTS_PATH;
+
+/// A file uploaded to a note. The contents are stored once per hash, however many notes
+/// have the same file attached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    pub note: String,
+    pub name: String,
+    pub mime: String,
+    pub size: u64,
+    /// sha-256 of the contents, hex encoded
+    pub hash: String,
+}
+
+impl Attachment {
+    pub fn url(&self) -> String {
+        format!("{}/{}", ATTACHMENTS_PATH, self.hash)
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+
+    /// The markdown that puts the attachment in a note, images are shown inline.
+    pub fn markdown(&self) -> String {
+        let name = self.name.replace(['[', ']'], "");
+        if self.is_image() {
+            format!("![{}]({})", name, self.url())
+        } else {
+            format!("[{}]({})", name, self.url())
+        }
+    }
+}
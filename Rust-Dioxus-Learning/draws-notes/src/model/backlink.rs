@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// A `[[Title]]` link from another note to the one being looked at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backlink {
+    /// the note the link is in
+    pub note: String,
+    pub title: String,
+    pub notebook: String,
+    /// the title as the link spells it, lowercased
+    pub link: String,
+}
+
+impl Backlink {
+    /// Whether the link still uses a title the note has been renamed from since.
+    pub fn is_stale(&self, title: &str) -> bool {
+        self.link != title.trim().to_lowercase()
+    }
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Unsaved edits to a note, kept in the browser so they survive a reload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Draft {
+    pub title: String,
+    pub content: String,
+    /// the version the edits were made on, so restoring them still catches a newer save
+    pub base_version: u64,
+}
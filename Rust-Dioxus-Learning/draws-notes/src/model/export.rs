@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::{note::Note, notebook::Notebook};
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// a zip of markdown files with yaml front matter, for reading the notes anywhere
+    Markdown,
+    /// everything we store, for moving notebooks between draws-notes instances
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "zip",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<ExportFormat> {
+        let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+        match extension.as_str() {
+            "zip" => Some(ExportFormat::Markdown),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedNote {
+    pub note: Note,
+    pub tags: Vec<String>,
+    /// when the note was created and last saved, for reading only. Notes are stamped like any
+    /// other new note when they're imported, these aren't put back.
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+/// The JSON dump of a notebook, and what both formats are read into on import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotebookExport {
+    pub notebook: Notebook,
+    pub notes: Vec<ExportedNote>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportFile {
+    pub file_name: String,
+    pub bytes: Vec<u8>,
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A change to a note or notebook, pushed to every session that can see the notebook.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct LiveChange {
+    pub table: String,
+    pub action: LiveAction,
+    pub id: String,
+    /// the notebook the note is in, or the notebook itself
+    pub notebook: String,
+}
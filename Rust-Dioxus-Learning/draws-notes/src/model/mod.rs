@@ -1,2 +1,17 @@
+pub mod attachment;
+pub mod backlink;
+pub mod draft;
+pub mod export;
+pub mod live;
 pub mod note;
 pub mod notebook;
+pub mod palette;
+pub mod related;
+pub mod revision;
+pub mod search;
+pub mod share;
+pub mod shortcut;
+pub mod tag;
+pub mod template;
+pub mod trash;
+pub mod user;
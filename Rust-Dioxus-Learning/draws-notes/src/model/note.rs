@@ -16,6 +16,13 @@ pub struct Note {
     pub title: String,
     pub content: String,
     pub notebook: String,
+    /// bumped by one on every save, a save only goes through when it carries the current one
+    pub version: u64,
+    /// set by the server, anything sent with a save is ignored
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
 }
 
 impl Note {
@@ -25,6 +32,55 @@ impl Note {
             title: "New Note".to_string(),
             content: "".to_string(),
             notebook,
+            version: 0,
+            created_at: None,
+            updated_at: None,
         }
     }
 }
+
+/// The orders the notes bar can list a notebook's notes in.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteSort {
+    /// most recently saved first
+    #[default]
+    Updated,
+    /// newest first
+    Created,
+    /// alphabetically, ignoring case
+    Title,
+}
+
+impl NoteSort {
+    pub const ALL: [NoteSort; 3] = [NoteSort::Updated, NoteSort::Created, NoteSort::Title];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NoteSort::Updated => "Last updated",
+            NoteSort::Created => "Date created",
+            NoteSort::Title => "Title",
+        }
+    }
+
+    /// What the sort selector's options carry.
+    pub fn value(&self) -> &'static str {
+        match self {
+            NoteSort::Updated => "updated",
+            NoteSort::Created => "created",
+            NoteSort::Title => "title",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Option<NoteSort> {
+        NoteSort::ALL.into_iter().find(|sort| sort.value() == value)
+    }
+}
+
+/// One page of note summaries. `next_cursor` asks for the page after it and is `None` on the
+/// last one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NotePage {
+    pub notes: Vec<Note>,
+    pub next_cursor: Option<String>,
+}
@@ -1,25 +1,137 @@
-// #[cfg(feature = "ssr")]
-use std::str::FromStr;
+use std::collections::HashSet;
 
 use serde::{Deserialize, Serialize};
-// #[cfg(feature = "ssr")]
-use surrealdb::sql::Thing;
+
+use crate::model::share::Role;
+
+// stands in for the Trash pseudo notebook's id, real ids always start with the table name
+const TRASH_ID: &str = "trash";
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct Notebook {
     pub id: Option<String>,
     pub name: String,
     pub count: Option<u32>,
+    /// the caller's role, `None` for pseudo notebooks like All Notes
+    pub role: Option<Role>,
+    /// the notebook this one is nested in, `None` at the top level
+    pub parent: Option<String>,
+    /// `count` plus the notes in every notebook nested under this one
+    pub total: Option<u32>,
 }
 
 impl Notebook {
+    pub fn can_edit(&self) -> bool {
+        self.role.map(|role| role.can_edit()).unwrap_or(false)
+    }
+
+    pub fn is_owned(&self) -> bool {
+        self.role == Some(Role::Owner)
+    }
+
     pub fn all() -> Notebook {
         Notebook {
             id: None,
             name: "All Notes".to_string(),
             count: None,
+            role: None,
+            parent: None,
+            total: None,
+        }
+    }
+
+    /// Deleted notes and notebooks, until they're restored or purged.
+    pub fn trash() -> Notebook {
+        Notebook {
+            id: Some(TRASH_ID.to_string()),
+            name: "Trash".to_string(),
+            count: None,
+            role: None,
+            parent: None,
+            total: None,
         }
     }
+
+    pub fn is_trash(&self) -> bool {
+        self.id.as_deref() == Some(TRASH_ID)
+    }
+}
+
+/// The ids of the notebooks nested under `id` in `notebooks`, at any depth. Only the notebooks
+/// in the list are followed, so a child the caller can't see hides its own children too.
+pub fn descendant_ids(notebooks: &[Notebook], id: &str) -> Vec<String> {
+    let mut found: Vec<String> = vec![];
+    let mut pending = vec![id.to_string()];
+    while let Some(parent) = pending.pop() {
+        for child in notebooks
+            .iter()
+            .filter(|nb| nb.parent.as_deref() == Some(parent.as_str()))
+        {
+            // the cycle checks keep the stored tree a tree, this only stops a bad one looping
+            let Some(child_id) = child.id.as_ref() else {
+                continue;
+            };
+            if child_id != id && !found.contains(child_id) {
+                found.push(child_id.clone());
+                pending.push(child_id.clone());
+            }
+        }
+    }
+    found
+}
+
+/// A notebook in the notebook bar's tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotebookTreeRow {
+    pub notebook: Notebook,
+    pub depth: usize,
+    pub has_children: bool,
+}
+
+/// Lays `notebooks` out depth first with every notebook under its parent, keeping the order
+/// they came in among siblings. A notebook whose parent isn't in the list is at the top level.
+/// The children of the notebooks in `collapsed` are left out.
+pub fn tree_rows(notebooks: &[Notebook], collapsed: &HashSet<String>) -> Vec<NotebookTreeRow> {
+    fn add_subtree(
+        notebook: &Notebook,
+        depth: usize,
+        notebooks: &[Notebook],
+        collapsed: &HashSet<String>,
+        visited: &mut HashSet<String>,
+        rows: &mut Vec<NotebookTreeRow>,
+    ) {
+        let id = notebook.id.clone().unwrap_or_default();
+        if !visited.insert(id.clone()) {
+            return;
+        }
+        let children: Vec<&Notebook> = notebooks
+            .iter()
+            .filter(|nb| nb.parent.as_deref() == Some(id.as_str()))
+            .collect();
+        rows.push(NotebookTreeRow {
+            notebook: notebook.clone(),
+            depth,
+            has_children: !children.is_empty(),
+        });
+        if collapsed.contains(&id) {
+            return;
+        }
+        for child in children {
+            add_subtree(child, depth + 1, notebooks, collapsed, visited, rows);
+        }
+    }
+
+    let ids: HashSet<&str> = notebooks.iter().filter_map(|nb| nb.id.as_deref()).collect();
+    let mut visited = HashSet::new();
+    let mut rows = vec![];
+    for root in notebooks.iter().filter(|nb| {
+        nb.parent
+            .as_deref()
+            .is_none_or(|parent| !ids.contains(parent))
+    }) {
+        add_subtree(root, 0, notebooks, collapsed, &mut visited, &mut rows);
+    }
+    rows
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
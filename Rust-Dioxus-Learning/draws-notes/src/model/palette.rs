@@ -0,0 +1,133 @@
+use crate::model::{note::Note, notebook::Notebook};
+
+// what a matched character is worth on its own, right after the previous match, and at the
+// start of a word
+const MATCH_SCORE: u32 = 1;
+const CONSECUTIVE_BONUS: u32 = 5;
+const WORD_START_BONUS: u32 = 3;
+
+/// The things the command palette runs on top of jumping to a notebook or note.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PaletteAction {
+    NewNote,
+    NewNotebook,
+    DeleteNote,
+    MoveNote,
+}
+
+impl PaletteAction {
+    pub const ALL: [PaletteAction; 4] = [
+        PaletteAction::NewNote,
+        PaletteAction::NewNotebook,
+        PaletteAction::DeleteNote,
+        PaletteAction::MoveNote,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaletteAction::NewNote => "New note",
+            PaletteAction::NewNotebook => "New notebook",
+            PaletteAction::DeleteNote => "Delete note",
+            PaletteAction::MoveNote => "Move note to notebook",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaletteItem {
+    Action(PaletteAction),
+    Notebook(Notebook),
+    Note(Note),
+}
+
+impl PaletteItem {
+    pub fn label(&self) -> String {
+        match self {
+            PaletteItem::Action(action) => action.label().to_string(),
+            PaletteItem::Notebook(notebook) => notebook.name.clone(),
+            PaletteItem::Note(note) => note.title.clone(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PaletteItem::Action(_) => "Action",
+            PaletteItem::Notebook(_) => "Notebook",
+            PaletteItem::Note(_) => "Note",
+        }
+    }
+}
+
+/// How well `text` matches `query` when the query's characters only have to show up in
+/// order, like `nbk` in "NoteBooK". `None` when they don't, otherwise higher is better: runs
+/// of characters and characters starting a word count for more.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + text[next..].iter().position(|c| *c == wanted)?;
+        score += MATCH_SCORE;
+        if found > 0 && last_match == Some(found - 1) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        last_match = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// The items matching `query`, best first. Ties keep the order they came in.
+pub fn fuzzy_filter(query: &str, items: Vec<PaletteItem>) -> Vec<PaletteItem> {
+    let mut scored: Vec<(u32, PaletteItem)> = items
+        .into_iter()
+        .filter_map(|item| fuzzy_score(query, &item.label()).map(|score| (score, item)))
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_needs_the_characters_in_order() {
+        assert!(fuzzy_score("nbk", "Notebook").is_some());
+        assert_eq!(fuzzy_score("kbn", "Notebook"), None);
+        assert_eq!(fuzzy_score("", "Notebook"), Some(0));
+        // spaces in the query don't have to match anything
+        assert!(fuzzy_score("new note", "Newnote").is_some());
+    }
+
+    #[test]
+    fn score_favours_runs_and_word_starts() {
+        let run = fuzzy_score("note", "Notebook").unwrap();
+        let scattered = fuzzy_score("note", "No time to eat").unwrap();
+        assert!(run > scattered);
+        let word_start = fuzzy_score("m", "Move note").unwrap();
+        let inside = fuzzy_score("m", "Emoji").unwrap();
+        assert!(word_start > inside);
+    }
+
+    #[test]
+    fn filter_keeps_matches_best_first() {
+        let items = vec![
+            PaletteItem::Action(PaletteAction::DeleteNote),
+            PaletteItem::Action(PaletteAction::NewNote),
+            PaletteItem::Action(PaletteAction::NewNotebook),
+        ];
+        assert_eq!(
+            fuzzy_filter("new note", items),
+            vec![
+                PaletteItem::Action(PaletteAction::NewNote),
+                PaletteItem::Action(PaletteAction::NewNotebook),
+            ]
+        );
+    }
+}
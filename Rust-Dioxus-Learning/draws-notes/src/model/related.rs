@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// A note that's about something similar to the one being looked at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelatedNote {
+    pub id: String,
+    pub title: String,
+    pub notebook: String,
+    /// cosine similarity of the two notes' embeddings, 1 is the same meaning
+    pub score: f32,
+}
+
+impl RelatedNote {
+    pub fn similarity(&self) -> String {
+        format!("{:.0}%", self.score.max(0.0) * 100.0)
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// A snapshot of a note taken every time it's saved. Revisions are never updated, restoring one
+/// just saves its title and content as a new revision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteRevision {
+    pub id: String,
+    pub note: String,
+    pub title: String,
+    pub content: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Unchanged(String),
+    Added(String),
+    Removed(String),
+}
+
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| {
+            let line = change.value().trim_end_matches('\n').to_string();
+            match change.tag() {
+                ChangeTag::Equal => DiffLine::Unchanged(line),
+                ChangeTag::Insert => DiffLine::Added(line),
+                ChangeTag::Delete => DiffLine::Removed(line),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_by_line() {
+        assert_eq!(
+            line_diff("one\ntwo\nthree\n", "one\n2\nthree\nfour"),
+            vec![
+                DiffLine::Unchanged("one".to_string()),
+                DiffLine::Removed("two".to_string()),
+                DiffLine::Added("2".to_string()),
+                DiffLine::Unchanged("three".to_string()),
+                DiffLine::Added("four".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_the_same_text() {
+        assert_eq!(
+            line_diff("same\n", "same\n"),
+            vec![DiffLine::Unchanged("same".to_string())]
+        );
+        assert!(line_diff("", "").is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+// surrealdb wraps the matched terms with these, they are control characters so they can't
+// show up in anything a user types and we never have to render the content as html
+pub const HIGHLIGHT_START: char = '\u{2}';
+pub const HIGHLIGHT_END: char = '\u{3}';
+
+// how many characters of context to keep on each side of the first match
+const SNIPPET_RADIUS: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteSearchHit {
+    pub id: String,
+    pub title: String,
+    pub notebook: String,
+    pub snippet: String,
+    pub score: f32,
+}
+
+impl NoteSearchHit {
+    pub fn plain_title(&self) -> String {
+        self.title.replace([HIGHLIGHT_START, HIGHLIGHT_END], "")
+    }
+}
+
+/// Cut a window out of highlighted content centered on the first match, falling back to the
+/// start of the content when nothing in it matched (ie. the hit was on the title).
+pub fn make_snippet(highlighted: &str) -> String {
+    let chars: Vec<char> = highlighted.chars().collect();
+    let first_match = chars
+        .iter()
+        .position(|c| *c == HIGHLIGHT_START)
+        .unwrap_or(0);
+
+    let start = first_match.saturating_sub(SNIPPET_RADIUS);
+    let end = usize::min(chars.len(), first_match + SNIPPET_RADIUS * 2);
+
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Split highlighted text into `(text, is_match)` pieces so the ui can style the matches.
+pub fn highlight_segments(highlighted: &str) -> Vec<(String, bool)> {
+    let mut segments = vec![];
+    let mut current = String::new();
+    let mut in_match = false;
+
+    for c in highlighted.chars() {
+        match c {
+            HIGHLIGHT_START | HIGHLIGHT_END => {
+                if !current.is_empty() {
+                    segments.push((std::mem::take(&mut current), in_match));
+                }
+                in_match = c == HIGHLIGHT_START;
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        segments.push((current, in_match));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(text: &str) -> String {
+        text.replace('<', &HIGHLIGHT_START.to_string())
+            .replace('>', &HIGHLIGHT_END.to_string())
+    }
+
+    #[test]
+    fn snippet_centers_on_the_first_match() {
+        let content = highlighted(&format!("{}<soup>{}", "a".repeat(100), "b".repeat(100)));
+        let snippet = make_snippet(&content);
+        let chars: Vec<char> = snippet.chars().collect();
+        assert_eq!(chars.first(), Some(&'…'));
+        assert_eq!(chars.last(), Some(&'…'));
+        // the radius before the match, twice that from it on, plus the two ellipses
+        assert_eq!(chars.len(), SNIPPET_RADIUS * 3 + 2);
+        assert_eq!(chars[SNIPPET_RADIUS + 1], HIGHLIGHT_START);
+    }
+
+    #[test]
+    fn snippet_without_a_match_starts_at_the_start() {
+        assert_eq!(make_snippet("short note"), "short note");
+        let long = "word ".repeat(40);
+        let snippet = make_snippet(&long);
+        assert!(snippet.starts_with("word"));
+        assert!(snippet.ends_with('…'));
+        assert_eq!(snippet.chars().count(), SNIPPET_RADIUS * 2 + 1);
+    }
+
+    #[test]
+    fn segments_mark_the_matches() {
+        assert_eq!(
+            highlight_segments(&highlighted("hot <soup> and <bread>")),
+            vec![
+                ("hot ".to_string(), false),
+                ("soup".to_string(), true),
+                (" and ".to_string(), false),
+                ("bread".to_string(), true),
+            ]
+        );
+        assert!(highlight_segments("").is_empty());
+    }
+
+    #[test]
+    fn plain_title_drops_the_markers() {
+        let hit = NoteSearchHit {
+            id: "note:1".to_string(),
+            title: highlighted("<Soup> recipes"),
+            notebook: "notebook:1".to_string(),
+            snippet: String::new(),
+            score: 1.0,
+        };
+        assert_eq!(hit.plain_title(), "Soup recipes");
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// What a user may do with a notebook. Ordered so `role >= Role::Editor` reads naturally.
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl Role {
+    pub fn can_edit(&self) -> bool {
+        *self >= Role::Editor
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct NotebookShare {
+    pub notebook: String,
+    pub username: String,
+    pub role: Role,
+}
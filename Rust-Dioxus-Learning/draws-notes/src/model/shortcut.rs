@@ -0,0 +1,244 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// What a keyboard shortcut can do, `app` carries these out.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum ShortcutAction {
+    CommandPalette,
+    Save,
+    NewNote,
+    NextNotebook,
+    PreviousNotebook,
+}
+
+impl ShortcutAction {
+    pub const ALL: [ShortcutAction; 5] = [
+        ShortcutAction::CommandPalette,
+        ShortcutAction::Save,
+        ShortcutAction::NewNote,
+        ShortcutAction::NextNotebook,
+        ShortcutAction::PreviousNotebook,
+    ];
+
+    /// Its key in the `[shortcuts]` table.
+    pub fn config_key(&self) -> &'static str {
+        match self {
+            ShortcutAction::CommandPalette => "command_palette",
+            ShortcutAction::Save => "save",
+            ShortcutAction::NewNote => "new_note",
+            ShortcutAction::NextNotebook => "next_notebook",
+            ShortcutAction::PreviousNotebook => "previous_notebook",
+        }
+    }
+
+    // ctrl+n and ctrl+tab belong to the browser, it never hands them to the page
+    pub fn default_binding(&self) -> &'static str {
+        match self {
+            ShortcutAction::CommandPalette => "ctrl+k",
+            ShortcutAction::Save => "ctrl+s",
+            ShortcutAction::NewNote => "alt+n",
+            ShortcutAction::NextNotebook => "alt+arrowdown",
+            ShortcutAction::PreviousNotebook => "alt+arrowup",
+        }
+    }
+}
+
+/// A key plus the modifiers held with it, written like `ctrl+shift+k`. The key is matched
+/// against both what the key types and where it sits, so `alt+n` still works on layouts
+/// where alt turns n into another character.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    /// lowercase, as in `KeyboardEvent.key`, e.g. `k` or `arrowdown`
+    pub key: String,
+}
+
+/// A key press as the browser's keyboard event describes it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyPress {
+    /// what the key typed, `KeyboardEvent.key`
+    pub key: String,
+    /// which key it was regardless of layout, `KeyboardEvent.code`
+    pub code: String,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+impl KeyBinding {
+    pub fn matches(&self, press: &KeyPress) -> bool {
+        if (self.ctrl, self.alt, self.shift, self.meta)
+            != (press.ctrl, press.alt, press.shift, press.meta)
+        {
+            return false;
+        }
+        let code_key = press
+            .code
+            .strip_prefix("Key")
+            .or_else(|| press.code.strip_prefix("Digit"))
+            .unwrap_or(&press.code);
+        press.key.to_lowercase() == self.key || code_key.to_lowercase() == self.key
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut binding = KeyBinding {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: String::new(),
+        };
+        let parts: Vec<String> = text
+            .split('+')
+            .map(|part| part.trim().to_lowercase())
+            .collect();
+        let Some((key, modifiers)) = parts.split_last() else {
+            return Err(format!("empty shortcut {:?}", text));
+        };
+        for modifier in modifiers {
+            match modifier.as_str() {
+                "ctrl" | "control" => binding.ctrl = true,
+                "alt" | "option" => binding.alt = true,
+                "shift" => binding.shift = true,
+                "meta" | "cmd" | "super" => binding.meta = true,
+                other => return Err(format!("unknown modifier {:?} in {:?}", other, text)),
+            }
+        }
+        if key.is_empty() {
+            return Err(format!("shortcut {:?} has no key", text));
+        }
+        binding.key = key.clone();
+        Ok(binding)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+            (self.meta, "Meta"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        let mut key = self.key.chars();
+        match key.next() {
+            Some(first) => write!(f, "{}{}", first.to_uppercase(), key.as_str()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The key bindings in effect, from the `[shortcuts]` table of Dioxus.toml.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shortcuts {
+    pub bindings: Vec<(ShortcutAction, KeyBinding)>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Shortcuts {
+            bindings: ShortcutAction::ALL
+                .into_iter()
+                .map(|action| {
+                    let binding = action
+                        .default_binding()
+                        .parse()
+                        .expect("invalid default shortcut");
+                    (action, binding)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Shortcuts {
+    pub fn binding(&self, action: ShortcutAction) -> Option<&KeyBinding> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, binding)| binding)
+    }
+
+    pub fn action_for(&self, press: &KeyPress) -> Option<ShortcutAction> {
+        self.bindings
+            .iter()
+            .find(|(_, binding)| binding.matches(press))
+            .map(|(action, _)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: &str, code: &str, ctrl: bool, alt: bool) -> KeyPress {
+        KeyPress {
+            key: key.to_string(),
+            code: code.to_string(),
+            ctrl,
+            alt,
+            shift: false,
+            meta: false,
+        }
+    }
+
+    #[test]
+    fn parse_and_display_bindings() {
+        let binding: KeyBinding = " Control + shift+P".parse().unwrap();
+        assert!(binding.ctrl && binding.shift && !binding.alt && !binding.meta);
+        assert_eq!(binding.key, "p");
+        assert_eq!(binding.to_string(), "Ctrl+Shift+P");
+        assert_eq!(
+            "cmd+arrowdown".parse::<KeyBinding>().unwrap().to_string(),
+            "Meta+Arrowdown"
+        );
+        assert!("hyper+k".parse::<KeyBinding>().is_err());
+        assert!("ctrl+".parse::<KeyBinding>().is_err());
+        assert!("".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn default_bindings_parse() {
+        let shortcuts = Shortcuts::default();
+        for action in ShortcutAction::ALL {
+            assert!(shortcuts.binding(action).is_some(), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn match_presses_to_actions() {
+        let shortcuts = Shortcuts::default();
+        assert_eq!(
+            shortcuts.action_for(&press("K", "KeyK", true, false)),
+            Some(ShortcutAction::CommandPalette)
+        );
+        // alt turns n into a dead key on a mac, the key's position still gives it away
+        assert_eq!(
+            shortcuts.action_for(&press("Dead", "KeyN", false, true)),
+            Some(ShortcutAction::NewNote)
+        );
+        assert_eq!(
+            shortcuts.action_for(&press("ArrowDown", "ArrowDown", false, true)),
+            Some(ShortcutAction::NextNotebook)
+        );
+        // the modifiers have to match exactly
+        assert_eq!(
+            shortcuts.action_for(&press("k", "KeyK", false, false)),
+            None
+        );
+        assert_eq!(shortcuts.action_for(&press("k", "KeyK", true, true)), None);
+    }
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: Option<String>,
+    pub name: String,
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct TagNoteCount {
+    pub id: String,
+    pub count: u32,
+}
+
+/// Tags are keyed by their normalized name so tagging with an existing name reuses the tag.
+pub fn normalize_tag_name(name: &str) -> String {
+    name.trim()
+        .trim_start_matches('#')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
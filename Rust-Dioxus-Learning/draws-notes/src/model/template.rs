@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/// A note to start from. `title` and `content` may use `{{date}}`, `{{time}}` and
+/// `{{notebook}}`, which are filled in when a note is made from it.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    pub id: Option<String>,
+    pub name: String,
+    pub title: String,
+    pub content: String,
+}
+
+impl Template {
+    pub fn new() -> Template {
+        Template {
+            id: None,
+            name: "New Template".to_string(),
+            title: "{{date}}".to_string(),
+            content: "".to_string(),
+        }
+    }
+
+    /// The title and content of a note made from this template.
+    pub fn instantiate(&self, vars: &TemplateVars) -> (String, String) {
+        (
+            fill_template(&self.title, vars),
+            fill_template(&self.content, vars),
+        )
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::new()
+    }
+}
+
+/// What the template variables stand for. The date and time are the user's local ones, so
+/// they're worked out in the browser rather than on the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateVars {
+    pub date: String,
+    pub time: String,
+    pub notebook: String,
+}
+
+impl TemplateVars {
+    fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "date" => Some(&self.date),
+            "time" => Some(&self.time),
+            "notebook" => Some(&self.notebook),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces every `{{name}}` in `text`, spaces inside the braces allowed. Unknown names are
+/// left as written, so a typo shows up in the note instead of quietly disappearing.
+pub fn fill_template(text: &str, vars: &TemplateVars) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            filled.push_str(&rest[start..]);
+            return filled;
+        };
+        match vars.get(after[..end].trim()) {
+            Some(value) => filled.push_str(value),
+            None => filled.push_str(&rest[start..start + end + 4]),
+        }
+        rest = &after[end + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashKind {
+    Note,
+    Notebook,
+}
+
+/// A deleted note or notebook. Notes in a deleted notebook aren't listed on their own, they
+/// come back with the notebook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub id: String,
+    pub kind: TrashKind,
+    pub name: String,
+    /// the name of the notebook a note is in, `None` for notebooks
+    #[serde(default)]
+    pub notebook: Option<String>,
+    pub deleted_at: String,
+    /// when the purge deletes it for good
+    pub purge_at: String,
+}
+
+impl TrashItem {
+    /// What it is and where it was, for listing it.
+    pub fn description(&self) -> String {
+        match (self.kind, self.notebook.as_ref()) {
+            (TrashKind::Notebook, _) => "Notebook".to_string(),
+            (TrashKind::Note, Some(notebook)) => format!("Note in {}", notebook),
+            (TrashKind::Note, None) => "Note".to_string(),
+        }
+    }
+
+    /// The day it was deleted, `deleted_at` without the time.
+    pub fn deleted_on(&self) -> &str {
+        self.deleted_at.get(..10).unwrap_or(&self.deleted_at)
+    }
+
+    pub fn purged_on(&self) -> &str {
+        self.purge_at.get(..10).unwrap_or(&self.purge_at)
+    }
+}
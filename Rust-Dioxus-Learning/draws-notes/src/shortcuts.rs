@@ -0,0 +1,60 @@
+use futures::{channel::mpsc, StreamExt};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{KeyboardEvent, Window};
+
+use crate::model::shortcut::{KeyPress, ShortcutAction, Shortcuts};
+
+/// The app's shortcuts, caught on the whole window so they work wherever the focus is. The
+/// listener is removed when this is dropped.
+pub struct ShortcutKeys {
+    window: Window,
+    actions: mpsc::UnboundedReceiver<ShortcutAction>,
+    // the browser calls into this, so it has to live as long as the listener
+    on_keydown: Closure<dyn FnMut(KeyboardEvent)>,
+}
+
+impl ShortcutKeys {
+    pub fn listen(shortcuts: Shortcuts) -> Option<Self> {
+        let window = web_sys::window()?;
+
+        let (sender, actions) = mpsc::unbounded();
+        let on_keydown = Closure::<dyn FnMut(KeyboardEvent)>::new(move |event: KeyboardEvent| {
+            let press = KeyPress {
+                key: event.key(),
+                code: event.code(),
+                ctrl: event.ctrl_key(),
+                alt: event.alt_key(),
+                shift: event.shift_key(),
+                meta: event.meta_key(),
+            };
+            if let Some(action) = shortcuts.action_for(&press) {
+                // otherwise ctrl+s saves the page and ctrl+k jumps to the browser's search bar
+                event.prevent_default();
+                let _ = sender.unbounded_send(action);
+            }
+        });
+        window
+            .add_event_listener_with_callback("keydown", on_keydown.as_ref().unchecked_ref())
+            .map_err(|e| log::error!("error listening for shortcuts {:?}", e))
+            .ok()?;
+
+        Some(Self {
+            window,
+            actions,
+            on_keydown,
+        })
+    }
+
+    pub async fn next(&mut self) -> Option<ShortcutAction> {
+        self.actions.next().await
+    }
+}
+
+impl Drop for ShortcutKeys {
+    fn drop(&mut self) {
+        let _ = self.window.remove_event_listener_with_callback(
+            "keydown",
+            self.on_keydown.as_ref().unchecked_ref(),
+        );
+    }
+}
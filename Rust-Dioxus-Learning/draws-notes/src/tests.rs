@@ -0,0 +1,1074 @@
+//! Runs the server functions against the in-memory engine. Each test signs up its own users,
+//! so they can share the one database.
+
+use std::{
+    collections::HashSet,
+    future::Future,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use dioxus_fullstack::prelude::*;
+use lazy_static::lazy_static;
+use surrealdb::sql::Thing;
+use tokio::runtime::Runtime;
+
+use crate::{
+    api, attachments, copy_notes, delete_note, delete_notebook, delete_permanently,
+    delete_template, embeddings,
+    error::NotesError,
+    export_notebook, get_backlinks, get_current_user, get_note, get_note_summaries, get_note_tags,
+    get_notebook_shares, get_notebooks, get_shortcuts, get_tags, get_templates, get_trash,
+    import_notebook, list_note_revisions, log_in, log_out, migrations,
+    model::{
+        export::ExportFormat,
+        note::{Note, NoteSort},
+        notebook::{tree_rows, Notebook},
+        share::Role,
+        shortcut::{KeyPress, ShortcutAction, Shortcuts},
+        template::{fill_template, Template, TemplateVars},
+        trash::TrashKind,
+        user::User,
+    },
+    move_notes, related_notes, restore_from_trash, restore_note_revision, rewrite_backlinks,
+    search_notes, set_notebook_parent, share_notebook, sign_up, tag_note, unshare_notebook,
+    untag_note, upload_attachment, upsert_note, upsert_notebook, upsert_template, DB,
+    NOTE_PAGE_SIZE, RUNTIME_STACK_SIZE,
+};
+
+const PASSWORD: &str = "correct horse battery";
+
+lazy_static! {
+    // the in-memory engine runs on tasks of whichever runtime first opens it, so the tests
+    // share one runtime instead of each getting a throwaway one from #[tokio::test]
+    static ref RUNTIME: Runtime = {
+        std::env::set_var("DRAWS_DB_ENGINE", "memory");
+        std::env::set_var(
+            "DRAWS_ATTACHMENTS_PATH",
+            std::env::temp_dir().join(format!("draws-notes-test-{}", std::process::id())),
+        );
+        // the related notes test stores its own vectors instead of downloading a model
+        std::env::set_var("DRAWS_EMBEDDINGS_MODEL", "none");
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_stack_size(RUNTIME_STACK_SIZE)
+            .build()
+            .expect("failed to start the test runtime")
+    };
+}
+
+static NEXT_USER: AtomicUsize = AtomicUsize::new(0);
+
+/// `block_on` polls on the calling thread, which is where the server context lives.
+fn run<F: Future>(test: F) -> F::Output {
+    RUNTIME.block_on(test)
+}
+
+struct Session {
+    user: User,
+    cookie: Option<String>,
+}
+
+impl Session {
+    /// Makes the following server function calls on this thread run as this session.
+    fn enter(&self) {
+        let cx = server_context();
+        let mut request = cx.request_parts_mut().expect("request parts poisoned");
+        request.headers.remove(http::header::COOKIE);
+        if let Some(cookie) = self.cookie.as_ref() {
+            request.headers.insert(
+                http::header::COOKIE,
+                cookie.parse().expect("invalid cookie"),
+            );
+        }
+    }
+}
+
+/// The cookie the last server function call set, cleared so the next call starts fresh.
+fn take_cookie() -> Option<String> {
+    let cx = server_context();
+    let mut response = cx.response_parts_mut().expect("response parts poisoned");
+    let cookie = response
+        .headers
+        .get_all(http::header::SET_COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .filter_map(|header| header.split(';').next())
+        .next_back()
+        .map(|cookie| cookie.to_string());
+    response.headers.remove(http::header::SET_COOKIE);
+    cookie
+}
+
+async fn new_session(name: &str) -> Session {
+    let username = format!("{}-{}", name, NEXT_USER.fetch_add(1, Ordering::SeqCst));
+    let user = sign_up(username, PASSWORD.to_string())
+        .await
+        .expect("couldn't sign up");
+    let session = Session {
+        user,
+        cookie: take_cookie(),
+    };
+    session.enter();
+    session
+}
+
+async fn new_notebook(name: &str) -> String {
+    upsert_notebook(Notebook {
+        id: None,
+        name: name.to_string(),
+        count: None,
+        role: None,
+        parent: None,
+        total: None,
+    })
+    .await
+    .expect("couldn't create notebook")
+}
+
+async fn new_note(notebook: &str, title: &str, content: &str) -> String {
+    upsert_note(Note {
+        title: title.to_string(),
+        content: content.to_string(),
+        ..Note::new(notebook.to_string())
+    })
+    .await
+    .expect("couldn't create note")
+}
+
+// the first page in the default order, which is all any test but the paging one needs
+async fn list_notes(notebook: &str, tag: Option<String>) -> Vec<Note> {
+    get_note_summaries(
+        Some(notebook.to_string()),
+        false,
+        tag,
+        NoteSort::default(),
+        None,
+        NOTE_PAGE_SIZE,
+    )
+    .await
+    .expect("couldn't list notes")
+    .notes
+}
+
+fn notes_error<T: std::fmt::Debug>(res: Result<T, ServerFnError>) -> NotesError {
+    NotesError::from_server_fn_error(&res.expect_err("expected an error"))
+}
+
+#[test]
+fn migrations_apply_once() {
+    run(async {
+        let db = DB.get().await;
+        let applied = || async {
+            let versions: Vec<u32> = db
+                .query("SELECT VALUE version FROM _migrations ORDER BY version;")
+                .await
+                .unwrap()
+                .take(0)
+                .unwrap();
+            versions
+        };
+
+        let before = applied().await;
+        assert!(!before.is_empty());
+        assert!(before.windows(2).all(|pair| pair[0] < pair[1]));
+
+        migrations::run(db).await.unwrap();
+        assert_eq!(applied().await, before);
+    });
+}
+
+#[test]
+fn sign_up_log_in_and_out() {
+    run(async {
+        let session = new_session("alice").await;
+        let current = get_current_user().await.unwrap();
+        assert_eq!(current.as_ref(), Some(&session.user));
+
+        let res = sign_up(session.user.username.clone(), PASSWORD.to_string()).await;
+        assert!(matches!(notes_error(res), NotesError::Validation(_)));
+        let res = sign_up("short-password".to_string(), "short".to_string()).await;
+        assert!(matches!(notes_error(res), NotesError::Validation(_)));
+
+        log_out().await.unwrap();
+        assert_eq!(get_current_user().await.unwrap(), None);
+
+        let res = log_in(session.user.username.clone(), "wrong password".to_string()).await;
+        assert!(matches!(notes_error(res), NotesError::Unauthorized(_)));
+        let user = log_in(session.user.username.clone(), PASSWORD.to_string())
+            .await
+            .unwrap();
+        Session {
+            user,
+            cookie: take_cookie(),
+        }
+        .enter();
+        assert_eq!(get_current_user().await.unwrap(), Some(session.user));
+    });
+}
+
+#[test]
+fn requires_a_session() {
+    run(async {
+        Session {
+            user: User {
+                id: "".to_string(),
+                username: "".to_string(),
+            },
+            cookie: None,
+        }
+        .enter();
+
+        assert!(matches!(
+            notes_error(get_notebooks().await),
+            NotesError::Unauthorized(_)
+        ));
+    });
+}
+
+#[test]
+fn create_and_rename_notebooks() {
+    run(async {
+        new_session("bob").await;
+        let id = new_notebook("Recipes").await;
+
+        let notebooks = get_notebooks().await.unwrap();
+        let notebook = notebooks
+            .iter()
+            .find(|nb| nb.id.as_ref() == Some(&id))
+            .expect("missing notebook");
+        assert_eq!(notebook.name, "Recipes");
+        assert_eq!(notebook.role, Some(Role::Owner));
+        assert_eq!(notebook.count, Some(0));
+
+        upsert_notebook(Notebook {
+            name: "Cooking".to_string(),
+            ..notebook.clone()
+        })
+        .await
+        .unwrap();
+        let notebooks = get_notebooks().await.unwrap();
+        assert_eq!(notebooks.len(), 1);
+        assert_eq!(notebooks[0].name, "Cooking");
+
+        let res = upsert_notebook(Notebook {
+            id: None,
+            name: " ".to_string(),
+            count: None,
+            role: None,
+            parent: None,
+            total: None,
+        })
+        .await;
+        assert!(matches!(notes_error(res), NotesError::Validation(_)));
+    });
+}
+
+#[test]
+fn save_notes_with_versions() {
+    run(async {
+        new_session("carol").await;
+        let notebook = new_notebook("Work").await;
+        let id = new_note(&notebook, "Standup", "nothing yet").await;
+
+        let note = get_note(id.clone()).await.unwrap();
+        assert_eq!(note.title, "Standup");
+        assert_eq!(note.version, 1);
+
+        upsert_note(Note {
+            content: "shipped the thing".to_string(),
+            ..note.clone()
+        })
+        .await
+        .unwrap();
+        assert_eq!(get_note(id.clone()).await.unwrap().version, 2);
+
+        // still based on version 1
+        let res = upsert_note(Note {
+            content: "something else".to_string(),
+            ..note
+        })
+        .await;
+        match notes_error(res) {
+            NotesError::Conflict(current) => assert_eq!(current.content, "shipped the thing"),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+
+        let summaries = list_notes(&notebook, None).await;
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].content, "shipped the thing");
+        assert_eq!(get_notebooks().await.unwrap()[0].count, Some(1));
+
+        delete_note(id).await.unwrap();
+        assert!(list_notes(&notebook, None).await.is_empty());
+        assert_eq!(get_notebooks().await.unwrap()[0].count, Some(0));
+    });
+}
+
+#[test]
+fn delete_notebooks_moving_or_dropping_notes() {
+    run(async {
+        new_session("dave").await;
+        let from = new_notebook("Old").await;
+        let to = new_notebook("New").await;
+        let note = new_note(&from, "Keep me", "").await;
+
+        let notebooks = get_notebooks().await.unwrap();
+        let old = notebooks
+            .iter()
+            .find(|nb| nb.id.as_ref() == Some(&from))
+            .unwrap()
+            .clone();
+        delete_notebook(old, Some(to.clone())).await.unwrap();
+        assert_eq!(get_note(note.clone()).await.unwrap().notebook, to);
+
+        let notebooks = get_notebooks().await.unwrap();
+        assert_eq!(notebooks.len(), 1);
+        delete_notebook(notebooks[0].clone(), None).await.unwrap();
+        assert!(get_notebooks().await.unwrap().is_empty());
+        let trash = get_trash().await.unwrap();
+        assert_eq!(trash.len(), 2);
+        assert!(trash.iter().any(|item| item.id == to));
+    });
+}
+
+#[test]
+fn share_notebooks_by_role() {
+    run(async {
+        let viewer = new_session("erin").await;
+        let owner = new_session("frank").await;
+        let notebook = new_notebook("Shared").await;
+        let note = new_note(&notebook, "Plans", "").await;
+
+        assert!(share_notebook(
+            notebook.clone(),
+            viewer.user.username.clone(),
+            Role::Owner
+        )
+        .await
+        .is_err());
+        share_notebook(notebook.clone(), viewer.user.username.clone(), Role::Viewer)
+            .await
+            .unwrap();
+        let shares = get_notebook_shares(notebook.clone()).await.unwrap();
+        assert_eq!(shares.len(), 1);
+        assert_eq!(shares[0].role, Role::Viewer);
+
+        viewer.enter();
+        let notebooks = get_notebooks().await.unwrap();
+        assert_eq!(notebooks.len(), 1);
+        assert_eq!(notebooks[0].role, Some(Role::Viewer));
+        let read = get_note(note.clone()).await.unwrap();
+        let res = upsert_note(Note {
+            title: "Mine now".to_string(),
+            ..read
+        })
+        .await;
+        assert!(matches!(notes_error(res), NotesError::Unauthorized(_)));
+        assert!(get_notebook_shares(notebook.clone()).await.is_err());
+
+        owner.enter();
+        unshare_notebook(notebook, viewer.user.username.clone())
+            .await
+            .unwrap();
+
+        viewer.enter();
+        assert!(matches!(
+            notes_error(get_note(note).await),
+            NotesError::NotFound(_)
+        ));
+        assert!(get_notebooks().await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn tag_and_filter_notes() {
+    run(async {
+        new_session("grace").await;
+        let notebook = new_notebook("Garden").await;
+        let tagged = new_note(&notebook, "Tomatoes", "").await;
+        new_note(&notebook, "Roses", "").await;
+
+        let tag = tag_note(tagged.clone(), " Vegetables ".to_string())
+            .await
+            .unwrap();
+        let note_tags = get_note_tags(tagged.clone()).await.unwrap();
+        assert_eq!(note_tags.len(), 1);
+        assert_eq!(note_tags[0].name, "vegetables");
+
+        let tags = get_tags().await.unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].count, Some(1));
+
+        let filtered = list_notes(&notebook, Some(tag.clone())).await;
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id.as_ref(), Some(&tagged));
+
+        untag_note(tagged.clone(), tag).await.unwrap();
+        assert!(get_note_tags(tagged).await.unwrap().is_empty());
+        assert!(get_tags().await.unwrap().is_empty());
+    });
+}
+
+#[test]
+fn restore_revisions() {
+    run(async {
+        new_session("heidi").await;
+        let notebook = new_notebook("Drafts").await;
+        let id = new_note(&notebook, "Essay", "first draft").await;
+        let note = get_note(id.clone()).await.unwrap();
+        upsert_note(Note {
+            content: "second draft".to_string(),
+            ..note
+        })
+        .await
+        .unwrap();
+
+        let revisions = list_note_revisions(id.clone()).await.unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].content, "second draft");
+
+        let restored = restore_note_revision(revisions[1].id.clone())
+            .await
+            .unwrap();
+        assert_eq!(restored.content, "first draft");
+        assert_eq!(restored.version, 3);
+        assert_eq!(list_note_revisions(id.clone()).await.unwrap().len(), 3);
+
+        // only revisions can be restored, and not onto a note in the trash
+        assert!(matches!(
+            notes_error(restore_note_revision(id.clone()).await),
+            NotesError::NotFound(_)
+        ));
+        delete_note(id.clone()).await.unwrap();
+        assert!(matches!(
+            notes_error(restore_note_revision(revisions[0].id.clone()).await),
+            NotesError::NotFound(_)
+        ));
+    });
+}
+
+#[test]
+fn search_only_visible_notes() {
+    run(async {
+        let outsider = new_session("ivan").await;
+        new_session("judy").await;
+        let notebook = new_notebook("Travel").await;
+        let id = new_note(&notebook, "Zeppelin tour", "airships over the lake").await;
+
+        let hits = search_notes("zeppelin".to_string(), Some(notebook.clone()))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, id);
+
+        outsider.enter();
+        assert!(search_notes("zeppelin".to_string(), None)
+            .await
+            .unwrap()
+            .is_empty());
+    });
+}
+
+#[test]
+fn export_and_import_notebooks() {
+    run(async {
+        new_session("mallory").await;
+        let notebook = new_notebook("Recipes").await;
+        let soup = new_note(&notebook, "Soup", "---\nboil water\n- [ ] salt").await;
+        new_note(&notebook, "Soup", "a second soup").await;
+        tag_note(soup, "dinner".to_string()).await.unwrap();
+
+        for format in [ExportFormat::Markdown, ExportFormat::Json] {
+            let file = export_notebook(notebook.clone(), format).await.unwrap();
+            assert_eq!(ExportFormat::from_file_name(&file.file_name), Some(format));
+
+            let imported = import_notebook(file.file_name, file.bytes).await.unwrap();
+            assert_ne!(imported, notebook);
+
+            // summaries only carry a plain text preview, the full notes have the markdown
+            let mut notes = vec![];
+            for summary in list_notes(&imported, None).await {
+                notes.push(get_note(summary.id.unwrap()).await.unwrap());
+            }
+            notes.sort_by(|a, b| a.content.cmp(&b.content));
+            assert_eq!(notes.len(), 2);
+            assert!(notes.iter().all(|note| note.title == "Soup"));
+            assert_eq!(notes[0].content, "---\nboil water\n- [ ] salt");
+            assert_eq!(notes[1].content, "a second soup");
+
+            let tags = get_note_tags(notes[0].id.clone().unwrap()).await.unwrap();
+            assert_eq!(tags.len(), 1);
+            assert_eq!(tags[0].name, "dinner");
+        }
+
+        assert!(import_notebook("notes.txt".to_string(), vec![])
+            .await
+            .is_err());
+    });
+}
+
+#[test]
+fn remove_attachments_with_their_last_note() {
+    run(async {
+        new_session("niaj").await;
+        let notebook = new_notebook("Photos").await;
+        let first = new_note(&notebook, "Beach", "").await;
+        let second = new_note(&notebook, "Also the beach", "").await;
+        let bytes = b"not really a png".to_vec();
+
+        let attachment = upload_attachment(first.clone(), "beach.png".to_string(), bytes.clone())
+            .await
+            .unwrap();
+        assert_eq!(attachment.mime, "image/png");
+        assert_eq!(
+            attachment.markdown(),
+            format!("![beach.png](/attachments/{})", attachment.hash)
+        );
+        let copy = upload_attachment(second.clone(), "copy.png".to_string(), bytes)
+            .await
+            .unwrap();
+        assert_eq!(copy.hash, attachment.hash);
+
+        let path = attachments::path_for(&attachment.hash);
+        assert!(path.exists());
+        for note in [first, second] {
+            delete_note(note.clone()).await.unwrap();
+            // still restorable from the trash
+            assert!(path.exists());
+            delete_permanently(note).await.unwrap();
+        }
+        assert!(!path.exists());
+    });
+}
+
+#[test]
+fn trash_and_restore() {
+    run(async {
+        new_session("olivia").await;
+        let notebook = new_notebook("Scratch").await;
+        let note = new_note(&notebook, "Draft", "").await;
+
+        delete_note(note.clone()).await.unwrap();
+        assert!(list_notes(&notebook, None).await.is_empty());
+        let trash = get_trash().await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].kind, TrashKind::Note);
+        assert_eq!(trash[0].notebook.as_deref(), Some("Scratch"));
+        let res = upsert_note(Note {
+            id: Some(note.clone()),
+            version: 1,
+            ..Note::new(notebook.clone())
+        })
+        .await;
+        assert!(matches!(notes_error(res), NotesError::NotFound(_)));
+        let res = copy_notes(vec![note.clone()], notebook.clone()).await;
+        assert!(matches!(notes_error(res), NotesError::NotFound(_)));
+        let res = upload_attachment(note.clone(), "draft.txt".to_string(), b"text".to_vec()).await;
+        assert!(matches!(notes_error(res), NotesError::NotFound(_)));
+
+        restore_from_trash(note.clone()).await.unwrap();
+        assert_eq!(list_notes(&notebook, None).await.len(), 1);
+        assert!(get_trash().await.unwrap().is_empty());
+
+        // a note in a trashed notebook only comes back with the notebook
+        delete_note(note.clone()).await.unwrap();
+        let notebooks = get_notebooks().await.unwrap();
+        delete_notebook(notebooks[0].clone(), None).await.unwrap();
+        let trash = get_trash().await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].kind, TrashKind::Notebook);
+        assert!(matches!(
+            notes_error(restore_from_trash(note.clone()).await),
+            NotesError::Validation(_)
+        ));
+        restore_from_trash(notebook.clone()).await.unwrap();
+        restore_from_trash(note.clone()).await.unwrap();
+
+        let other = new_note(&notebook, "Not deleted", "").await;
+        assert!(matches!(
+            notes_error(delete_permanently(other).await),
+            NotesError::Validation(_)
+        ));
+        delete_note(note.clone()).await.unwrap();
+        delete_permanently(note.clone()).await.unwrap();
+        assert!(matches!(
+            notes_error(get_note(note).await),
+            NotesError::NotFound(_)
+        ));
+
+        // notes aren't moved into a notebook in the trash
+        let old = new_notebook("Old").await;
+        let notebooks = get_notebooks().await.unwrap();
+        let find = |id: &str| {
+            notebooks
+                .iter()
+                .find(|nb| nb.id.as_deref() == Some(id))
+                .cloned()
+        };
+        delete_notebook(find(&old).unwrap(), None).await.unwrap();
+        let res = delete_notebook(find(&notebook).unwrap(), Some(old)).await;
+        assert!(matches!(notes_error(res), NotesError::NotFound(_)));
+        assert_eq!(get_notebooks().await.unwrap().len(), 1);
+    });
+}
+
+#[test]
+fn sort_and_page_summaries() {
+    run(async {
+        new_session("priya").await;
+        let notebook = new_notebook("Recipes").await;
+        let mut ids = vec![];
+        for title in ["banana bread", "Apple pie", "carrot cake"] {
+            ids.push(new_note(&notebook, title, "").await);
+        }
+        let first = get_note(ids[0].clone()).await.unwrap();
+        assert!(first.created_at.is_some());
+        upsert_note(Note {
+            content: "with walnuts".to_string(),
+            ..first
+        })
+        .await
+        .unwrap();
+
+        let page =
+            |sort, cursor| get_note_summaries(Some(notebook.clone()), false, None, sort, cursor, 2);
+        let titles = |notes: &[Note]| {
+            notes
+                .iter()
+                .map(|note| note.title.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let by_title = page(NoteSort::Title, None).await.unwrap();
+        assert_eq!(titles(&by_title.notes), ["Apple pie", "banana bread"]);
+        let rest = page(NoteSort::Title, by_title.next_cursor).await.unwrap();
+        assert_eq!(titles(&rest.notes), ["carrot cake"]);
+        assert_eq!(rest.next_cursor, None);
+
+        let by_update = page(NoteSort::Updated, None).await.unwrap();
+        assert_eq!(by_update.notes[0].title, "banana bread");
+        let rest = page(NoteSort::Updated, by_update.next_cursor)
+            .await
+            .unwrap();
+        assert_eq!(rest.notes.len(), 1);
+
+        let by_creation = page(NoteSort::Created, None).await.unwrap();
+        assert_eq!(titles(&by_creation.notes), ["carrot cake", "Apple pie"]);
+        let rest = page(NoteSort::Created, by_creation.next_cursor)
+            .await
+            .unwrap();
+        assert_eq!(titles(&rest.notes), ["banana bread"]);
+
+        assert!(matches!(
+            notes_error(page(NoteSort::Title, Some("not a cursor".to_string())).await),
+            NotesError::Validation(_)
+        ));
+    });
+}
+
+#[test]
+fn backlinks_follow_renames() {
+    run(async {
+        new_session("quentin").await;
+        let notebook = new_notebook("Wiki").await;
+        let roadmap = new_note(&notebook, "Roadmap", "").await;
+        let standup = new_note(&notebook, "Standup", "see [[roadmap]] and [[Glossary]]").await;
+        new_note(&notebook, "Syntax", "links look like `[[Roadmap]]`").await;
+
+        let backlinks = get_backlinks(roadmap.clone()).await.unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].note, standup);
+        assert!(!backlinks[0].is_stale("Roadmap"));
+
+        // linked before the note existed
+        let glossary = new_note(&notebook, "Glossary", "").await;
+        assert_eq!(get_backlinks(glossary).await.unwrap().len(), 1);
+
+        let note = get_note(roadmap.clone()).await.unwrap();
+        upsert_note(Note {
+            title: "Plans".to_string(),
+            ..note
+        })
+        .await
+        .unwrap();
+        let backlinks = get_backlinks(roadmap.clone()).await.unwrap();
+        assert!(backlinks[0].is_stale("Plans"));
+
+        assert_eq!(rewrite_backlinks(roadmap.clone()).await.unwrap(), 1);
+        assert_eq!(
+            get_note(standup).await.unwrap().content,
+            "see [[Plans]] and [[Glossary]]"
+        );
+        let backlinks = get_backlinks(roadmap).await.unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert!(!backlinks[0].is_stale("Plans"));
+    });
+}
+
+#[test]
+fn related_notes_by_embedding() {
+    run(async {
+        new_session("rosa").await;
+        let notebook = new_notebook("Garden").await;
+        let tomatoes = new_note(&notebook, "Tomatoes", "").await;
+        let peppers = new_note(&notebook, "Peppers", "").await;
+        let taxes = new_note(&notebook, "Taxes", "").await;
+        assert!(related_notes(tomatoes.clone(), 5).await.unwrap().is_empty());
+
+        for (note, vector) in [
+            (&tomatoes, vec![1.0, 0.0, 0.0]),
+            (&peppers, vec![0.8, 0.6, 0.0]),
+            (&taxes, vec![0.0, 0.0, 1.0]),
+        ] {
+            embeddings::store(&Thing::from_str(note).unwrap(), vector, "test")
+                .await
+                .unwrap();
+        }
+
+        let related = related_notes(tomatoes.clone(), 5).await.unwrap();
+        assert_eq!(related.len(), 2);
+        assert_eq!(related[0].id, peppers);
+        assert!((related[0].score - 0.8).abs() < 1e-4);
+        assert_eq!(related[1].id, taxes);
+        assert_eq!(related_notes(tomatoes.clone(), 1).await.unwrap().len(), 1);
+
+        delete_note(peppers).await.unwrap();
+        let related = related_notes(tomatoes, 5).await.unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].id, taxes);
+    });
+}
+
+#[test]
+fn rest_api_with_tokens() {
+    run(async {
+        let session = new_session("sam").await;
+        let notebook = new_notebook("Scripts").await;
+        let log_in = |password: &str| api::LogIn {
+            username: session.user.username.clone(),
+            password: password.to_string(),
+        };
+
+        let res = api::create_token(Json(log_in("not the password"))).await;
+        assert_eq!(
+            res.unwrap_err().into_response().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        let (status, Json(api::Token { token })) =
+            api::create_token(Json(log_in(PASSWORD))).await.unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+
+        let Json(notebooks) = api::list_notebooks(headers.clone()).await.unwrap();
+        assert_eq!(notebooks.len(), 1);
+
+        let (_, Json(note)) = api::create_note(
+            headers.clone(),
+            Json(api::NewNote {
+                notebook: notebook.clone(),
+                title: "From a script".to_string(),
+                content: "hello".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+        let id = note.id.clone().unwrap();
+        // written through the api, read back through the server functions
+        assert_eq!(get_note(id.clone()).await.unwrap().content, "hello");
+
+        let update = |content: &str| api::NoteUpdate {
+            notebook: None,
+            title: note.title.clone(),
+            content: content.to_string(),
+            version: note.version,
+        };
+        let bare_id = id.split_once(':').unwrap().1.to_string();
+        let Json(saved) = api::update_note(
+            Path(bare_id.clone()),
+            headers.clone(),
+            Json(update("again")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(saved.version, 2);
+        assert_eq!(saved.notebook, notebook);
+        let res = api::update_note(Path(bare_id), headers.clone(), Json(update("stale"))).await;
+        assert_eq!(
+            res.unwrap_err().into_response().status(),
+            StatusCode::CONFLICT
+        );
+
+        let query = api::NotesQuery {
+            notebook: Some(notebook.clone()),
+            ..Default::default()
+        };
+        let Json(page) = api::list_notes(Query(query), headers.clone())
+            .await
+            .unwrap();
+        assert_eq!(page.notes.len(), 1);
+        assert_eq!(page.notes[0].content, "again");
+
+        let res = api::read_note(Path(notebook), headers.clone()).await;
+        assert_eq!(
+            res.unwrap_err().into_response().status(),
+            StatusCode::NOT_FOUND
+        );
+
+        assert_eq!(
+            api::revoke_token(headers.clone()).await.unwrap(),
+            StatusCode::NO_CONTENT
+        );
+        let res = api::list_notebooks(headers).await;
+        assert_eq!(
+            res.unwrap_err().into_response().status(),
+            StatusCode::UNAUTHORIZED
+        );
+    });
+}
+
+#[test]
+fn get_shortcuts_from_config() {
+    run(async {
+        let shortcuts = get_shortcuts().await.unwrap();
+        assert_eq!(shortcuts, Shortcuts::default());
+        assert_eq!(
+            shortcuts.action_for(&KeyPress {
+                key: "k".to_string(),
+                code: "KeyK".to_string(),
+                ctrl: true,
+                alt: false,
+                shift: false,
+                meta: false,
+            }),
+            Some(ShortcutAction::CommandPalette)
+        );
+    });
+}
+
+#[test]
+fn move_and_copy_notes_in_batches() {
+    run(async {
+        let other = new_session("ivan").await;
+        let elsewhere = new_notebook("Not yours").await;
+        new_session("judy").await;
+        let from = new_notebook("Inbox").await;
+        let to = new_notebook("Projects").await;
+        let first = new_note(&from, "Roof", "fix the [[Gutter]]").await;
+        let second = new_note(&from, "Gutter", "").await;
+        tag_note(first.clone(), "house".to_string()).await.unwrap();
+        let count = |notebooks: &[Notebook], id: &str| {
+            notebooks
+                .iter()
+                .find(|nb| nb.id.as_deref() == Some(id))
+                .and_then(|nb| nb.count)
+        };
+
+        // one notebook the caller can't add to fails the whole batch
+        assert!(move_notes(vec![first.clone(), second.clone()], elsewhere)
+            .await
+            .is_err());
+        assert_eq!(list_notes(&from, None).await.len(), 2);
+
+        move_notes(vec![first.clone(), second.clone()], to.clone())
+            .await
+            .unwrap();
+        let notebooks = get_notebooks().await.unwrap();
+        assert_eq!(count(&notebooks, &from), Some(0));
+        assert_eq!(count(&notebooks, &to), Some(2));
+        let moved = get_note(first.clone()).await.unwrap();
+        assert_eq!(moved.notebook, to);
+        assert_eq!(moved.version, 2);
+
+        let copies = copy_notes(vec![first.clone()], from.clone()).await.unwrap();
+        assert_eq!(copies.len(), 1);
+        let copy = get_note(copies[0].clone()).await.unwrap();
+        assert_eq!(copy.notebook, from);
+        assert_eq!(copy.title, "Roof");
+        assert_eq!(copy.version, 1);
+        assert_eq!(
+            get_note_tags(copies[0].clone()).await.unwrap()[0].name,
+            "house"
+        );
+        assert_eq!(get_tags().await.unwrap()[0].count, Some(2));
+        let notebooks = get_notebooks().await.unwrap();
+        assert_eq!(count(&notebooks, &from), Some(1));
+        assert_eq!(count(&notebooks, &to), Some(2));
+
+        other.enter();
+        assert!(copy_notes(vec![second], from).await.is_err());
+    });
+}
+
+#[test]
+fn nest_notebooks_without_cycles() {
+    run(async {
+        new_session("kim").await;
+        let work = new_notebook("Work").await;
+        let clients = new_notebook("Clients").await;
+        let acme = new_notebook("Acme").await;
+        set_notebook_parent(clients.clone(), Some(work.clone()))
+            .await
+            .unwrap();
+        set_notebook_parent(acme.clone(), Some(clients.clone()))
+            .await
+            .unwrap();
+
+        for parent in [acme.clone(), work.clone()] {
+            let res = set_notebook_parent(work.clone(), Some(parent)).await;
+            assert!(matches!(notes_error(res), NotesError::Validation(_)));
+        }
+
+        new_note(&work, "Goals", "").await;
+        new_note(&acme, "Contract", "").await;
+        new_note(&acme, "Invoice", "").await;
+        let notebooks = get_notebooks().await.unwrap();
+        let find = |id: &str| {
+            notebooks
+                .iter()
+                .find(|nb| nb.id.as_deref() == Some(id))
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(find(&work).count, Some(1));
+        assert_eq!(find(&work).total, Some(3));
+        assert_eq!(find(&clients).total, Some(2));
+        assert_eq!(find(&acme).parent.as_ref(), Some(&clients));
+
+        let rows = tree_rows(&notebooks, &HashSet::new());
+        let layout: Vec<(String, usize)> = rows
+            .iter()
+            .map(|row| (row.notebook.name.clone(), row.depth))
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                ("Work".to_string(), 0),
+                ("Clients".to_string(), 1),
+                ("Acme".to_string(), 2)
+            ]
+        );
+        let collapsed = HashSet::from([clients.clone()]);
+        assert_eq!(tree_rows(&notebooks, &collapsed).len(), 2);
+
+        let summaries = |include_children| {
+            get_note_summaries(
+                Some(work.clone()),
+                include_children,
+                None,
+                NoteSort::Title,
+                None,
+                NOTE_PAGE_SIZE,
+            )
+        };
+        assert_eq!(summaries(false).await.unwrap().notes.len(), 1);
+        assert_eq!(summaries(true).await.unwrap().notes.len(), 3);
+
+        set_notebook_parent(acme.clone(), None).await.unwrap();
+        let notebooks = get_notebooks().await.unwrap();
+        let acme = notebooks
+            .iter()
+            .find(|nb| nb.id.as_ref() == Some(&acme))
+            .unwrap();
+        assert_eq!(acme.parent, None);
+        assert_eq!(summaries(true).await.unwrap().notes.len(), 1);
+    });
+}
+
+#[test]
+fn note_templates_per_user() {
+    run(async {
+        new_session("lou").await;
+        let meeting = Template {
+            id: None,
+            name: "Meeting".to_string(),
+            title: "{{notebook}} meeting {{ date }}".to_string(),
+            content: "## Attendees\n\n## Notes\n\nStarted {{time}}, see {{agenda}}".to_string(),
+        };
+        let id = upsert_template(meeting.clone()).await.unwrap();
+        let res = upsert_template(Template {
+            name: "  ".to_string(),
+            ..Template::new()
+        })
+        .await;
+        assert!(matches!(notes_error(res), NotesError::Validation(_)));
+
+        upsert_template(Template {
+            id: Some(id.clone()),
+            name: "Weekly meeting".to_string(),
+            ..meeting
+        })
+        .await
+        .unwrap();
+        let templates = get_templates().await.unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].name, "Weekly meeting");
+
+        let vars = TemplateVars {
+            date: "2024-05-01".to_string(),
+            time: "09:30".to_string(),
+            notebook: "Work".to_string(),
+        };
+        let (title, content) = templates[0].instantiate(&vars);
+        assert_eq!(title, "Work meeting 2024-05-01");
+        assert_eq!(
+            content,
+            "## Attendees\n\n## Notes\n\nStarted 09:30, see {{agenda}}"
+        );
+        assert_eq!(fill_template("{{date", &vars), "{{date");
+
+        // owning the template doesn't make it pass for a notebook
+        let res = upsert_notebook(Notebook {
+            id: Some(id.clone()),
+            name: "Renamed".to_string(),
+            count: None,
+            role: None,
+            parent: None,
+            total: None,
+        })
+        .await;
+        assert!(matches!(notes_error(res), NotesError::NotFound(_)));
+
+        // nor does a note pass for a template
+        let notebook = new_notebook("Work").await;
+        let note = new_note(&notebook, "Standup", "yesterday, today").await;
+        let res = upsert_template(Template {
+            id: Some(note.clone()),
+            name: "Standup".to_string(),
+            ..Template::new()
+        })
+        .await;
+        assert!(matches!(notes_error(res), NotesError::NotFound(_)));
+        assert!(matches!(
+            notes_error(delete_template(note.clone()).await),
+            NotesError::NotFound(_)
+        ));
+        let kept = get_note(note).await.unwrap();
+        assert_eq!(kept.title, "Standup");
+        assert_eq!(kept.content, "yesterday, today");
+
+        new_session("max").await;
+        assert!(get_templates().await.unwrap().is_empty());
+        let res = upsert_template(Template {
+            id: Some(id.clone()),
+            ..Template::new()
+        })
+        .await;
+        assert!(matches!(notes_error(res), NotesError::NotFound(_)));
+        assert!(matches!(
+            notes_error(delete_template(id.clone()).await),
+            NotesError::NotFound(_)
+        ));
+    });
+}
+
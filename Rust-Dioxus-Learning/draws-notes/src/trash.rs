@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use surrealdb::sql::Thing;
+
+use crate::{
+    attachments, config, ATTACHMENT_TABLE, DB, NOTEBOOK_TABLE, NOTE_EMBEDDING_TABLE, NOTE_TABLE,
+    SHARE_TABLE,
+};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Starts purging whatever has been in the trash longer than the configured retention, once
+/// at startup and then every hour.
+pub fn start() {
+    let retention = config::trash_retention().expect("invalid trash config");
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = purge_expired(retention).await {
+                log::error!("error purging the trash {:?}", e);
+            }
+            tokio::time::sleep(PURGE_INTERVAL).await;
+        }
+    });
+}
+
+pub async fn purge_expired(retention: Duration) -> surrealdb::Result<()> {
+    let con = DB.get().await;
+    let mut res = con
+        .query("SELECT VALUE id FROM type::table($notebook_table) WHERE deleted_at IS NOT NONE AND deleted_at < time::now() - $retention;")
+        .query("SELECT VALUE id FROM type::table($note_table) WHERE deleted_at IS NOT NONE AND deleted_at < time::now() - $retention;")
+        .bind(("notebook_table", NOTEBOOK_TABLE))
+        .bind(("note_table", NOTE_TABLE))
+        .bind(("retention", surrealdb::sql::Duration::from(retention)))
+        .await?;
+    let notebooks: Vec<Thing> = res.take(0)?;
+    let notes: Vec<Thing> = res.take(1)?;
+
+    for notebook in notebooks {
+        purge_notebook(notebook).await?;
+    }
+    for note in notes {
+        purge_note(note).await?;
+    }
+    Ok(())
+}
+
+/// Deletes a note for good, with its attachments and embedding.
+pub async fn purge_note(note: Thing) -> surrealdb::Result<()> {
+    let con = DB.get().await;
+    let hashes: Vec<String> = con
+        .query("SELECT VALUE hash FROM type::table($table) WHERE note = $note;")
+        .bind(("table", ATTACHMENT_TABLE))
+        .bind(("note", note.clone()))
+        .await?
+        .take(0)?;
+
+    con.query("BEGIN TRANSACTION;")
+        .query("DELETE type::table($table) WHERE note = $note;")
+        .query("DELETE type::table($embedding_table) WHERE note = $note;")
+        .query("DELETE $note;")
+        .query("COMMIT TRANSACTION;")
+        .bind(("table", ATTACHMENT_TABLE))
+        .bind(("embedding_table", NOTE_EMBEDDING_TABLE))
+        .bind(("note", note))
+        .await?
+        .check()?;
+
+    // the same file can be attached to other notes too, those keep it
+    attachments::remove_orphans(hashes).await
+}
+
+/// Deletes a notebook for good, with every note in it, their attachments and embeddings and
+/// the shares. The notebooks nested in it move to the top level.
+pub async fn purge_notebook(notebook: Thing) -> surrealdb::Result<()> {
+    let con = DB.get().await;
+    let hashes: Vec<String> = con
+        .query("SELECT VALUE hash FROM type::table($table) WHERE note.notebook = $notebook;")
+        .bind(("table", ATTACHMENT_TABLE))
+        .bind(("notebook", notebook.clone()))
+        .await?
+        .take(0)?;
+
+    con.query("BEGIN TRANSACTION;")
+        .query("DELETE type::table($attachment_table) WHERE note.notebook = $notebook;")
+        .query("DELETE type::table($embedding_table) WHERE note.notebook = $notebook;")
+        .query("DELETE type::table($note_table) WHERE notebook = $notebook;")
+        .query("DELETE type::table($share_table) WHERE notebook = $notebook;")
+        .query("UPDATE type::table($notebook_table) SET parent = NONE WHERE parent = $notebook;")
+        .query("DELETE $notebook;")
+        .query("COMMIT TRANSACTION;")
+        .bind(("attachment_table", ATTACHMENT_TABLE))
+        .bind(("embedding_table", NOTE_EMBEDDING_TABLE))
+        .bind(("note_table", NOTE_TABLE))
+        .bind(("share_table", SHARE_TABLE))
+        .bind(("notebook_table", NOTEBOOK_TABLE))
+        .bind(("notebook", notebook))
+        .await?
+        .check()?;
+
+    attachments::remove_orphans(hashes).await
+}
@@ -0,0 +1,348 @@
+//! Items fetched from the api, shared by every view that shows them. Stories go stale sooner
+//! than comments since their score and comment count keep moving. Asking for an item that's
+//! already being fetched waits on that request instead of sending another one.
+
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use chrono::{DateTime, Duration, Utc};
+use futures::{
+    future::{LocalBoxFuture, Shared},
+    FutureExt,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Comment, StoryItem, BASE_API_URL, ITEM_API};
+
+const STORY_TTL_SECS: i64 = 5 * 60;
+const COMMENT_TTL_SECS: i64 = 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    Story,
+    Comment,
+}
+
+/// An item as the api returned it, comments without their `sub_comments` filled in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CachedItem {
+    Story(StoryItem),
+    Comment(Comment),
+}
+
+impl CachedItem {
+    fn ttl(&self) -> Duration {
+        match self {
+            CachedItem::Story(_) => Duration::seconds(STORY_TTL_SECS),
+            CachedItem::Comment(_) => Duration::seconds(COMMENT_TTL_SECS),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Entry {
+    item: CachedItem,
+    fetched_at: DateTime<Utc>,
+}
+
+impl Entry {
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now - self.fetched_at < self.item.ttl()
+    }
+}
+
+/// Cloneable so every caller waiting on the same request can get the result.
+#[derive(Clone, Debug)]
+pub enum FetchError {
+    Request(Rc<reqwest::Error>),
+    /// the id belongs to a different kind of item than the one asked for
+    WrongKind(i64),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        FetchError::Request(Rc::new(err))
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Request(err) => write!(f, "{err}"),
+            FetchError::WrongKind(id) => write!(f, "item {id} isn't the kind of item expected"),
+        }
+    }
+}
+
+type PendingItem = Shared<LocalBoxFuture<'static, Result<CachedItem, FetchError>>>;
+
+#[derive(Default)]
+struct ItemCache {
+    entries: HashMap<i64, Entry>,
+    // by kind too, so a request for the wrong kind of item doesn't answer the right one
+    pending: HashMap<(i64, ItemKind), PendingItem>,
+}
+
+// everything runs on the browser's one thread
+thread_local! {
+    static CACHE: RefCell<ItemCache> = RefCell::new(ItemCache::default());
+}
+
+enum Lookup {
+    Hit(CachedItem),
+    Pending(PendingItem),
+}
+
+pub async fn get_item(id: i64, kind: ItemKind) -> Result<CachedItem, FetchError> {
+    let lookup = CACHE.with(|cache| lookup(&mut cache.borrow_mut(), id, kind, Utc::now()));
+
+    match lookup {
+        Lookup::Hit(item) => Ok(item),
+        Lookup::Pending(pending) => pending.await,
+    }
+}
+
+pub async fn get_story_item(id: i64) -> Result<StoryItem, FetchError> {
+    match get_item(id, ItemKind::Story).await? {
+        CachedItem::Story(story) => Ok(story),
+        CachedItem::Comment(_) => Err(FetchError::WrongKind(id)),
+    }
+}
+
+pub async fn get_comment_item(id: i64) -> Result<Comment, FetchError> {
+    match get_item(id, ItemKind::Comment).await? {
+        CachedItem::Comment(comment) => Ok(comment),
+        CachedItem::Story(_) => Err(FetchError::WrongKind(id)),
+    }
+}
+
+fn lookup(cache: &mut ItemCache, id: i64, kind: ItemKind, now: DateTime<Utc>) -> Lookup {
+    if let Some(entry) = cache.entries.get(&id).filter(|entry| entry.is_fresh(now)) {
+        return Lookup::Hit(entry.item.clone());
+    }
+    // about to be fetched again, and kept only if that succeeds
+    cache.entries.remove(&id);
+    match storage::load(id) {
+        Some(entry) if entry.is_fresh(now) => {
+            let item = entry.item.clone();
+            cache.entries.insert(id, entry);
+            return Lookup::Hit(item);
+        }
+        Some(_) => storage::remove(id),
+        None => {}
+    }
+    Lookup::Pending(
+        cache
+            .pending
+            .entry((id, kind))
+            .or_insert_with(|| fetch(id, kind).boxed_local().shared())
+            .clone(),
+    )
+}
+
+/// Runs once per request however many callers are waiting on it, so this is where the result
+/// gets stored.
+async fn fetch(id: i64, kind: ItemKind) -> Result<CachedItem, FetchError> {
+    let res = request(id, kind).await;
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        cache.pending.remove(&(id, kind));
+        if let Ok(item) = &res {
+            let entry = Entry {
+                item: item.clone(),
+                fetched_at: Utc::now(),
+            };
+            storage::save(id, &entry);
+            cache.entries.insert(id, entry);
+        }
+    });
+    res
+}
+
+async fn request(id: i64, kind: ItemKind) -> Result<CachedItem, FetchError> {
+    let url = format!("{}{}{}.json", BASE_API_URL, ITEM_API, id);
+    let response = reqwest::get(&url).await?;
+    Ok(match kind {
+        ItemKind::Story => CachedItem::Story(response.json().await?),
+        ItemKind::Comment => CachedItem::Comment(response.json().await?),
+    })
+}
+
+/// Keeps items in the browser's local storage so they outlive a reload, one key per item. At
+/// most `MAX_PERSISTED` are kept, stale ones are the first to go and then the oldest.
+#[cfg(feature = "persist-cache")]
+mod storage {
+    use chrono::Utc;
+
+    use super::Entry;
+
+    const KEY_PREFIX: &str = "hn-item-";
+    const MAX_PERSISTED: usize = 500;
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn load(id: i64) -> Option<Entry> {
+        let json = local_storage()?
+            .get_item(&format!("{KEY_PREFIX}{id}"))
+            .ok()??;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn save(id: i64, entry: &Entry) {
+        let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(entry)) else {
+            return;
+        };
+        prune(&storage, &format!("{KEY_PREFIX}{id}"));
+        // a full quota only means this one is fetched again after a reload
+        if let Err(err) = storage.set_item(&format!("{KEY_PREFIX}{id}"), &json) {
+            log::warn!("couldn't persist item {id}: {err:?}");
+        }
+    }
+
+    pub fn remove(id: i64) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(&format!("{KEY_PREFIX}{id}"));
+        }
+    }
+
+    /// Makes room for `incoming` if the cap is reached. Only reads the entries back then, so
+    /// most saves just count keys.
+    fn prune(storage: &web_sys::Storage, incoming: &str) {
+        let len = storage.length().unwrap_or(0);
+        let keys: Vec<String> = (0..len)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter(|key| key.starts_with(KEY_PREFIX) && key != incoming)
+            .collect();
+        if keys.len() < MAX_PERSISTED {
+            return;
+        }
+
+        let now = Utc::now();
+        // unreadable entries sort first along with the stale ones
+        let mut entries: Vec<(String, Option<Entry>)> = keys
+            .into_iter()
+            .map(|key| {
+                let entry = storage
+                    .get_item(&key)
+                    .ok()
+                    .flatten()
+                    .and_then(|json| serde_json::from_str::<Entry>(&json).ok());
+                (key, entry)
+            })
+            .collect();
+        entries.sort_by_key(|(_, entry)| {
+            entry
+                .as_ref()
+                .map(|entry| (entry.is_fresh(now), entry.fetched_at))
+        });
+
+        let excess = entries.len() + 1 - MAX_PERSISTED;
+        let stale = entries
+            .iter()
+            .take_while(|(_, entry)| !entry.as_ref().is_some_and(|entry| entry.is_fresh(now)))
+            .count();
+        for (key, _) in entries.iter().take(excess.max(stale)) {
+            let _ = storage.remove_item(key);
+        }
+    }
+}
+
+#[cfg(not(feature = "persist-cache"))]
+mod storage {
+    use super::Entry;
+
+    pub fn load(_id: i64) -> Option<Entry> {
+        None
+    }
+
+    pub fn save(_id: i64, _entry: &Entry) {}
+
+    pub fn remove(_id: i64) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story() -> CachedItem {
+        CachedItem::Story(StoryItem {
+            id: 1,
+            title: "A story".to_string(),
+            url: None,
+            text: None,
+            by: "someone".to_string(),
+            score: 1,
+            descendants: 0,
+            time: Utc::now(),
+            kids: vec![],
+            r#type: "story".to_string(),
+        })
+    }
+
+    fn comment() -> CachedItem {
+        CachedItem::Comment(Comment {
+            id: 2,
+            by: "someone".to_string(),
+            text: "A comment".to_string(),
+            time: Utc::now(),
+            kids: vec![],
+            sub_comments: vec![],
+            r#type: "comment".to_string(),
+        })
+    }
+
+    fn fetched_ago(item: CachedItem, minutes: i64, now: DateTime<Utc>) -> Entry {
+        Entry {
+            item,
+            fetched_at: now - Duration::minutes(minutes),
+        }
+    }
+
+    #[test]
+    fn ttl_per_kind() {
+        assert_eq!(story().ttl(), Duration::minutes(5));
+        assert_eq!(comment().ttl(), Duration::hours(1));
+    }
+
+    #[test]
+    fn stories_go_stale_after_five_minutes() {
+        let now = Utc::now();
+        assert!(fetched_ago(story(), 0, now).is_fresh(now));
+        assert!(fetched_ago(story(), 4, now).is_fresh(now));
+        assert!(!fetched_ago(story(), 5, now).is_fresh(now));
+        assert!(!fetched_ago(story(), 6, now).is_fresh(now));
+    }
+
+    #[test]
+    fn comments_go_stale_after_an_hour() {
+        let now = Utc::now();
+        assert!(fetched_ago(comment(), 6, now).is_fresh(now));
+        assert!(fetched_ago(comment(), 59, now).is_fresh(now));
+        assert!(!fetched_ago(comment(), 60, now).is_fresh(now));
+        assert!(!fetched_ago(comment(), 61, now).is_fresh(now));
+    }
+
+    #[test]
+    fn lookup_drops_stale_entries_and_fetches_per_kind() {
+        let now = Utc::now();
+        let mut cache = ItemCache::default();
+        cache.entries.insert(1, fetched_ago(story(), 1, now));
+        assert!(matches!(
+            lookup(&mut cache, 1, ItemKind::Story, now),
+            Lookup::Hit(CachedItem::Story(_))
+        ));
+
+        cache.entries.insert(1, fetched_ago(story(), 10, now));
+        assert!(matches!(
+            lookup(&mut cache, 1, ItemKind::Story, now),
+            Lookup::Pending(_)
+        ));
+        assert!(cache.entries.is_empty());
+
+        // the same id asked for as a comment is its own request
+        lookup(&mut cache, 1, ItemKind::Story, now);
+        lookup(&mut cache, 1, ItemKind::Comment, now);
+        assert_eq!(cache.pending.len(), 2);
+    }
+}
@@ -1,5 +1,8 @@
 #![allow(non_snake_case)]
 
+mod cache;
+
+use cache::FetchError;
 use dioxus::prelude::*;
 // use log::LevelFilter;
 
@@ -34,19 +37,11 @@ fn Stories() -> Element {
     }
 }
 
-async fn resolve_story(
-    mut full_story: Signal<Option<StoryPageData>>,
-    mut preview_state: Signal<PreviewState>,
-    story_id: i64,
-) {
-    if let Some(cached) = full_story.as_ref() {
-        *preview_state.write() = PreviewState::Loaded(cached.clone());
-        return;
-    }
+// hovering the same story again is answered from the item cache until it goes stale
+async fn resolve_story(mut preview_state: Signal<PreviewState>, story_id: i64) {
     *preview_state.write() = PreviewState::Loading;
     if let Ok(story) = get_story(story_id).await {
-        *preview_state.write() = PreviewState::Loaded(story.clone());
-        *full_story.write() = Some(story);
+        *preview_state.write() = PreviewState::Loaded(story);
     }
 }
 
@@ -63,7 +58,6 @@ fn StoryListing(story: ReadOnlySignal<StoryItem>) -> Element {
         id,
         ..
     } = story();
-    let full_story = use_signal(|| None);
     let url = url.as_deref().unwrap_or_default();
     let hostname = url
         .trim_start_matches("https://")
@@ -89,11 +83,11 @@ fn StoryListing(story: ReadOnlySignal<StoryItem>) -> Element {
      div {
          padding: "0.5rem",
          position: "relative",
-         onmouseenter: move |_event| { resolve_story(full_story, preview_state, id) },
+         onmouseenter: move |_event| { resolve_story(preview_state, id) },
          div { font_size: "1.5rem",
              a {
                  href: url,
-                 onfocus: move |_event| { resolve_story(full_story, preview_state, id) },
+                 onfocus: move |_event| { resolve_story(preview_state, id) },
                  "{title}"
              }
              a {
@@ -162,12 +156,11 @@ pub static ITEM_API: &str = "item/";
 pub static USER_API: &str = "user/";
 const COMMENT_DEPTH: i64 = 2;
 
-pub async fn get_story_preview(id: i64) -> Result<StoryItem, reqwest::Error> {
-    let url = format!("{}{}{}.json", BASE_API_URL, ITEM_API, id);
-    reqwest::get(&url).await?.json().await
+pub async fn get_story_preview(id: i64) -> Result<StoryItem, FetchError> {
+    cache::get_story_item(id).await
 }
 
-pub async fn get_stories(count: usize) -> Result<Vec<StoryItem>, reqwest::Error> {
+pub async fn get_stories(count: usize) -> Result<Vec<StoryItem>, FetchError> {
     let url = format!("{}topstories.json", BASE_API_URL);
     let stories_ids = &reqwest::get(&url).await?.json::<Vec<i64>>().await?[..count];
 
@@ -224,24 +217,21 @@ pub struct StoryItem {
     pub kids: Vec<i64>,
     pub r#type: String,
 }
-pub async fn get_story(id: i64) -> Result<StoryPageData, reqwest::Error> {
-    let url = format!("{}{}{}.json", BASE_API_URL, ITEM_API, id);
-    let mut story = reqwest::get(&url).await?.json::<StoryPageData>().await?;
-    let comment_futures = story.item.kids.iter().map(|&id| get_comment(id));
+pub async fn get_story(id: i64) -> Result<StoryPageData, FetchError> {
+    let item = get_story_preview(id).await?;
+    let comment_futures = item.kids.iter().map(|&id| get_comment(id));
     let comments = join_all(comment_futures)
         .await
         .into_iter()
         .filter_map(|c| c.ok())
         .collect();
 
-    story.comments = comments;
-    Ok(story)
+    Ok(StoryPageData { item, comments })
 }
 
 #[async_recursion::async_recursion(?Send)]
-pub async fn get_comment_with_depth(id: i64, depth: i64) -> Result<Comment, reqwest::Error> {
-    let url = format!("{}{}{}.json", BASE_API_URL, ITEM_API, id);
-    let mut comment = reqwest::get(&url).await?.json::<Comment>().await?;
+pub async fn get_comment_with_depth(id: i64, depth: i64) -> Result<Comment, FetchError> {
+    let mut comment = cache::get_comment_item(id).await?;
     if depth > 0 {
         let sub_comments_futures = comment
             .kids
@@ -256,6 +246,6 @@ pub async fn get_comment_with_depth(id: i64, depth: i64) -> Result<Comment, reqw
     Ok(comment)
 }
 
-pub async fn get_comment(comment_id: i64) -> Result<Comment, reqwest::Error> {
+pub async fn get_comment(comment_id: i64) -> Result<Comment, FetchError> {
     get_comment_with_depth(comment_id, COMMENT_DEPTH).await
 }